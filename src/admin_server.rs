@@ -0,0 +1,94 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post},
+};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use crate::config::AdminConfig;
+use crate::transform_layer::hls_convertor::{HlsConvertor, StreamInfo};
+use crate::utils::bearer_token::has_bearer_token;
+
+/// 송출 중인 스트림을 조회하고 강제로 종료하는 관리자 API.
+/// 모든 요청은 "Authorization: Bearer <admin.token>" 헤더가 있어야 한다.
+pub struct AdminServer {
+    hls_convertor: Arc<HlsConvertor>,
+    token: String,
+}
+
+async fn require_token(
+    State(server): State<Arc<AdminServer>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if !has_bearer_token(request.headers(), &server.token) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(next.run(request).await)
+}
+
+async fn list_streams(State(server): State<Arc<AdminServer>>) -> Json<Vec<StreamInfo>> {
+    Json(server.hls_convertor.streams())
+}
+
+async fn get_stream(
+    State(server): State<Arc<AdminServer>>,
+    Path(stream_id): Path<u32>,
+) -> Result<Json<StreamInfo>, StatusCode> {
+    server.hls_convertor.stream(stream_id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// 파이프라인을 멈추고 송출자의 RTMP 세션을 바로 닫는다.
+async fn kick_stream(
+    State(server): State<Arc<AdminServer>>,
+    Path(stream_id): Path<u32>,
+) -> StatusCode {
+    if server.hls_convertor.kick_stream(stream_id) {
+        tracing::warn!(stream_id, "Stream kicked by admin");
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+pub async fn start_admin_server(
+    hls_convertor: Arc<HlsConvertor>,
+    config: &AdminConfig,
+    token: String,
+    shutdown: CancellationToken,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let server = Arc::new(AdminServer { hls_convertor, token });
+    let app = Router::new()
+        .route("/admin/streams", get(list_streams))
+        .route("/admin/streams/{stream_id}", get(get_stream))
+        .route("/admin/streams/{stream_id}/kick", post(kick_stream))
+        .layer(middleware::from_fn_with_state(server.clone(), require_token))
+        .with_state(server);
+
+    let listener = tokio::net::TcpListener::bind(&config.bind).await?;
+    tracing::info!(bind = %config.bind, "Admin API listening");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await?;
+    Ok(())
+}
+
+/// admin.token 이 없으면 관리자 API 를 열지 않는다.
+pub fn start_admin_server_background(
+    hls_convertor: Arc<HlsConvertor>,
+    config: &'static AdminConfig,
+    shutdown: CancellationToken,
+) {
+    let Some(token) = config.token.clone() else {
+        tracing::info!("Admin API disabled (admin.token is not set)");
+        return;
+    };
+    tokio::spawn(async move {
+        if let Err(e) = start_admin_server(hls_convertor, config, token, shutdown).await {
+            tracing::error!(error = %e, "Admin server error");
+        }
+    });
+}
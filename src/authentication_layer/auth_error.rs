@@ -0,0 +1,53 @@
+use std::fmt;
+
+/// 스트림 키 인증 실패 원인
+#[derive(Debug)]
+pub enum AuthError {
+    /// 인증 서버가 스트림 키를 거절했다.
+    Rejected(String),
+    /// 인증 서버에 연결할 수 없거나 서버 오류가 났다.
+    Unreachable(String),
+    Timeout,
+    /// 응답 본문을 해석할 수 없다.
+    MalformedResponse(String),
+}
+
+impl AuthError {
+    /// 메트릭 라벨에 쓰는 짧은 이름
+    pub fn kind(&self) -> &'static str {
+        match self {
+            AuthError::Rejected(_) => "rejected",
+            AuthError::Unreachable(_) => "unreachable",
+            AuthError::Timeout => "timeout",
+            AuthError::MalformedResponse(_) => "malformed",
+        }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthError::Rejected(reason) => write!(f, "stream key rejected: {}", reason),
+            AuthError::Unreachable(reason) => write!(f, "authentication server unreachable: {}", reason),
+            AuthError::Timeout => write!(f, "authentication request timed out"),
+            AuthError::MalformedResponse(reason) => write!(f, "malformed authentication response: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+impl From<reqwest::Error> for AuthError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            AuthError::Timeout
+        } else if e.is_builder() {
+            // 요청을 만들 수 없으면 다시 시도해도 같으므로 재시도하지 않는 오류로 본다.
+            AuthError::MalformedResponse(e.to_string())
+        } else if e.is_decode() {
+            AuthError::MalformedResponse(e.to_string())
+        } else {
+            AuthError::Unreachable(e.to_string())
+        }
+    }
+}
@@ -1,15 +1,80 @@
-use reqwest::Client;
+use std::error::Error;
+use std::time::Duration;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::{Client, Method, Url};
+use crate::authentication_layer::auth_error::AuthError;
 use crate::authentication_layer::authentication_request::response::{BaseStreamUserResponse};
+use crate::config::{self, AuthConfig, AuthMethod};
 
-pub async fn get_authentication(stream_key: &str, client: &Client) -> Result<BaseStreamUserResponse, String> {
-    let data = client
-        .post("http://localhost:8080/stream")
-        .header("X-Stream-Key", stream_key)
-        .send().await
-        .unwrap();
-    if data.status().is_success() {
-        Ok(data.json().await.unwrap())
-    } else {
-        Err("stream key is not allowed".to_string())
+/// 연결 실패와 시간 초과는 auth.retries 만큼 다시 시도한다.
+pub async fn get_authentication(stream_key: &str, client: &Client) -> Result<BaseStreamUserResponse, AuthError> {
+    let auth_config = &config::get_config().auth;
+    let mut attempt = 0;
+    loop {
+        match request_authentication(stream_key, client, auth_config).await {
+            Err(AuthError::Unreachable(_) | AuthError::Timeout) if attempt < auth_config.retries => {
+                attempt += 1;
+                tokio::time::sleep(Duration::from_millis(auth_config.retry_backoff)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// 주소나 헤더가 잘못되었으면 첫 송출 때가 아니라 시작할 때 알린다.
+pub fn validate_auth_config(auth_config: &AuthConfig) -> Result<(), Box<dyn Error>> {
+    Url::parse(&auth_config.url).map_err(|e| format!("invalid auth.url {:?}: {}", auth_config.url, e))?;
+    HeaderName::from_bytes(auth_config.header.as_bytes())
+        .map_err(|e| format!("invalid auth.header {:?}: {}", auth_config.header, e))?;
+    for (name, value) in &auth_config.headers {
+        HeaderName::from_bytes(name.as_bytes()).map_err(|e| format!("invalid auth.headers name {:?}: {}", name, e))?;
+        HeaderValue::from_str(value).map_err(|e| format!("invalid auth.headers value for {:?}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+async fn request_authentication(stream_key: &str, client: &Client, auth_config: &AuthConfig) -> Result<BaseStreamUserResponse, AuthError> {
+    let method = match auth_config.method {
+        AuthMethod::Get => Method::GET,
+        AuthMethod::Post => Method::POST,
+    };
+    // 헤더에 넣을 수 없는 키는 인증 서버에 물을 것도 없이 거절한다.
+    let stream_key = HeaderValue::from_str(stream_key)
+        .map_err(|_| AuthError::Rejected("stream key is not a valid header value".to_string()))?;
+    let mut request = client
+        .request(method, &auth_config.url)
+        .timeout(Duration::from_millis(auth_config.timeout))
+        .header(&auth_config.header, stream_key);
+    for (name, value) in &auth_config.headers {
+        request = request.header(name, value);
+    }
+
+    let data = request.send().await?;
+    match data.status() {
+        status if status.is_success() => Ok(data.json().await?),
+        status if status.is_client_error() => {
+            Err(AuthError::Rejected(format!("stream key is not allowed ({})", status)))
+        }
+        status => Err(AuthError::Unreachable(format!("authentication server returned {}", status))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_invalid_headers() {
+        assert!(validate_auth_config(&AuthConfig::default()).is_ok());
+
+        let config = AuthConfig { header: "X Stream Key".to_string(), ..AuthConfig::default() };
+        assert!(validate_auth_config(&config).is_err());
+
+        let mut config = AuthConfig::default();
+        config.headers.insert("Authorization".to_string(), "Bearer token\n".to_string());
+        assert!(validate_auth_config(&config).is_err());
+
+        let config = AuthConfig { url: "localhost/stream".to_string(), ..AuthConfig::default() };
+        assert!(validate_auth_config(&config).is_err());
     }
 }
\ No newline at end of file
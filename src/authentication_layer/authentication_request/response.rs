@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use crate::config::RelayTargetConfig;
 
 #[derive(Deserialize, Debug)]
 pub struct BaseStreamUserResponse {
@@ -15,7 +16,11 @@ pub struct BaseStreamUserResponse {
 pub struct StreamUserResponse {
     nickname: String,
     #[serde(rename = "createdAt")]
-    created_at: String
+    created_at: String,
+    #[serde(rename = "relayTargets", default)]
+    relay_targets: Vec<RelayTargetConfig>,
+    #[serde(default)]
+    record: Option<bool>,
 }
 
 impl StreamUserResponse {
@@ -26,4 +31,12 @@ impl StreamUserResponse {
     pub fn get_start_time(&self) -> String {
         self.created_at.clone()
     }
+
+    pub fn get_relay_targets(&self) -> Vec<RelayTargetConfig> {
+        self.relay_targets.clone()
+    }
+
+    pub fn get_record(&self) -> Option<bool> {
+        self.record
+    }
 }
\ No newline at end of file
@@ -0,0 +1,67 @@
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use reqwest::Client;
+use crate::authentication_layer::auth_error::AuthError;
+use crate::authentication_layer::http_authenticator::HttpAuthenticator;
+use crate::authentication_layer::jwt_authenticator::JwtAuthenticator;
+use crate::authentication_layer::static_authenticator::StaticAuthenticator;
+use crate::config::{AuthKind, Config, RelayTargetConfig};
+
+pub type AuthFuture<'a> = Pin<Box<dyn Future<Output = Result<AuthedStream, AuthError>> + Send + 'a>>;
+
+/// 인증을 통과한 송출
+pub struct AuthedStream {
+    /// "닉네임/시작시각" 형태의 스트림 경로
+    pub path: String,
+    /// 이 스트림만 다시 송출할 대상. [relay] 의 대상에 더해진다.
+    pub relay_targets: Vec<RelayTargetConfig>,
+    /// 있으면 [recording] enabled 대신 이 값으로 녹화 여부를 정한다.
+    pub record: Option<bool>,
+}
+
+impl AuthedStream {
+    pub fn new(path: String) -> Self {
+        Self { path, relay_targets: Vec::new(), record: None }
+    }
+
+    pub fn with_relay_targets(mut self, relay_targets: Vec<RelayTargetConfig>) -> Self {
+        self.relay_targets = relay_targets;
+        self
+    }
+
+    pub fn with_record(mut self, record: Option<bool>) -> Self {
+        self.record = record;
+        self
+    }
+}
+
+/// 스트림 키를 검증하고 스트림 경로와 송출 설정을 돌려준다.
+pub trait Authenticator: Send + Sync {
+    fn authenticate<'a>(&'a self, stream_key: &'a str) -> AuthFuture<'a>;
+}
+
+pub fn create_authenticator(config: &Config, client: Arc<Client>) -> Result<Arc<dyn Authenticator>, Box<dyn Error>> {
+    match config.auth.kind {
+        AuthKind::Http => Ok(Arc::new(HttpAuthenticator::new(client, &config.auth)?)),
+        AuthKind::Static => {
+            let file = config.auth.file.as_ref().ok_or("auth.file is required for kind = \"static\"")?;
+            Ok(Arc::new(StaticAuthenticator::load(file)?))
+        }
+        AuthKind::Jwt => {
+            let jwt = config.auth.jwt.as_ref().ok_or("[auth.jwt] is required for kind = \"jwt\"")?;
+            Ok(Arc::new(JwtAuthenticator::new(jwt)?))
+        }
+    }
+}
+
+/// 닉네임과 시작 시각은 저장소 경로가 되므로 경로 구분자나 상대 경로를 허용하지 않는다.
+pub fn stream_path(nickname: &str, started_at: &str) -> Result<String, AuthError> {
+    for component in [nickname, started_at] {
+        if component.is_empty() || component == "." || component == ".." || component.contains(['/', '\\']) {
+            return Err(AuthError::MalformedResponse(format!("invalid stream path component: {:?}", component)));
+        }
+    }
+    Ok(format!("{}/{}", nickname, started_at))
+}
\ No newline at end of file
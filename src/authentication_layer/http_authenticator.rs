@@ -0,0 +1,30 @@
+use std::error::Error;
+use std::sync::Arc;
+use reqwest::Client;
+use crate::authentication_layer::authentication_request::api::{get_authentication, validate_auth_config};
+use crate::authentication_layer::authenticator::{stream_path, AuthFuture, AuthedStream, Authenticator};
+use crate::config::AuthConfig;
+
+/// [auth] 에 설정된 인증 서버로 스트림 키를 확인한다.
+pub struct HttpAuthenticator {
+    client: Arc<Client>,
+}
+
+impl HttpAuthenticator {
+    pub fn new(client: Arc<Client>, auth_config: &AuthConfig) -> Result<Self, Box<dyn Error>> {
+        validate_auth_config(auth_config)?;
+        Ok(Self { client })
+    }
+}
+
+impl Authenticator for HttpAuthenticator {
+    fn authenticate<'a>(&'a self, stream_key: &'a str) -> AuthFuture<'a> {
+        Box::pin(async move {
+            let response = get_authentication(stream_key, &self.client).await?.data;
+            let path = stream_path(&response.get_nickname(), &response.get_start_time())?;
+            Ok(AuthedStream::new(path)
+                .with_relay_targets(response.get_relay_targets())
+                .with_record(response.get_record()))
+        })
+    }
+}
\ No newline at end of file
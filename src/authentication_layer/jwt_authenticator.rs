@@ -0,0 +1,313 @@
+use std::error::Error;
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use ring::signature::{UnparsedPublicKey, RSA_PKCS1_2048_8192_SHA256};
+use serde_json::Value;
+use sha2::Sha256;
+use crate::authentication_layer::auth_error::AuthError;
+use crate::authentication_layer::authenticator::{stream_path, AuthFuture, AuthedStream, Authenticator};
+use crate::config::{JwtAlgorithm, JwtConfig};
+use crate::utils::time::format_rfc3339;
+
+/// 스트림 키로 받은 JWT 의 서명을 설정된 키로 검증하고 claim 에서 닉네임과 시작 시각을 꺼낸다.
+/// 인증 서버에 요청하지 않는다.
+pub struct JwtAuthenticator {
+    algorithm: JwtAlgorithm,
+    key: VerifyKey,
+    nickname_claim: String,
+    started_at_claim: String,
+}
+
+enum VerifyKey {
+    Hmac(Vec<u8>),
+    /// DER 로 인코딩된 PKCS#1 RSAPublicKey
+    Rsa(Vec<u8>),
+}
+
+impl JwtAuthenticator {
+    pub fn new(config: &JwtConfig) -> Result<Self, Box<dyn Error>> {
+        let key = match config.algorithm {
+            JwtAlgorithm::HS256 => {
+                let secret = config.secret.as_ref().ok_or("auth.jwt.secret is required for HS256")?;
+                VerifyKey::Hmac(secret.clone().into_bytes())
+            }
+            JwtAlgorithm::RS256 => {
+                let path = config.public_key.as_ref().ok_or("auth.jwt.public_key is required for RS256")?;
+                VerifyKey::Rsa(read_rsa_public_key(&fs::read_to_string(path)?)?)
+            }
+        };
+        Ok(Self {
+            algorithm: config.algorithm,
+            key,
+            nickname_claim: config.nickname_claim.clone(),
+            started_at_claim: config.started_at_claim.clone(),
+        })
+    }
+
+    fn verify(&self, token: &str) -> Result<String, AuthError> {
+        let (signing_input, signature) = token.rsplit_once('.').ok_or_else(|| rejected("not a JWT"))?;
+        let (header, payload) = signing_input.split_once('.').ok_or_else(|| rejected("not a JWT"))?;
+
+        // 설정과 다른 alg 를 받아들이면 HS256/RS256 혼동 공격이 가능하므로 정확히 일치해야 한다.
+        let header = decode_json(header)?;
+        let expected_alg = match self.algorithm {
+            JwtAlgorithm::HS256 => "HS256",
+            JwtAlgorithm::RS256 => "RS256",
+        };
+        if header.get("alg").and_then(Value::as_str) != Some(expected_alg) {
+            return Err(rejected("unexpected alg"));
+        }
+
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| rejected("invalid signature encoding"))?;
+        let valid = match &self.key {
+            VerifyKey::Hmac(secret) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+                mac.update(signing_input.as_bytes());
+                mac.verify_slice(&signature).is_ok()
+            }
+            VerifyKey::Rsa(public_key) => UnparsedPublicKey::new(&RSA_PKCS1_2048_8192_SHA256, public_key)
+                .verify(signing_input.as_bytes(), &signature)
+                .is_ok(),
+        };
+        if !valid {
+            return Err(rejected("invalid signature"));
+        }
+
+        let claims = decode_json(payload)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs();
+        if numeric_date(&claims, "exp")?.is_some_and(|exp| now >= exp) {
+            return Err(rejected("token expired"));
+        }
+        if numeric_date(&claims, "nbf")?.is_some_and(|nbf| now < nbf) {
+            return Err(rejected("token not yet valid"));
+        }
+
+        let nickname = claims.get(&self.nickname_claim)
+            .and_then(Value::as_str)
+            .ok_or_else(|| rejected(&format!("missing {} claim", self.nickname_claim)))?;
+        let started_at = match claims.get(&self.started_at_claim) {
+            Some(Value::String(started_at)) => started_at.clone(),
+            Some(Value::Number(secs)) => {
+                let secs = secs.as_u64().ok_or_else(|| rejected(&format!("invalid {} claim", self.started_at_claim)))?;
+                format_rfc3339(UNIX_EPOCH + Duration::from_secs(secs))
+            }
+            _ => return Err(rejected(&format!("missing {} claim", self.started_at_claim))),
+        };
+        stream_path(nickname, &started_at)
+    }
+}
+
+impl Authenticator for JwtAuthenticator {
+    fn authenticate<'a>(&'a self, stream_key: &'a str) -> AuthFuture<'a> {
+        Box::pin(async move { self.verify(stream_key).map(AuthedStream::new) })
+    }
+}
+
+fn rejected(reason: &str) -> AuthError {
+    AuthError::Rejected(format!("invalid token: {}", reason))
+}
+
+fn decode_json(part: &str) -> Result<Value, AuthError> {
+    let bytes = URL_SAFE_NO_PAD.decode(part).map_err(|_| rejected("invalid base64url"))?;
+    serde_json::from_slice(&bytes).map_err(|_| rejected("invalid JSON"))
+}
+
+/// 없으면 None 이다. 정수 unix 초가 아니면 만료 검사를 건너뛰지 않도록 거절한다.
+fn numeric_date(claims: &Value, name: &str) -> Result<Option<u64>, AuthError> {
+    match claims.get(name) {
+        None => Ok(None),
+        Some(value) => value.as_u64().map(Some).ok_or_else(|| rejected(&format!("invalid {} claim", name))),
+    }
+}
+
+/// "PUBLIC KEY"(SubjectPublicKeyInfo) 와 "RSA PUBLIC KEY"(PKCS#1) PEM 을 모두 받는다.
+fn read_rsa_public_key(pem: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let body: String = pem.lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let der = STANDARD.decode(body.trim())?;
+    if pem.contains("BEGIN RSA PUBLIC KEY") {
+        return Ok(der);
+    }
+    let pkcs1 = spki_to_pkcs1(&der).ok_or("auth.jwt.public_key is not an RSA public key")?;
+    Ok(pkcs1.to_vec())
+}
+
+/// SubjectPublicKeyInfo 의 BIT STRING 안에 들어있는 PKCS#1 RSAPublicKey 를 꺼낸다.
+fn spki_to_pkcs1(der: &[u8]) -> Option<&[u8]> {
+    let (_, spki) = read_der(der, 0x30)?;
+    let (rest, _algorithm) = read_der(spki, 0x30)?;
+    let (_, bits) = read_der(rest, 0x03)?;
+    bits.strip_prefix(&[0])
+}
+
+/// tag 가 일치하는 DER TLV 하나를 읽어 (남은 바이트, 내용) 을 돌려준다.
+fn read_der(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    let (&actual, rest) = input.split_first()?;
+    if actual != tag {
+        return None;
+    }
+    let (&first, rest) = rest.split_first()?;
+    let (len, rest) = if first < 0x80 {
+        (first as usize, rest)
+    } else {
+        let n = (first & 0x7f) as usize;
+        if n == 0 || n > 4 || rest.len() < n {
+            return None;
+        }
+        let len = rest[..n].iter().fold(0usize, |len, b| len << 8 | *b as usize);
+        (len, &rest[n..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((&rest[len..], &rest[..len]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SECRET: &str = "test-secret";
+
+    /// openssl 로 만든 2048 비트 RSA 키의 공개키
+    const RSA_SPKI_PEM: &str = "\
+-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAwn65I1XjEGBxvysSpjIl
+Nb0LN9AbVDz5Dd9WO1aP+zv21re/nf/YpJGAbpMsjFSLrD41kNimPseqtRdkUEo5
+SIj4zrkUyvkTWbTjf41J+ZZjukbrY7KEDg/hrMg8ZHQmmGrVlxdaKpf79qUy0hwR
+L+bKVHqdYCZhXof7sER1xg7P/nxy4Zkyj2BWHwlMg/U9Li9iC1UZ3hFrjmEOImME
+l7Ed12a75hpF1y5s0+2Exvhvmm77ROAo84nmjnUWXzW8veP6NwH9gQ/t5+Z7aHuB
+urW7CMmFeBil1h//ybQvklQXrsPrd2svA1WZIad46fS8iKW0aKRR1Zb+Kq8hnuLR
+MQIDAQAB
+-----END PUBLIC KEY-----
+";
+
+    const RSA_PKCS1_PEM: &str = "\
+-----BEGIN RSA PUBLIC KEY-----
+MIIBCgKCAQEAwn65I1XjEGBxvysSpjIlNb0LN9AbVDz5Dd9WO1aP+zv21re/nf/Y
+pJGAbpMsjFSLrD41kNimPseqtRdkUEo5SIj4zrkUyvkTWbTjf41J+ZZjukbrY7KE
+Dg/hrMg8ZHQmmGrVlxdaKpf79qUy0hwRL+bKVHqdYCZhXof7sER1xg7P/nxy4Zky
+j2BWHwlMg/U9Li9iC1UZ3hFrjmEOImMEl7Ed12a75hpF1y5s0+2Exvhvmm77ROAo
+84nmjnUWXzW8veP6NwH9gQ/t5+Z7aHuBurW7CMmFeBil1h//ybQvklQXrsPrd2sv
+A1WZIad46fS8iKW0aKRR1Zb+Kq8hnuLRMQIDAQAB
+-----END RSA PUBLIC KEY-----
+";
+
+    /// {"nickname":"pang","iat":1700000000} 을 위 키의 개인키로 서명한 토큰
+    const RS256_TOKEN: &str = "eyJhbGciOiJSUzI1NiIsInR5cCI6IkpXVCJ9.eyJuaWNrbmFtZSI6InBhbmciLCJpYXQiOjE3MDAwMDAwMDB9.\
+        imRr5CTZ8zSW0DqgROvcY0PWTxSlvdh2FO-0KtFlfsu5nGaS3RQBcIYDLHKO6NEn3uxx_H3RvfRxJ-xngUZkCdZt_DudEkJsk2GBpafvIX2_4N5jx-Pw5N5MpU1vykMhHkPkPvGN47zOO6dqUYjMwFI1LG-StwNHIzFiGsocDKMz7yZ2lBnk6CaUONiKyBWLksUOYoH1kDcSRGxuuZSPbc2oTv8FL5FDILUCB7pslFRBU_MKDOj91tuJASarWD0FSUyQtWDpDdBH05Cr5hYjp7UL8uM8ZpvUb4zHL6N8k2g4MGP_OAh3-GZx_y33NAKgCyMBS_ZbyQH1EBd6zOHwcw";
+
+    fn authenticator() -> JwtAuthenticator {
+        JwtAuthenticator::new(&JwtConfig {
+            algorithm: JwtAlgorithm::HS256,
+            secret: Some(SECRET.to_string()),
+            public_key: None,
+            nickname_claim: "nickname".to_string(),
+            started_at_claim: "iat".to_string(),
+        }).unwrap()
+    }
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn sign(header: Value, claims: Value, secret: &str) -> String {
+        let signing_input = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(signing_input.as_bytes());
+        format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()))
+    }
+
+    fn token(claims: Value) -> String {
+        sign(json!({ "alg": "HS256", "typ": "JWT" }), claims, SECRET)
+    }
+
+    fn rejection(result: Result<String, AuthError>) -> String {
+        match result {
+            Err(AuthError::Rejected(reason)) => reason,
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(path) => panic!("accepted: {}", path),
+        }
+    }
+
+    #[test]
+    fn accepts_valid_token() {
+        let token = token(json!({ "nickname": "pang", "iat": 1_700_000_000, "exp": now() + 60 }));
+        assert_eq!(authenticator().verify(&token).unwrap(), "pang/2023-11-14T22:13:20.000Z");
+    }
+
+    #[test]
+    fn rejects_expired_token() {
+        let token = token(json!({ "nickname": "pang", "iat": 1_700_000_000, "exp": now() - 1 }));
+        assert_eq!(rejection(authenticator().verify(&token)), "invalid token: token expired");
+    }
+
+    #[test]
+    fn rejects_float_exp() {
+        let token = token(json!({ "nickname": "pang", "iat": 1_700_000_000, "exp": 1.5 }));
+        assert_eq!(rejection(authenticator().verify(&token)), "invalid token: invalid exp claim");
+    }
+
+    #[test]
+    fn rejects_wrong_alg() {
+        let claims = json!({ "nickname": "pang", "iat": 1_700_000_000 });
+        let token = sign(json!({ "alg": "none" }), claims.clone(), SECRET);
+        assert_eq!(rejection(authenticator().verify(&token)), "invalid token: unexpected alg");
+        let token = sign(json!({ "alg": "RS256" }), claims, SECRET);
+        assert_eq!(rejection(authenticator().verify(&token)), "invalid token: unexpected alg");
+    }
+
+    #[test]
+    fn rejects_bad_signature() {
+        let claims = json!({ "nickname": "pang", "iat": 1_700_000_000 });
+        let token = sign(json!({ "alg": "HS256" }), claims, "other-secret");
+        assert_eq!(rejection(authenticator().verify(&token)), "invalid token: invalid signature");
+    }
+
+    #[test]
+    fn rejects_token_before_nbf() {
+        let early = token(json!({ "nickname": "pang", "iat": 1_700_000_000, "nbf": now() + 60 }));
+        assert_eq!(rejection(authenticator().verify(&early)), "invalid token: token not yet valid");
+        let valid = token(json!({ "nickname": "pang", "iat": 1_700_000_000, "nbf": now() - 1 }));
+        assert!(authenticator().verify(&valid).is_ok());
+    }
+
+    fn rsa_authenticator(pem: &str) -> JwtAuthenticator {
+        JwtAuthenticator {
+            algorithm: JwtAlgorithm::RS256,
+            key: VerifyKey::Rsa(read_rsa_public_key(pem).unwrap()),
+            nickname_claim: "nickname".to_string(),
+            started_at_claim: "iat".to_string(),
+        }
+    }
+
+    #[test]
+    fn accepts_rs256_token() {
+        assert_eq!(rsa_authenticator(RSA_SPKI_PEM).verify(RS256_TOKEN).unwrap(), "pang/2023-11-14T22:13:20.000Z");
+        assert_eq!(rsa_authenticator(RSA_PKCS1_PEM).verify(RS256_TOKEN).unwrap(), "pang/2023-11-14T22:13:20.000Z");
+    }
+
+    #[test]
+    fn reads_pkcs1_from_spki() {
+        assert_eq!(read_rsa_public_key(RSA_SPKI_PEM).unwrap(), read_rsa_public_key(RSA_PKCS1_PEM).unwrap());
+        assert!(spki_to_pkcs1(&[0x30, 0x03, 0x02, 0x01, 0x00]).is_none());
+    }
+
+    #[test]
+    fn rejects_tampered_rs256_token() {
+        let (_, signature) = RS256_TOKEN.rsplit_once('.').unwrap();
+        let claims = URL_SAFE_NO_PAD.encode(json!({ "nickname": "other", "iat": 1_700_000_000 }).to_string());
+        let header = URL_SAFE_NO_PAD.encode(json!({ "alg": "RS256", "typ": "JWT" }).to_string());
+        let token = format!("{}.{}.{}", header, claims, signature);
+        assert_eq!(rejection(rsa_authenticator(RSA_SPKI_PEM).verify(&token)), "invalid token: invalid signature");
+    }
+}
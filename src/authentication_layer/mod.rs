@@ -1,2 +1,6 @@
-pub mod auth;
-mod authentication_request;
\ No newline at end of file
+pub mod auth_error;
+pub mod authenticator;
+mod authentication_request;
+mod http_authenticator;
+mod jwt_authenticator;
+mod static_authenticator;
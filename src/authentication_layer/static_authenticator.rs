@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::time::SystemTime;
+use serde::Deserialize;
+use crate::authentication_layer::auth_error::AuthError;
+use crate::authentication_layer::authenticator::{stream_path, AuthFuture, AuthedStream, Authenticator};
+use crate::config::RelayTargetConfig;
+use crate::utils::time::format_rfc3339;
+
+/// 로컬 개발, CI 용 고정 스트림 키 목록.
+///
+/// ```toml
+/// [keys.test-key]
+/// nickname = "tester"
+/// record = true
+///
+/// [[keys.test-key.relay_targets]]
+/// name = "local"
+/// url = "rtmp://127.0.0.1:1936/live/test"
+/// ```
+///
+/// .json 파일은 {"keys": {"test-key": {"nickname": "tester"}}} 형태로 쓴다.
+pub struct StaticAuthenticator {
+    keys: HashMap<String, StaticUser>,
+}
+
+#[derive(Deserialize)]
+struct KeyFile {
+    keys: HashMap<String, StaticUser>,
+}
+
+#[derive(Deserialize)]
+struct StaticUser {
+    nickname: String,
+    /// 없으면 송출을 시작한 시각을 쓴다.
+    created_at: Option<String>,
+    #[serde(default)]
+    relay_targets: Vec<RelayTargetConfig>,
+    record: Option<bool>,
+}
+
+impl StaticAuthenticator {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let key_file: KeyFile = if path.ends_with(".json") {
+            serde_json::from_str(&content)?
+        } else {
+            toml::from_str(&content)?
+        };
+        Ok(Self { keys: key_file.keys })
+    }
+}
+
+impl Authenticator for StaticAuthenticator {
+    fn authenticate<'a>(&'a self, stream_key: &'a str) -> AuthFuture<'a> {
+        Box::pin(async move {
+            let user = self.keys.get(stream_key)
+                .ok_or_else(|| AuthError::Rejected("unknown stream key".to_string()))?;
+            // created_at 이 없으면 접속할 때마다 경로가 바뀐다. 재연결 유예 중이면 HlsConvertor 가 채널로 찾아 이어 쓴다.
+            let started_at = user.created_at.clone()
+                .unwrap_or_else(|| format_rfc3339(SystemTime::now()));
+            let path = stream_path(&user.nickname, &started_at)?;
+            Ok(AuthedStream::new(path)
+                .with_relay_targets(user.relay_targets.clone())
+                .with_record(user.record))
+        })
+    }
+}
\ No newline at end of file
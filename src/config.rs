@@ -1,11 +1,36 @@
 use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::sync::OnceLock;
 
 #[derive(Debug, Deserialize)]
 pub struct Config {
     pub server: ServerConfig,
-    pub hls: HlsConfig
+    pub hls: HlsConfig,
+    #[serde(default)]
+    pub dash: DashConfig,
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub admin: AdminConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub log: LogConfig,
+    #[serde(default)]
+    pub slate: SlateConfig,
+    #[serde(default)]
+    pub relay: RelayConfig,
+    #[serde(default)]
+    pub play: PlayConfig,
+    #[serde(default)]
+    pub recording: RecordingConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
 }
 
 #[derive(Debug, Deserialize)]
@@ -13,11 +38,589 @@ pub struct ServerConfig {
     pub host: String,
     pub segment_delay: u32,
     pub port: u16,
+    /// ms
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+    /// 송출이 끊긴 뒤 재연결을 기다리는 시간(ms). 0 이면 바로 종료한다.
+    #[serde(default)]
+    pub reconnect_grace: u64,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct HlsConfig {
     pub save_dir: String,
+    #[serde(default = "default_renditions")]
+    pub renditions: Vec<RenditionConfig>,
+    /// 값은 renditions 의 name 목록이다.
+    #[serde(default)]
+    pub profiles: HashMap<String, Vec<String>>,
+    /// RTMP app 이름 -> profiles 의 키
+    #[serde(default)]
+    pub apps: HashMap<String, String>,
+    /// 없으면 모든 renditions 를 사용한다.
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub output_mode: OutputMode,
+    #[serde(default)]
+    pub low_latency: LowLatencyConfig,
+    #[serde(default)]
+    pub dvr: DvrConfig,
+    #[serde(default)]
+    pub thumbnail: ThumbnailConfig,
+    #[serde(default)]
+    pub sprites: SpriteConfig,
+}
+
+/// url 부터 retry_backoff 까지는 kind = "http" 에서 사용한다.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub kind: AuthKind,
+    /// .toml 또는 .json
+    pub file: Option<String>,
+    pub jwt: Option<JwtConfig>,
+    pub url: String,
+    pub method: AuthMethod,
+    pub header: String,
+    pub headers: HashMap<String, String>,
+    /// ms
+    pub timeout: u64,
+    /// 거절된 키는 다시 시도하지 않는다.
+    pub retries: u32,
+    /// ms
+    pub retry_backoff: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            kind: AuthKind::Http,
+            file: None,
+            jwt: None,
+            url: "http://localhost:8080/stream".to_string(),
+            method: AuthMethod::Post,
+            header: "X-Stream-Key".to_string(),
+            headers: HashMap::new(),
+            timeout: 3000,
+            retries: 2,
+            retry_backoff: 500,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthKind {
+    Http,
+    Static,
+    Jwt,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct JwtConfig {
+    pub algorithm: JwtAlgorithm,
+    pub secret: Option<String>,
+    /// RS256 공개키 PEM 파일 경로
+    pub public_key: Option<String>,
+    #[serde(default = "default_nickname_claim")]
+    pub nickname_claim: String,
+    /// 숫자면 unix 초로 보고 RFC 3339 로 바꾼다.
+    #[serde(default = "default_started_at_claim")]
+    pub started_at_claim: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum JwtAlgorithm {
+    HS256,
+    RS256,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum AuthMethod {
+    Get,
+    Post,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub level: LogLevel,
+    pub format: LogFormat,
+    /// GST_DEBUG 형식
+    pub gstreamer: String,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            format: LogFormat::Pretty,
+            gstreamer: "2".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for tracing::level_filters::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => Self::ERROR,
+            LogLevel::Warn => Self::WARN,
+            LogLevel::Info => Self::INFO,
+            LogLevel::Debug => Self::DEBUG,
+            LogLevel::Trace => Self::TRACE,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SlateConfig {
+    pub enabled: bool,
+    /// ms
+    pub timeout: u64,
+    /// PNG 또는 JPEG. 없으면 pattern 의 videotestsrc 패턴을 쓴다.
+    pub image: Option<String>,
+    pub pattern: String,
+}
+
+impl Default for SlateConfig {
+    fn default() -> Self {
+        Self { enabled: false, timeout: 5000, image: None, pattern: "smpte".to_string() }
+    }
+}
+
+/// targets 는 모든 스트림에 적용되고, 인증 결과에 담긴 대상은 그 스트림에만 더해진다.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct RelayConfig {
+    pub targets: Vec<RelayTargetConfig>,
+    /// 대상별 FLV 태그 개수. 넘치면 다음 키프레임까지 버린다.
+    pub queue_size: usize,
+    /// ms
+    pub connect_timeout: u64,
+    /// ms. 실패할 때마다 max_backoff 까지 두 배로 늘어난다.
+    pub retry_backoff: u64,
+    /// ms
+    pub max_backoff: u64,
+    /// ms. 넘기면 다시 연결한다.
+    pub write_timeout: u64,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            targets: Vec::new(),
+            queue_size: 1024,
+            connect_timeout: 5000,
+            retry_backoff: 1000,
+            max_backoff: 30000,
+            write_timeout: 10000,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+pub struct RelayTargetConfig {
+    pub name: String,
+    /// rtmp://host[:port]/app/stream_key
+    pub url: String,
+}
+
+/// 재생에는 인증이 없어서 켜면 채널 이름만 알면 누구나 원본을 받을 수 있다.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PlayConfig {
+    pub enabled: bool,
+    /// 재생자별 FLV 태그 개수. 넘치면 다음 키프레임까지 버린다.
+    pub queue_size: usize,
+}
+
+impl Default for PlayConfig {
+    fn default() -> Self {
+        Self { enabled: false, queue_size: 1024 }
+    }
+}
+
+/// 인증 결과에 record 가 있으면 스트림마다 그 값을 따른다.
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub enabled: bool,
+}
+
+/// 로컬 저장소에서만 동작한다. 보관 기간(ms)은 마지막으로 파일이 쓰인 뒤부터 세고, 0 이면 지우지 않는다.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub enabled: bool,
+    /// ms
+    pub interval: u64,
+    pub live: u64,
+    /// dvr.m3u8 이 있는 출력
+    pub dvr: u64,
+    /// vod.m3u8 이 있는 출력
+    pub recording: u64,
+    /// save_dir 사용량(byte)이 이 값을 넘으면 low_water 아래가 될 때까지 오래된 출력부터 지운다.
+    /// 송출 중인 출력과 보관 기간이 0 인 종류도 사용량에 들어가지만 지우지는 않는다.
+    pub high_water: Option<u64>,
+    /// 없으면 high_water 의 90% 이다.
+    pub low_water: Option<u64>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval: 60_000,
+            live: 3_600_000,
+            dvr: 86_400_000,
+            recording: 0,
+            high_water: None,
+            low_water: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct AdminConfig {
+    /// Bearer 토큰. 없으면 관리자 API 를 열지 않는다.
+    pub token: Option<String>,
+    pub bind: String,
+}
+
+impl Default for AdminConfig {
+    fn default() -> Self {
+        Self { token: None, bind: "127.0.0.1:8082".to_string() }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    /// 있으면 /metrics 요청에 Bearer 토큰이 있어야 한다.
+    pub token: Option<String>,
+    pub bind: String,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self { token: None, bind: "127.0.0.1:9091".to_string() }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub urls: Vec<String>,
+    /// 있으면 본문의 HMAC-SHA256 서명을 signature_header 에 "sha256=<hex>" 로 붙인다.
+    pub secret: Option<String>,
+    pub signature_header: String,
+    /// ms
+    pub timeout: u64,
+    pub retries: u32,
+    /// ms. 재시도마다 두 배로 늘어난다.
+    pub retry_backoff: u64,
+    /// 가득 차면 새 이벤트를 버린다.
+    pub queue_size: usize,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            secret: None,
+            signature_header: "X-Pang-Signature".to_string(),
+            timeout: 3000,
+            retries: 3,
+            retry_backoff: 500,
+            queue_size: 256,
+        }
+    }
+}
+
+/// kind = "local" 이면 hls.save_dir 아래에 저장한다.
+#[derive(Debug, Deserialize, Default)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum StorageConfig {
+    #[default]
+    Local,
+    S3(S3Config),
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct S3Config {
+    pub endpoint: String,
+    pub bucket: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    pub prefix: Option<String>,
+    /// ms
+    #[serde(default = "default_s3_connect_timeout")]
+    pub connect_timeout: u64,
+    /// ms
+    #[serde(default = "default_s3_timeout")]
+    pub timeout: u64,
+}
+
+/// fmp4 output_mode 에서만 사용할 수 있다.
+#[derive(Debug, Deserialize, Default)]
+pub struct DashConfig {
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct DvrConfig {
+    /// dvr.m3u8 이 보여주는 시간(ms). 0 이면 끈다.
+    pub window: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ThumbnailConfig {
+    pub enabled: bool,
+    /// ms
+    pub interval: u64,
+    /// px. 세로는 원본 비율을 따른다.
+    pub width: u32,
+    /// 0-100
+    pub quality: u32,
+}
+
+impl Default for ThumbnailConfig {
+    fn default() -> Self {
+        Self { enabled: false, interval: 10_000, width: 320, quality: 75 }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct SpriteConfig {
+    pub enabled: bool,
+    /// 타일 하나가 맡는 시간(ms)
+    pub interval: u64,
+    /// px. 원본 비율이 다르면 여백을 넣는다.
+    pub width: u32,
+    pub height: u32,
+    pub columns: u32,
+    pub rows: u32,
+    /// 0-100
+    pub quality: u32,
+}
+
+impl Default for SpriteConfig {
+    fn default() -> Self {
+        Self { enabled: false, interval: 5000, width: 160, height: 90, columns: 5, rows: 5, quality: 70 }
+    }
+}
+
+/// fmp4 output_mode 에서만 사용할 수 있다.
+#[derive(Debug, Deserialize)]
+pub struct LowLatencyConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// ms
+    #[serde(default = "default_part_duration")]
+    pub part_duration: u32,
+}
+
+impl Default for LowLatencyConfig {
+    fn default() -> Self {
+        Self { enabled: false, part_duration: default_part_duration() }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    #[default]
+    Ts,
+    Fmp4,
+}
+
+/// bitrate 는 kbps 단위이다.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RenditionConfig {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub video_bitrate: u32,
+    #[serde(default = "default_audio_bitrate")]
+    pub audio_bitrate: u32,
+    #[serde(default = "default_fps")]
+    pub fps: u32,
+    /// baseline, main, high
+    #[serde(default = "default_codec_profile")]
+    pub profile: String,
+    /// 키프레임 간격(초). 없으면 segment_delay 를 사용한다.
+    pub keyframe_interval: Option<u32>,
+}
+
+impl HlsConfig {
+    /// 화질 이름은 경로와 GStreamer 요소 이름이 되므로 겹치거나 경로 구분자를 담을 수 없다.
+    /// profiles, apps, default_profile 이 가리키는 화질과 프로필이 모두 있어야 한다.
+    pub fn validate_renditions(&self) -> Result<(), String> {
+        if self.renditions.is_empty() {
+            return Err("hls.renditions must contain at least one rendition".to_string());
+        }
+        let mut names = HashSet::new();
+        for rendition in &self.renditions {
+            let name = rendition.name.as_str();
+            if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\\']) {
+                return Err(format!("invalid rendition name: {:?}", name));
+            }
+            if !names.insert(name) {
+                return Err(format!("duplicate rendition name: {}", name));
+            }
+        }
+        for (profile_name, rendition_names) in &self.profiles {
+            if rendition_names.is_empty() {
+                return Err(format!("profile {} must contain at least one rendition", profile_name));
+            }
+            let mut seen = HashSet::new();
+            for name in rendition_names {
+                if !names.contains(name.as_str()) {
+                    return Err(format!("profile {} references unknown rendition: {}", profile_name, name));
+                }
+                if !seen.insert(name) {
+                    return Err(format!("profile {} lists rendition {} twice", profile_name, name));
+                }
+            }
+        }
+        let referenced = self.apps.values().map(|p| ("hls.apps", p))
+            .chain(self.default_profile.iter().map(|p| ("hls.default_profile", p)));
+        for (key, profile_name) in referenced {
+            if !self.profiles.contains_key(profile_name) {
+                return Err(format!("{} references unknown rendition profile: {}", key, profile_name));
+            }
+        }
+        Ok(())
+    }
+
+    /// app 이름에 맞는 화질 단계 목록을 찾는다.
+    pub fn renditions_for_app(&self, app_name: &str) -> Result<Vec<RenditionConfig>, String> {
+        let profile_name = match self.apps.get(app_name).or(self.default_profile.as_ref()) {
+            Some(name) => name,
+            None => return Ok(self.renditions.clone()),
+        };
+
+        let names = self.profiles.get(profile_name)
+            .ok_or_else(|| format!("unknown rendition profile: {}", profile_name))?;
+
+        names.iter()
+            .map(|name| {
+                self.renditions.iter()
+                    .find(|r| &r.name == name)
+                    .cloned()
+                    .ok_or_else(|| format!("profile {} references unknown rendition: {}", profile_name, name))
+            })
+            .collect()
+    }
+}
+
+impl RenditionConfig {
+    /// 마스터 플레이리스트의 CODECS 속성에 들어갈 문자열
+    pub fn codecs(&self) -> String {
+        format!("{},mp4a.40.2", self.video_codec())
+    }
+
+    /// 영상 트랙만의 CODECS 문자열 (avc1.PPCCLL)
+    pub fn video_codec(&self) -> String {
+        let profile_idc = match self.profile.as_str() {
+            "baseline" => "42e0",
+            "main" => "4d40",
+            _ => "6400",
+        };
+        let level_idc = match self.height {
+            0..=480 => "1e",
+            481..=720 => "1f",
+            _ => "28",
+        };
+        format!("avc1.{}{}", profile_idc, level_idc)
+    }
+
+    /// 마스터 플레이리스트의 BANDWIDTH 속성 (bit/s)
+    pub fn bandwidth(&self) -> u32 {
+        (self.video_bitrate + self.audio_bitrate) * 1000
+    }
+}
+
+fn default_shutdown_timeout() -> u64 {
+    10000
+}
+
+fn default_nickname_claim() -> String {
+    "nickname".to_string()
+}
+
+fn default_started_at_claim() -> String {
+    "iat".to_string()
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_s3_connect_timeout() -> u64 {
+    3000
+}
+
+fn default_s3_timeout() -> u64 {
+    10000
+}
+
+fn default_part_duration() -> u32 {
+    500
+}
+
+fn default_audio_bitrate() -> u32 {
+    128
+}
+
+fn default_fps() -> u32 {
+    30
+}
+
+fn default_codec_profile() -> String {
+    "main".to_string()
+}
+
+fn default_renditions() -> Vec<RenditionConfig> {
+    [("720p", 1280, 720, 2800), ("480p", 854, 480, 1400), ("360p", 640, 360, 800)]
+        .into_iter()
+        .map(|(name, width, height, video_bitrate)| RenditionConfig {
+            name: name.to_string(),
+            width,
+            height,
+            video_bitrate,
+            audio_bitrate: default_audio_bitrate(),
+            fps: default_fps(),
+            profile: default_codec_profile(),
+            keyframe_interval: None,
+        })
+        .collect()
 }
 
 static CONFIG: OnceLock<Config> = OnceLock::new();
@@ -29,3 +632,28 @@ pub fn get_config() -> &'static Config {
         toml::from_str(&toml_str).expect("환경변수를 파싱하는데 실패했습니다.")
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hls(extra: &str) -> HlsConfig {
+        toml::from_str(&format!("save_dir = \"hls\"\n{}", extra)).unwrap()
+    }
+
+    #[test]
+    fn validates_renditions_and_profiles() {
+        assert!(hls("").validate_renditions().is_ok());
+        assert!(hls("default_profile = \"mobile\"\n[profiles]\nmobile = [\"480p\", \"360p\"]").validate_renditions().is_ok());
+
+        let duplicate = "[[renditions]]\nname = \"720p\"\nwidth = 1280\nheight = 720\nvideo_bitrate = 2800\n\
+                         [[renditions]]\nname = \"720p\"\nwidth = 1280\nheight = 720\nvideo_bitrate = 2000";
+        assert!(hls(duplicate).validate_renditions().is_err());
+        let traversal = "[[renditions]]\nname = \"..\"\nwidth = 1280\nheight = 720\nvideo_bitrate = 2800";
+        assert!(hls(traversal).validate_renditions().is_err());
+        assert!(hls("[profiles]\nmobile = [\"240p\"]").validate_renditions().is_err());
+        assert!(hls("[profiles]\nmobile = []").validate_renditions().is_err());
+        assert!(hls("[apps]\nlive = \"mobile\"").validate_renditions().is_err());
+        assert!(hls("default_profile = \"mobile\"").validate_renditions().is_err());
+    }
+}
@@ -1,53 +1,241 @@
 use scuffle_rtmp::session::server::{ServerSessionError, SessionData, SessionHandler};
-use std::sync::{Arc};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
-use reqwest::Client;
-use crate::authentication_layer::auth::authenticate_and_get_stream_id;
-use crate::config;
+use crate::authentication_layer::auth_error::AuthError;
+use crate::authentication_layer::authenticator::Authenticator;
+use crate::config::{self, RelayTargetConfig};
+use crate::play_layer::live_broadcast::LiveBroadcast;
+use crate::play_layer::session_stream::SessionCloser;
+use crate::play_layer::stream_hub::StreamHub;
+use crate::metrics_layer::server_metrics::{ACTIVE_PUBLISHERS, AUTH_DURATION, AUTH_REQUESTS, INGEST_BYTES, INGEST_TAGS, RTMP_PUBLISHES};
+use crate::relay_layer::relay::Relay;
 use crate::transform_layer::gstreamer::push::push_to_gstreamer;
 use crate::transform_layer::hls_convertor::HlsConvertor;
+use crate::transform_layer::ingest::Ingest;
+use crate::transform_layer::stop_signal::StopSignal;
 use crate::utils::log_error::LogError;
+use crate::webhook_layer::stream_event::{EventKind, StreamEvent};
+use crate::webhook_layer::webhook_sender::WebhookSender;
 
 pub struct Handler {
     hls_convertor: Arc<HlsConvertor>,
-    http_client: Arc<Client>,
+    authenticator: Arc<dyn Authenticator>,
+    webhooks: WebhookSender,
+    stream_hub: Arc<StreamHub>,
+    publisher_addr: SocketAddr,
+    /// RTMP stream id -> 송출 중인 스트림
+    published: HashMap<u32, PublishedStream>,
+    /// publish 를 거절하거나 송출을 끊을 때 세션을 닫는다.
+    closer: SessionCloser,
+    /// 변환이 서버 쪽 사유로 멈추면 취소된다. main 이 세션과 함께 기다렸다가 연결을 닫는다.
+    closed: CancellationToken,
 }
 
+struct PublishedStream {
+    /// 서버 전체에서 유일한 id. scuffle-rtmp 는 모든 세션에 RTMP stream id 1 을 주므로 따로 발급한다.
+    id: u32,
+    /// 인증된 스트림 경로 ("닉네임/시작시각")
+    authed_stream_id: String,
+    /// 메트릭 라벨로 쓰는 채널 이름 (닉네임)
+    channel: String,
+    ingest: Arc<Ingest>,
+    stop: StopSignal,
+    /// 받은 태그를 그대로 다시 송출할 대상들
+    relays: Vec<Relay>,
+    /// RTMP play, HTTP-FLV 재생자에게 보내는 원본 방송. [play] 가 꺼져 있으면 None
+    broadcast: Option<Arc<LiveBroadcast>>,
+    /// 연결 span 아래의 스트림 span
+    span: tracing::Span,
+}
+
+impl PublishedStream {
+    fn end_reason(&self, default: &str) -> String {
+        self.stop.reason().unwrap_or(default).to_string()
+    }
+
+    fn clear_metrics(&self) {
+        ACTIVE_PUBLISHERS.dec();
+        INGEST_BYTES.remove("stream", &self.channel);
+        INGEST_TAGS.remove("stream", &self.channel);
+    }
+}
+
+static NEXT_STREAM_ID: AtomicU32 = AtomicU32::new(1);
+
 impl Handler {
-    pub fn new(hls_convertor: Arc<HlsConvertor>, client: Arc<Client>) -> Result<Self, Box<dyn std::error::Error>> {
+    pub fn new(
+        hls_convertor: Arc<HlsConvertor>,
+        authenticator: Arc<dyn Authenticator>,
+        webhooks: WebhookSender,
+        stream_hub: Arc<StreamHub>,
+        publisher_addr: SocketAddr,
+        closer: SessionCloser,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         Ok(Self {
             hls_convertor,
-            http_client: client,
+            authenticator,
+            webhooks,
+            stream_hub,
+            publisher_addr,
+            published: HashMap::new(),
+            closer,
+            closed: CancellationToken::new(),
         })
     }
+
+    /// 송출 중인 스트림이 관리자 강제 종료나 파이프라인 오류로 멈추면 취소된다.
+    pub fn closed(&self) -> CancellationToken {
+        self.closed.clone()
+    }
+
+    /// publish 에 실패를 알리고 세션을 닫는다. on_publish 는 그 뒤 Ok 를 돌려줘야 세션이 정상 종료된다.
+    fn reject_publish(&self, stream_id: u32, code: &str, description: &str) {
+        RTMP_PUBLISHES.inc(&["rejected"]);
+        self.closer.reject_publish(stream_id, code, description).log_error("reject_publish_failed");
+    }
+
+    /// [relay] 에 설정된 대상과 인증 결과로 받은 대상으로 릴레이를 시작한다.
+    /// 잘못된 대상은 건너뛰고 나머지만 시작한다.
+    fn spawn_relays(&self, extra_targets: &[RelayTargetConfig]) -> Vec<Relay> {
+        let relay_config = &config::get_config().relay;
+        relay_config.targets.iter()
+            .chain(extra_targets)
+            .filter_map(|target| match Relay::spawn(target, relay_config) {
+                Ok(relay) => Some(relay),
+                Err(e) => {
+                    tracing::warn!(name = %target.name, error = %e, "Skipping relay target");
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// 송출자가 스스로 끊었다면 재연결 유예 시간 동안 기다리고, 그렇지 않으면 바로 변환을 끝낸다.
+    /// 유예 중인 스트림의 Unpublish 이벤트는 유예 시간이 끝날 때 HlsConvertor 가 보낸다.
+    fn end_stream(&self, stream: &PublishedStream, default_reason: &str) {
+        stream.clear_metrics();
+        if let Some(broadcast) = &stream.broadcast {
+            self.stream_hub.unpublish(&stream.channel, broadcast);
+        }
+        let reason = stream.end_reason(default_reason);
+        let suspended = stream.stop.reason().is_none()
+            && stream.span.in_scope(|| self.hls_convertor.suspend_hls_conversion(stream.id, reason.clone()));
+        if suspended {
+            return;
+        }
+        self.hls_convertor.stop_hls_conversion(stream.id);
+        let event = StreamEvent::new(EventKind::Unpublish, &stream.authed_stream_id).with_reason(reason);
+        // 관리자가 끊었거나 파이프라인이 실패해 이미 내려간 스트림도 마무리 작업은 남아 있다.
+        let Some(finalizing) = self.hls_convertor.take_finalizing(stream.id) else {
+            self.webhooks.send(event);
+            return;
+        };
+        // 녹화본 위치를 알리려면 VOD 플레이리스트가 저장된 뒤에 보내야 한다.
+        let webhooks = self.webhooks.clone();
+        tokio::spawn(async move {
+            let archive = finalizing.await.ok().flatten();
+            webhooks.send(event.with_archive(archive));
+        }.instrument(stream.span.clone()));
+    }
+}
+
+/// 클라이언트가 unpublish 없이 연결을 끊으면 on_unpublish 가 호출되지 않으므로 세션이 끝날 때 정리한다.
+impl Drop for Handler {
+    fn drop(&mut self) {
+        for stream in self.published.values() {
+            self.end_stream(stream, "disconnected");
+        }
+    }
 }
 
 impl SessionHandler for Handler {
     async fn on_publish(
         &mut self,
         stream_id: u32,
-        _app_name: &str,
+        app_name: &str,
         stream_key: &str,
     ) -> Result<(), ServerSessionError> {
-        if stream_key.is_empty() {
-            return Err(ServerSessionError::InvalidChunkSize(0));
-        }
-
-        let authed_stream_id: &str = &authenticate_and_get_stream_id(stream_key, &self.http_client).await?;
+        let auth_started = Instant::now();
+        let authed = if stream_key.is_empty() {
+            Err(AuthError::Rejected("empty stream key".to_string()))
+        } else {
+            self.authenticator.authenticate(stream_key).await
+        };
+        AUTH_DURATION.observe(auth_started.elapsed());
+        AUTH_REQUESTS.inc(&[authed.as_ref().map_or_else(|e| e.kind(), |_| "ok")]);
+        let authed = match authed {
+            Ok(authed) => authed,
+            Err(e) => {
+                tracing::warn!(stream_id, app_name, error = %e, "Publish rejected");
+                // 거절된 스트림 키는 BadName, 인증 서버 문제는 Unauthorized 로 알린다.
+                let code = match e {
+                    AuthError::Rejected(_) => "NetStream.Publish.BadName",
+                    _ => "NetStream.Publish.Unauthorized",
+                };
+                self.reject_publish(stream_id, code, &e.to_string());
+                return Ok(());
+            }
+        };
+        let authed_stream_id: &str = &authed.path;
         let config = config::get_config();
 
-        if let Err(e) = self.hls_convertor.start_hls_conversion(stream_id, authed_stream_id, &config.server.host) {
-            eprintln!("Failed to start HLS conversion: {}", e);
-            return Err(ServerSessionError::InvalidChunkSize(0));
+        let id = NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed);
+        let ingest = Arc::new(Ingest::new(self.publisher_addr));
+        let span = tracing::info_span!("stream", id, name = %authed_stream_id);
+        let record = authed.record.unwrap_or(config.recording.enabled);
+        let stop = StopSignal::new(self.closed.clone());
+        let started = span.in_scope(|| {
+            self.hls_convertor.start_hls_conversion(id, app_name, authed_stream_id, ingest.clone(), record, stop.clone())
+        });
+        let conversion = match started {
+            Ok(conversion) => conversion,
+            Err(e) => {
+                tracing::error!(error = %e, "Failed to start HLS conversion");
+                self.webhooks.send(StreamEvent::new(EventKind::Error, authed_stream_id).with_reason(e.to_string()));
+                self.reject_publish(stream_id, "NetStream.Failed", &e.to_string());
+                return Ok(());
+            }
+        };
+        // 재연결로 이어 쓰는 스트림은 끊긴 적이 없는 것으로 보고 이전 경로로 이벤트를 보낸다.
+        let authed_stream_id = conversion.stream_name.as_str();
+        if !conversion.resumed {
+            self.webhooks.send(StreamEvent::new(EventKind::Publish, authed_stream_id));
         }
+        RTMP_PUBLISHES.inc(&["accepted"]);
+        ACTIVE_PUBLISHERS.inc();
+        let relays = span.in_scope(|| self.spawn_relays(&authed.relay_targets));
+        self.hls_convertor.set_relays(id, relays.iter().map(Relay::status).collect());
+        let channel = authed_stream_id.split('/').next().unwrap_or(authed_stream_id).to_string();
+        let broadcast = config.play.enabled.then(|| self.stream_hub.publish(&channel));
+        self.published.insert(stream_id, PublishedStream {
+            id,
+            authed_stream_id: authed_stream_id.to_string(),
+            channel,
+            ingest,
+            stop,
+            relays,
+            broadcast,
+            span,
+        });
 
-        let flv_header = self.hls_convertor.create_flv_header();
-        let _ = push_to_gstreamer(self.hls_convertor.get_pipelines(), stream_id, flv_header, 0);
+        // 슬레이트를 내보내던 파이프라인에 이어 붙였다면 flvdemux 가 이미 헤더를 읽었다.
+        if !conversion.spliced {
+            let flv_header = self.hls_convertor.create_flv_header();
+            let _ = push_to_gstreamer(self.hls_convertor.get_pipelines(), id, flv_header, 0);
+        }
         Ok(())
     }
 
     async fn on_unpublish(&mut self, stream_id: u32) -> Result<(), ServerSessionError> {
-        self.hls_convertor.stop_hls_conversion(stream_id);
+        if let Some(stream) = self.published.remove(&stream_id) {
+            self.end_stream(&stream, "unpublished");
+        }
         Ok(())
     }
 
@@ -56,14 +244,40 @@ impl SessionHandler for Handler {
         stream_id: u32,
         data: SessionData,
     ) -> Result<(), ServerSessionError> {
+        let Some(stream) = self.published.get(&stream_id) else {
+            tracing::warn!(stream_id, "No pipeline found");
+            return Ok(());
+        };
+        // main 이 세션을 닫기 전에 데이터가 먼저 들어와도 멈춘 변환에는 넘기지 않는다.
+        if let Some(reason) = stream.stop.reason() {
+            stream.span.in_scope(|| tracing::warn!(reason, "Disconnecting publisher"));
+            self.closer.close();
+            return Ok(());
+        }
+
         let (tag_type, timestamp, payload) = match data {
             SessionData::Video { timestamp, data } => (9, timestamp, data),
             SessionData::Audio { timestamp, data } => (8, timestamp, data),
             SessionData::Amf0 { timestamp, data } => (18, timestamp, data),
         };
 
+        stream.ingest.record_tag(tag_type, &payload);
+        let tag_name = match tag_type {
+            9 => "video",
+            8 => "audio",
+            _ => "data",
+        };
+        INGEST_BYTES.inc_by(&[&stream.channel], payload.len() as u64);
+        INGEST_TAGS.inc(&[&stream.channel, tag_name]);
+        for relay in &stream.relays {
+            relay.send(tag_type, timestamp, payload.clone());
+        }
+        if let Some(broadcast) = &stream.broadcast {
+            broadcast.send(tag_type, timestamp, payload.clone());
+        }
+        let timestamp = self.hls_convertor.rebase_timestamp(stream.id, timestamp);
         let flv_tag = self.hls_convertor.create_flv_tag(tag_type, timestamp, &payload);
-        push_to_gstreamer(self.hls_convertor.get_pipelines(), stream_id, flv_tag, timestamp).log_error("push_failed");
+        push_to_gstreamer(self.hls_convertor.get_pipelines(), stream.id, flv_tag, timestamp).log_error("push_failed");
         Ok(())
     }
 }
@@ -0,0 +1,20 @@
+use gstreamer_app::gst;
+
+/// GStreamer 디버그 로그를 tracing 의 "gstreamer" target 으로 보낸다.
+/// threshold 는 GST_DEBUG 와 같은 형식("2", "*:2,flvdemux:5")이며, 그보다 자세한 로그는 GStreamer 가 거른다.
+pub fn route_gstreamer_logs(threshold: &str) {
+    gst::log::remove_default_log_function();
+    gst::log::add_log_function(|category, level, file, _function, line, object, message| {
+        let Some(message) = message.get() else { return };
+        let category = category.name();
+        let object = object.map(|o| o.to_string()).unwrap_or_default();
+        match level {
+            gst::DebugLevel::Error => tracing::error!(target: "gstreamer", category, object, file = %file, line, "{}", message),
+            gst::DebugLevel::Warning | gst::DebugLevel::Fixme => tracing::warn!(target: "gstreamer", category, object, file = %file, line, "{}", message),
+            gst::DebugLevel::Info => tracing::info!(target: "gstreamer", category, object, file = %file, line, "{}", message),
+            gst::DebugLevel::Debug => tracing::debug!(target: "gstreamer", category, object, file = %file, line, "{}", message),
+            _ => tracing::trace!(target: "gstreamer", category, object, file = %file, line, "{}", message),
+        }
+    });
+    gst::log::set_threshold_from_string(threshold, true);
+}
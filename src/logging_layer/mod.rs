@@ -0,0 +1,31 @@
+/*
+ 로깅 레이어 (logging_layer)
+ tracing 이벤트를 RTMP 연결, 스트림 span 정보와 함께 pretty 또는 JSON 한 줄로 출력한다.
+ GStreamer 디버그 로그도 같은 출력으로 보낸다.
+ */
+pub mod gstreamer_log;
+
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::{filter_fn, EnvFilter};
+use tracing_subscriber::fmt;
+use tracing_subscriber::prelude::*;
+use crate::config::{LogConfig, LogFormat};
+
+/// gst::init 이후에 한 번 호출해야 한다.
+/// RUST_LOG 가 있으면 그 필터를 쓰고, 없으면 log.level 로 이벤트를 거른다.
+/// 이때 span 은 레벨과 상관없이 만들어 두어 경고 로그에도 연결, 스트림 정보가 붙게 한다.
+pub fn init_logging(config: &LogConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let env_filter = EnvFilter::try_from_default_env().ok();
+    let level = LevelFilter::from(config.level);
+    let level_filter = env_filter.is_none()
+        .then(|| filter_fn(move |metadata| metadata.is_span() || *metadata.level() <= level));
+    let json = matches!(config.format, LogFormat::Json);
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with(level_filter)
+        .with(json.then(|| fmt::layer().json().with_span_list(true).with_current_span(false)))
+        .with((!json).then(fmt::layer))
+        .try_init()?;
+    gstreamer_log::route_gstreamer_logs(&config.gstreamer);
+    Ok(())
+}
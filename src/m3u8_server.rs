@@ -1,151 +1,449 @@
 use axum::{
     Router,
     body::Body,
-    extract::Path,
+    extract::{Path, Query, Request, State},
     http::{StatusCode, header},
-    response::IntoResponse,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     routing::get,
 };
 
-use tokio::fs::File;
-
-use std::{path::PathBuf, sync::Arc};
-use tokio::fs;
-use tokio_util::io::ReaderStream;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use serde::Deserialize;
+use std::{sync::Arc, time::{Duration, Instant}};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 use tower_http::cors::CorsLayer;
+use crate::config;
+use crate::metrics_layer::server_metrics::{HTTP_REQUESTS, SEGMENT_SERVE_DURATION};
+use crate::play_layer::http_flv::flv_body;
+use crate::play_layer::stream_hub::StreamHub;
+use crate::storage_layer::segment_store::{InvalidKey, SegmentStore};
+use crate::transform_layer::playlist::dash_manifest::{render_manifest, split_representations};
+use crate::transform_layer::playlist::live_playlist::BlockingReloadError;
+use crate::transform_layer::hls_convertor::HlsConvertor;
+use crate::transform_layer::playlist::live_registry::LiveRegistry;
+use crate::transform_layer::playlist::master_playlist::render_master_playlist;
 
-pub struct M3U8Server {}
+pub struct M3U8Server {
+    live_registry: Arc<LiveRegistry>,
+    store: Arc<dyn SegmentStore>,
+    hls_convertor: Arc<HlsConvertor>,
+    stream_hub: Arc<StreamHub>,
+    /// 끝나지 않는 HTTP-FLV 응답도 종료할 때 끊는다.
+    shutdown: CancellationToken,
+}
 
 impl M3U8Server {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(hls_convertor: Arc<HlsConvertor>, stream_hub: Arc<StreamHub>, shutdown: CancellationToken) -> Self {
+        Self {
+            live_registry: hls_convertor.live_registry(),
+            store: hls_convertor.store(),
+            hls_convertor,
+            stream_hub,
+            shutdown,
+        }
+    }
+
+    /// 송출 중이거나 마지막으로 끝난 방송의 출력 key 를, 둘 다 없으면 같은 이름의 key 를 쓴다.
+    fn output_prefix(&self, stream_key: &str) -> String {
+        self.live_registry
+            .last_output_key(stream_key)
+            .unwrap_or_else(|| stream_key.to_string())
+    }
+
+    fn object_key(&self, stream_key: &str, rendition: &str, file_name: &str) -> String {
+        format!("{}/{}/{}", self.output_prefix(stream_key), rendition, file_name)
+    }
+
+    /// 경로 조각으로 만든 key 가 저장소에서 거절되면 없는 파일로 본다.
+    async fn read(&self, key: &str) -> Result<Option<Vec<u8>>, StatusCode> {
+        self.store.get(key).await.map_err(|e| {
+            if e.is::<InvalidKey>() {
+                return StatusCode::NOT_FOUND;
+            }
+            tracing::error!(key, error = %e, "Failed to read from store");
+            StatusCode::BAD_GATEWAY
+        })
     }
 }
 
-async fn get_master_playlist(
-    Path(stream_key): Path<String>,
-) -> Result<([(String, String); 1], String), StatusCode> {
-    let master_playlist = format!(
-        "#EXTM3U\n\
-         #EXT-X-VERSION:3\n\
-         #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=854x480,CODECS=\"avc1.64001f,mp4a.40.2\"\n\
-         {}/playlist.m3u8\n\
-         #EXT-X-STREAM-INF:BANDWIDTH=1400000,RESOLUTION=1280x720,CODECS=\"avc1.64001f,mp4a.40.2\"\n\
-         {}/playlist.m3u8\n",
-        stream_key, stream_key
-    );
+/// LL-HLS blocking playlist reload 쿼리
+#[derive(Deserialize)]
+struct PlaylistQuery {
+    #[serde(rename = "_HLS_msn")]
+    msn: Option<u64>,
+    #[serde(rename = "_HLS_part")]
+    part: Option<u64>,
+}
 
-    Ok((
+fn playlist_response(playlist: String) -> ([(String, String); 1], String) {
+    (
         [(
             header::CONTENT_TYPE.as_str().to_string(),
             "application/vnd.apple.mpegurl".to_string(),
         )],
-        master_playlist,
+        playlist,
+    )
+}
+
+/// blocking 요청은 목표 세그먼트 길이의 3배까지 기다린다.
+fn blocking_timeout() -> Duration {
+    Duration::from_secs(config::get_config().server.segment_delay as u64 * 3)
+}
+
+/// 송출 중이거나 저장소에 playlist_file 이 남아 있는 화질들로 마스터 플레이리스트를 만든다.
+async fn master_playlist(
+    server: &M3U8Server,
+    stream_key: &str,
+    playlist_file: &str,
+) -> Result<([(String, String); 1], String), StatusCode> {
+    let config = config::get_config();
+
+    let mut variants = Vec::new();
+    for rendition in &config.hls.renditions {
+        let available = server.live_registry.playlist(stream_key, &rendition.name).is_some()
+            || server.read(&server.object_key(stream_key, &rendition.name, playlist_file)).await?.is_some();
+        if available {
+            variants.push(rendition);
+        }
+    }
+
+    if variants.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    // 되감기 중에는 탐색 미리보기 트랙도 알려준다.
+    let thumbnails = (playlist_file == "dvr.m3u8" && has_thumbnails(server, stream_key).await?)
+        .then_some("thumbnails.vtt");
+    Ok(playlist_response(render_master_playlist(variants, playlist_file, thumbnails)))
+}
+
+async fn has_thumbnails(server: &M3U8Server, stream_key: &str) -> Result<bool, StatusCode> {
+    if let Some(track) = server.live_registry.sprite_track(stream_key) {
+        return Ok(!track.lock().unwrap().is_empty());
+    }
+    Ok(server.read(&format!("{}/thumbnails.vtt", server.output_prefix(stream_key))).await?.is_some())
+}
+
+async fn get_master_playlist(
+    State(server): State<Arc<M3U8Server>>,
+    Path(stream_key): Path<String>,
+) -> Result<([(String, String); 1], String), StatusCode> {
+    master_playlist(&server, &stream_key, "playlist.m3u8").await
+}
+
+/// 송출 중이면 메모리의 최신 미리보기를, 끝났으면 저장소에 남은 마지막 미리보기를 보낸다.
+async fn get_thumbnail(
+    State(server): State<Arc<M3U8Server>>,
+    Path(stream_key): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let interval = config::get_config().hls.thumbnail.interval;
+    let image = match server.live_registry.thumbnail(&stream_key) {
+        Some(image) => image,
+        None => {
+            let key = format!("{}/thumbnail.jpg", server.output_prefix(&stream_key));
+            Bytes::from(server.read(&key).await?.ok_or(StatusCode::NOT_FOUND)?)
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/jpeg".to_string()),
+            (header::CACHE_CONTROL, format!("public, max-age={}", (interval / 1000).max(1))),
+        ],
+        Body::from(image),
     ))
 }
 
-async fn get_segment_playlist(
+/// 라이브 끝(playlist.m3u8) 대신 되감기 윈도우 전체를 보여주는 마스터 플레이리스트
+async fn get_dvr_master_playlist(
+    State(server): State<Arc<M3U8Server>>,
     Path(stream_key): Path<String>,
 ) -> Result<([(String, String); 1], String), StatusCode> {
-    let playlist_path = format!("./hls_output/{}/playlist.m3u8", stream_key);
-
-    match fs::read_to_string(&playlist_path).await {
-        Ok(content) => {
-            let modified_content = content
-                .lines()
-                .map(|line| {
-                    if line.ends_with(".ts") && !line.starts_with("http") {
-                        format!("http://localhost:8080/hls/{}/{}", stream_key, line)
-                    } else if line.starts_with("http://localhost:8080/") && !line.contains("/hls/") {
-                        line.replace(
-                            &format!("http://localhost:8080/{}/", stream_key),
-                            &format!("http://localhost:8080/hls/{}/", stream_key)
-                        )
-                    } else {
-                        line.to_string()
-                    }
-                })
-                .collect::<Vec<String>>()
-                .join("\n");
-            Ok((
-                [(
-                    header::CONTENT_TYPE.as_str().to_string(),
-                    "application/vnd.apple.mpegurl".to_string(),
-                )],
-                modified_content,
-            ))
-        },
-        Err(_) => {
+    if config::get_config().hls.dvr.window == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    master_playlist(&server, &stream_key, "dvr.m3u8").await
+}
+
+async fn get_dvr_playlist(
+    State(server): State<Arc<M3U8Server>>,
+    Path((stream_key, rendition)): Path<(String, String)>,
+) -> Result<([(String, String); 1], String), StatusCode> {
+    if let Some(live_playlist) = server.live_registry.playlist(&stream_key, &rendition) {
+        return live_playlist.read(|p| p.render_dvr())
+            .map(playlist_response)
+            .ok_or(StatusCode::NOT_FOUND);
+    }
+
+    let key = server.object_key(&stream_key, &rendition, "dvr.m3u8");
+    let content = server.read(&key).await?.ok_or(StatusCode::NOT_FOUND)?;
+    Ok(playlist_response(String::from_utf8_lossy(&content).into_owned()))
+}
+
+async fn get_segment_playlist(
+    State(server): State<Arc<M3U8Server>>,
+    Path((stream_key, rendition)): Path<(String, String)>,
+    Query(query): Query<PlaylistQuery>,
+) -> Result<([(String, String); 1], String), StatusCode> {
+    // _HLS_part 는 _HLS_msn 과 함께여야 한다. (RFC 8216bis 6.2.5.2)
+    if query.part.is_some() && query.msn.is_none() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if let Some(live_playlist) = server.live_registry.playlist(&stream_key, &rendition) {
+        if let Some(msn) = query.msn {
+            live_playlist.wait_for(msn, query.part, blocking_timeout()).await
+                .map_err(|e| match e {
+                    BlockingReloadError::TooFarAhead => StatusCode::BAD_REQUEST,
+                    BlockingReloadError::Timeout => StatusCode::SERVICE_UNAVAILABLE,
+                })?;
+        }
+        return Ok(playlist_response(live_playlist.render()));
+    }
+
+    let key = server.object_key(&stream_key, &rendition, "playlist.m3u8");
+    match server.read(&key).await? {
+        Some(content) => Ok(playlist_response(String::from_utf8_lossy(&content).into_owned())),
+        None => {
             let default_playlist = "#EXTM3U\n\
                  #EXT-X-VERSION:3\n\
                  #EXT-X-TARGETDURATION:6\n\
                  #EXT-X-MEDIA-SEQUENCE:0\n\
                  #EXT-X-PLAYLIST-TYPE:EVENT\n".to_string();
-            Ok((
-                [(
-                    header::CONTENT_TYPE.as_str().to_string(),
-                    "application/vnd.apple.mpegurl".to_string(),
-                )],
-                default_playlist,
-            ))
+            Ok(playlist_response(default_playlist))
         }
     }
 }
 
-async fn get_init_mp4(
+fn segment_content_type(segment: &str) -> Option<&'static str> {
+    match segment.rsplit_once('.')?.1 {
+        "ts" => Some("video/mp2t"),
+        "m4s" => Some("video/iso.segment"),
+        "mp4" => Some("video/mp4"),
+        _ => None,
+    }
+}
+
+/// 송출 중이면 메모리의 세그먼트 목록으로 만들고, 끝났으면 저장소에 남은 static 매니페스트를 보낸다.
+async fn get_dash_manifest(
+    State(server): State<Arc<M3U8Server>>,
     Path(stream_key): Path<String>,
+) -> Result<([(String, String); 1], String), StatusCode> {
+    let config = config::get_config();
+    if !config.dash.enabled {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let manifest = match server.live_registry.started_at(&stream_key) {
+        Some(started_at) => {
+            let playlists = server.live_registry.dash_playlists(&stream_key);
+            let (video, audio) = split_representations(&config.hls.renditions, playlists);
+            if video.is_empty() {
+                return Err(StatusCode::NOT_FOUND);
+            }
+            render_manifest(started_at, config.server.segment_delay, &video, audio)
+        }
+        None => {
+            let key = format!("{}/dash/manifest.mpd", server.output_prefix(&stream_key));
+            let content = server.read(&key).await?.ok_or(StatusCode::NOT_FOUND)?;
+            String::from_utf8_lossy(&content).into_owned()
+        }
+    };
+    Ok((
+        [(
+            header::CONTENT_TYPE.as_str().to_string(),
+            "application/dash+xml".to_string(),
+        )],
+        manifest,
+    ))
+}
+
+async fn get_init_mp4(
+    State(server): State<Arc<M3U8Server>>,
+    Path((stream_key, rendition)): Path<(String, String)>,
 ) -> Result<([(String, String); 1], Vec<u8>), StatusCode> {
-    let file_path = format!("./hls_output/{}/init.mp4", stream_key);
+    let key = server.object_key(&stream_key, &rendition, "init.mp4");
 
-    match fs::read(&file_path).await {
-        Ok(data) => Ok((
+    match server.read(&key).await? {
+        Some(data) => Ok((
             [(
                 header::CONTENT_TYPE.as_str().to_string(),
                 "video/mp4".to_string(),
             )],
             data,
         )),
-        Err(_) => Err(StatusCode::NOT_FOUND),
+        None => Err(StatusCode::NOT_FOUND),
     }
 }
 
-async fn get_ts_segment(
-    Path((stream_key, segment)): Path<(String, String)>,
+async fn get_segment(
+    State(server): State<Arc<M3U8Server>>,
+    Path((stream_key, rendition, segment)): Path<(String, String, String)>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let content_type = segment_content_type(&segment).ok_or(StatusCode::NOT_FOUND)?;
+
+    let key = server.object_key(&stream_key, &rendition, &segment);
+
+    let data = match server.read(&key).await? {
+        Some(data) => data,
+        None => {
+            // EXT-X-PRELOAD-HINT 로 알려준 부분 세그먼트만 만들어질 때까지 기다렸다가 보낸다.
+            // 힌트가 다음 부분으로 넘어갔는데도 없으면 기다려도 생기지 않는다.
+            let live_playlist = server.live_registry
+                .playlist(&stream_key, &rendition)
+                .filter(|playlist| playlist.is_preload_hint(&segment))
+                .ok_or(StatusCode::NOT_FOUND)?;
+            let deadline = tokio::time::Instant::now() + blocking_timeout();
+            loop {
+                let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+                if remaining.is_zero() || !live_playlist.wait_for_update(remaining).await {
+                    return Err(StatusCode::NOT_FOUND);
+                }
+                if let Some(data) = server.read(&key).await? {
+                    break data;
+                }
+                if !live_playlist.is_preload_hint(&segment) {
+                    return Err(StatusCode::NOT_FOUND);
+                }
+            }
+        }
+    };
+
+    Ok(([(header::CONTENT_TYPE, content_type)], Body::from(data)))
+}
+
+/// DASH Representation 의 init.mp4 와 세그먼트. "출력위치/dash/이름" 아래에 기록된다.
+async fn get_dash_segment(
+    State(server): State<Arc<M3U8Server>>,
+    Path((stream_key, representation, segment)): Path<(String, String, String)>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let content_type = segment_content_type(&segment).ok_or(StatusCode::NOT_FOUND)?;
+    let key = format!("{}/dash/{}/{}", server.output_prefix(&stream_key), representation, segment);
+    let data = server.read(&key).await?.ok_or(StatusCode::NOT_FOUND)?;
+    Ok(([(header::CONTENT_TYPE, content_type)], Body::from(data)))
+}
+
+/// 녹화본 파일을 저장소에서 그대로 읽어 보낸다. 플레이리스트와 세그먼트만 보낸다.
+async fn vod_response(server: &M3U8Server, key: &str) -> Result<impl IntoResponse + use<>, StatusCode> {
+    let content_type = match key.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("m3u8") => "application/vnd.apple.mpegurl",
+        Some("vtt") => "text/vtt",
+        Some("jpg") => "image/jpeg",
+        _ => segment_content_type(key).ok_or(StatusCode::NOT_FOUND)?,
+    };
+    let data = server.read(key).await?.ok_or(StatusCode::NOT_FOUND)?;
+    Ok(([(header::CONTENT_TYPE, content_type)], Body::from(data)))
+}
+
+/// 마스터 VOD 플레이리스트와 탐색 미리보기(thumbnails.vtt, sprite_*.jpg)
+async fn get_vod_stream_file(
+    State(server): State<Arc<M3U8Server>>,
+    Path((stream_key, started_at, file_name)): Path<(String, String, String)>,
+) -> Result<impl IntoResponse, StatusCode> {
+    vod_response(&server, &format!("{}/{}/{}", stream_key, started_at, file_name)).await
+}
+
+/// 송출 중이거나 마지막으로 끝난 방송의 탐색 미리보기(thumbnails.vtt, sprite_*.jpg)
+async fn get_sprite_file(
+    State(server): State<Arc<M3U8Server>>,
+    Path((stream_key, file_name)): Path<(String, String)>,
 ) -> Result<impl IntoResponse, StatusCode> {
-    if !segment.ends_with(".ts") {
+    if file_name != "thumbnails.vtt" && !file_name.starts_with("sprite_") {
         return Err(StatusCode::NOT_FOUND);
     }
+    vod_response(&server, &format!("{}/{}", server.output_prefix(&stream_key), file_name)).await
+}
+
+async fn get_vod_file(
+    State(server): State<Arc<M3U8Server>>,
+    Path((stream_key, started_at, rendition, file_name)): Path<(String, String, String, String)>,
+) -> Result<impl IntoResponse, StatusCode> {
+    vod_response(&server, &format!("{}/{}/{}/{}", stream_key, started_at, rendition, file_name)).await
+}
+
+/// 원본 스트림을 HTTP-FLV 로 보낸다. matchit 은 경로 조각 안의 접미사를 지원하지 않아 ".flv" 는 직접 뗀다.
+async fn get_flv(
+    State(server): State<Arc<M3U8Server>>,
+    Path(file_name): Path<String>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let stream_key = file_name.strip_suffix(".flv").ok_or(StatusCode::NOT_FOUND)?;
+    let broadcast = server.stream_hub.get(stream_key).ok_or(StatusCode::NOT_FOUND)?;
+    let body = flv_body(server.hls_convertor.clone(), broadcast.subscribe(), stream_key.to_string())
+        .take_until(server.shutdown.clone().cancelled_owned());
 
-    let file_path = PathBuf::from("hls_output").join(&stream_key).join(&segment);
+    Ok((
+        [(header::CONTENT_TYPE, "video/x-flv"), (header::CACHE_CONTROL, "no-cache")],
+        Body::from_stream(body),
+    ))
+}
 
-    let file = File::open(file_path)
-        .await
-        .map_err(|_| StatusCode::NOT_FOUND)?;
-    let stream = ReaderStream::new(file);
-    let body = Body::from_stream(stream);
+/// 요청 경로로 메트릭 라벨에 쓸 route 이름을 정한다.
+fn request_route(path: &str) -> &'static str {
+    if path.ends_with(".m3u8") {
+        "playlist"
+    } else if path.ends_with(".mpd") {
+        "manifest"
+    } else if path.ends_with(".flv") {
+        "flv"
+    } else if path.ends_with(".jpg") || path.ends_with(".vtt") {
+        "thumbnail"
+    } else {
+        "segment"
+    }
+}
 
-    Ok(([(header::CONTENT_TYPE, "video/mp2t")], body))
+async fn record_request(request: Request, next: Next) -> Response {
+    let route = request_route(request.uri().path());
+    let started = Instant::now();
+    let response = next.run(request).await;
+    HTTP_REQUESTS.inc(&[route, response.status().as_str()]);
+    if route == "segment" {
+        SEGMENT_SERVE_DURATION.observe(started.elapsed());
+    }
+    response
 }
 
-pub async fn start_m3u8_server() -> Result<(), Box<dyn std::error::Error>> {
-    let server = Arc::new(M3U8Server::new());
+pub async fn start_m3u8_server(
+    hls_convertor: Arc<HlsConvertor>,
+    stream_hub: Arc<StreamHub>,
+    shutdown: CancellationToken,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let server = Arc::new(M3U8Server::new(hls_convertor, stream_hub, shutdown.clone()));
     let app = Router::new()
         .route("/hls/{stream_key}/master.m3u8", get(get_master_playlist))
-        .route("/hls/{stream_key}/playlist.m3u8", get(get_segment_playlist))
-        .route("/hls/{stream_key}/init.mp4", get(get_init_mp4))
-        .route("/hls/{stream_key}/{segment}", get(get_ts_segment))
+        .route("/hls/{stream_key}/dvr.m3u8", get(get_dvr_master_playlist))
+        .route("/hls/{stream_key}/thumbnail.jpg", get(get_thumbnail))
+        .route("/hls/{stream_key}/{file_name}", get(get_sprite_file))
+        .route("/hls/{stream_key}/{rendition}/playlist.m3u8", get(get_segment_playlist))
+        .route("/hls/{stream_key}/{rendition}/dvr.m3u8", get(get_dvr_playlist))
+        .route("/hls/{stream_key}/{rendition}/init.mp4", get(get_init_mp4))
+        .route("/hls/{stream_key}/{rendition}/{segment}", get(get_segment))
+        .route("/dash/{stream_key}/manifest.mpd", get(get_dash_manifest))
+        .route("/dash/{stream_key}/{representation}/{segment}", get(get_dash_segment))
+        .route("/live/{file_name}", get(get_flv))
+        .route("/vod/{stream_key}/{started_at}/{file_name}", get(get_vod_stream_file))
+        .route("/vod/{stream_key}/{started_at}/{rendition}/{file_name}", get(get_vod_file))
+        .layer(middleware::from_fn(record_request))
         .layer(CorsLayer::permissive())
         .with_state(server);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:8081").await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await?;
     Ok(())
 }
 
-pub fn start_m3u8_server_background() {
-    tokio::spawn(async {
-        if let Err(e) = start_m3u8_server().await {
-            eprintln!("Web server error: {}", e);
+/// shutdown 이 취소되면 진행 중인 요청을 마친 뒤 반환된 작업이 끝난다.
+pub fn start_m3u8_server_background(
+    hls_convertor: Arc<HlsConvertor>,
+    stream_hub: Arc<StreamHub>,
+    shutdown: CancellationToken,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        if let Err(e) = start_m3u8_server(hls_convertor, stream_hub, shutdown).await {
+            tracing::error!(error = %e, "Web server error");
         }
-    });
+    })
 }
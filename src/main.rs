@@ -1,48 +1,125 @@
 use std::sync::Arc;
+use std::time::Duration;
 use gstreamer_app::gst;
 use scuffle_rtmp::ServerSession;
+use scuffle_rtmp::error::RtmpError;
+use scuffle_rtmp::session::server::ServerSessionError;
 use reqwest::Client;
 use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+mod admin_server;
 mod config;
 mod handler;
+mod logging_layer;
+mod metrics_layer;
+mod relay_layer;
 mod m3u8_server;
+mod play_layer;
 mod authentication_layer;
+mod storage_layer;
 mod utils;
 mod transform_layer;
+mod webhook_layer;
 
+use admin_server::start_admin_server_background;
 use handler::Handler;
+use crate::authentication_layer::authenticator::create_authenticator;
+use crate::play_layer::rtmp_player::serve_play;
+use crate::play_layer::session_stream::SessionStream;
+use crate::play_layer::stream_hub::StreamHub;
 use m3u8_server::start_m3u8_server_background;
+use crate::metrics_layer::metrics_server::start_metrics_server_background;
+use crate::config::StorageConfig;
+use crate::storage_layer::janitor::Janitor;
+use crate::storage_layer::segment_store::create_store;
 use crate::transform_layer::hls_convertor::HlsConvertor;
+use crate::utils::shutdown_signal::shutdown_signal;
+use crate::webhook_layer::webhook_sender::WebhookSender;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     gst::init().expect("Failed to initialize GStreamer");
-    start_m3u8_server_background();
-    tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
     let config = config::get_config();
-    let client = Arc::new(Client::new());
-    let hls_convertor = Arc::new(HlsConvertor::new(format!("{}", config.hls.save_dir))?);
+    logging_layer::init_logging(&config.log)?;
+    let client = Client::new();
+    let authenticator = create_authenticator(config, Arc::new(client.clone()))?;
+    let webhooks = WebhookSender::spawn(&config.webhooks, client);
+    let store = create_store(config)?;
+    let hls_convertor = Arc::new(HlsConvertor::new(store, webhooks.clone())?);
+    if config.retention.enabled {
+        match config.storage {
+            StorageConfig::Local => {
+                Janitor::new(&config.hls.save_dir, config.retention.clone(), hls_convertor.live_registry()).start().await;
+            }
+            StorageConfig::S3(_) => tracing::info!("Retention is not applied to S3 storage; use bucket lifecycle rules"),
+        }
+    }
+    let http_shutdown = CancellationToken::new();
+    let stream_hub = Arc::new(StreamHub::new(config.play.queue_size));
+    let m3u8_server = start_m3u8_server_background(hls_convertor.clone(), stream_hub.clone(), http_shutdown.clone());
+    start_admin_server_background(hls_convertor.clone(), &config.admin, http_shutdown.clone());
+    start_metrics_server_background(&config.metrics, http_shutdown.clone());
+    tokio::time::sleep(tokio::time::Duration::from_millis(300)).await;
     let listener = TcpListener::bind(format!("[::]:{}", config.server.port)).await?;
-    println!("RTMP Server listening on [::]:{}", config.server.port);
+    tracing::info!(port = config.server.port, "RTMP Server listening");
 
-    while let Ok((stream, addr)) = listener.accept().await {
-        println!("New connection from: {}", addr);
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(_) => break,
+            },
+            _ = &mut shutdown => break,
+        };
+        let span = tracing::info_span!("connection", peer = %addr);
+        span.in_scope(|| tracing::info!("New connection"));
         let hls_convertor_clone = Arc::clone(&hls_convertor);
-        let client_clone = Arc::clone(&client);
+        let authenticator_clone = Arc::clone(&authenticator);
+        let webhooks_clone = webhooks.clone();
+        let stream_hub_clone = Arc::clone(&stream_hub);
         tokio::spawn(async move {
-            let handler = match Handler::new(hls_convertor_clone, client_clone) {
+            let mut stream = SessionStream::new(stream);
+            let handler = match Handler::new(hls_convertor_clone, authenticator_clone, webhooks_clone, stream_hub_clone.clone(), addr, stream.closer()) {
                 Ok(h) => h,
                 Err(e) => {
-                    eprintln!("Failed to create handler for {}: {}", addr, e);
+                    tracing::error!(error = %e, "Failed to create handler");
                     return;
                 }
             };
 
-            let session = ServerSession::new(stream, handler);
-            if let Err(err) = session.run().await {
-                eprintln!("Session error from {}: {:?}", addr, err);
+            let closed = handler.closed();
+            let session = ServerSession::new(&mut stream, handler);
+            // 관리자 강제 종료나 파이프라인 오류로 변환이 멈추면 다음 데이터를 기다리지 않고 세션을 버려 연결을 닫는다.
+            // 세션과 함께 Handler 가 drop 되면서 스트림을 정리한다.
+            let result = tokio::select! {
+                result = session.run() => result,
+                _ = closed.cancelled() => {
+                    tracing::warn!("Disconnecting publisher");
+                    Ok(false)
+                }
+            };
+            match result {
+                // scuffle-rtmp 세션은 송출만 받으므로 play 명령이 오면 연결을 이어받아 재생한다.
+                Err(RtmpError::Session(ServerSessionError::PlayNotSupported)) if config::get_config().play.enabled => {
+                    if let Err(e) = serve_play(stream, &stream_hub_clone).await {
+                        tracing::warn!(error = %e, "Play session error");
+                    }
+                }
+                Err(err) => tracing::warn!(error = ?err, "Session error"),
+                Ok(_) => {}
             }
-        });
+        }.instrument(span));
     }
+
+    // 새 연결을 받지 않고, 파이프라인이 플레이리스트를 마무리한 뒤 HTTP 서버를 내린다.
+    drop(listener);
+    tracing::info!("Shutting down");
+    hls_convertor.shutdown(Duration::from_millis(config.server.shutdown_timeout)).await;
+    http_shutdown.cancel();
+    let _ = m3u8_server.await;
+    tracing::info!("Shutdown complete");
     Ok(())
 }
\ No newline at end of file
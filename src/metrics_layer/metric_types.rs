@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+
+pub struct Gauge {
+    name: &'static str,
+    help: &'static str,
+    value: AtomicI64,
+}
+
+impl Gauge {
+    pub const fn new(name: &'static str, help: &'static str) -> Self {
+        Self { name, help, value: AtomicI64::new(0) }
+    }
+
+    pub fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dec(&self) {
+        self.value.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn render(&self, out: &mut String) {
+        write_header(out, self.name, self.help, "gauge");
+        let _ = writeln!(out, "{} {}", self.name, self.value.load(Ordering::Relaxed));
+    }
+}
+
+/// 라벨 값 조합마다 따로 세는 카운터
+pub struct CounterVec {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+
+impl CounterVec {
+    pub const fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self { name, help, labels, values: Mutex::new(BTreeMap::new()) }
+    }
+
+    pub fn inc(&self, label_values: &[&str]) {
+        self.inc_by(label_values, 1);
+    }
+
+    pub fn inc_by(&self, label_values: &[&str], amount: u64) {
+        let key = label_values.iter().map(|v| v.to_string()).collect();
+        *self.values.lock().unwrap().entry(key).or_insert(0) += amount;
+    }
+
+    /// label 값이 value 인 시계열을 모두 지운다. 끝난 스트림의 시계열이 계속 쌓이지 않게 한다.
+    pub fn remove(&self, label: &str, value: &str) {
+        let Some(index) = self.labels.iter().position(|l| *l == label) else { return };
+        self.values.lock().unwrap().retain(|key, _| key[index] != value);
+    }
+
+    pub fn render(&self, out: &mut String) {
+        write_header(out, self.name, self.help, "counter");
+        for (key, value) in self.values.lock().unwrap().iter() {
+            let _ = writeln!(out, "{}{{{}}} {}", self.name, format_labels(self.labels, key), value);
+        }
+    }
+}
+
+/// 초 단위 히스토그램
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    buckets: &'static [f64],
+    state: Mutex<HistogramState>,
+}
+
+struct HistogramState {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    pub const fn new(name: &'static str, help: &'static str, buckets: &'static [f64]) -> Self {
+        Self {
+            name,
+            help,
+            buckets,
+            state: Mutex::new(HistogramState { counts: Vec::new(), sum: 0.0, count: 0 }),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let mut state = self.state.lock().unwrap();
+        state.counts.resize(self.buckets.len(), 0);
+        for (count, bound) in state.counts.iter_mut().zip(self.buckets) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        state.sum += seconds;
+        state.count += 1;
+    }
+
+    pub fn render(&self, out: &mut String) {
+        write_header(out, self.name, self.help, "histogram");
+        let state = self.state.lock().unwrap();
+        for (index, bound) in self.buckets.iter().enumerate() {
+            let count = state.counts.get(index).copied().unwrap_or(0);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", self.name, bound, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", self.name, state.count);
+        let _ = writeln!(out, "{}_sum {}", self.name, state.sum);
+        let _ = writeln!(out, "{}_count {}", self.name, state.count);
+    }
+}
+
+fn write_header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn format_labels(labels: &[&str], values: &[String]) -> String {
+    labels.iter()
+        .zip(values)
+        .map(|(label, value)| {
+            let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            format!("{}=\"{}\"", label, escaped)
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_gauge() {
+        let gauge = Gauge::new("test_active", "Active things");
+        gauge.inc();
+        gauge.inc();
+        gauge.dec();
+        let mut out = String::new();
+        gauge.render(&mut out);
+        assert_eq!(out, "# HELP test_active Active things\n# TYPE test_active gauge\ntest_active 1\n");
+    }
+
+    #[test]
+    fn renders_counter_with_escaped_labels() {
+        let counter = CounterVec::new("test_bytes_total", "Bytes", &["stream", "kind"]);
+        counter.inc_by(&["a\"b", "video"], 10);
+        counter.inc(&["a\"b", "video"]);
+        counter.inc(&["c", "audio"]);
+        counter.remove("stream", "c");
+        let mut out = String::new();
+        counter.render(&mut out);
+        assert_eq!(
+            out,
+            "# HELP test_bytes_total Bytes\n\
+             # TYPE test_bytes_total counter\n\
+             test_bytes_total{stream=\"a\\\"b\",kind=\"video\"} 11\n"
+        );
+    }
+
+    #[test]
+    fn renders_cumulative_histogram() {
+        let histogram = Histogram::new("test_seconds", "Durations", &[0.1, 1.0]);
+        histogram.observe(Duration::from_millis(50));
+        histogram.observe(Duration::from_millis(500));
+        histogram.observe(Duration::from_secs(2));
+        let mut out = String::new();
+        histogram.render(&mut out);
+        assert_eq!(
+            out,
+            "# HELP test_seconds Durations\n\
+             # TYPE test_seconds histogram\n\
+             test_seconds_bucket{le=\"0.1\"} 1\n\
+             test_seconds_bucket{le=\"1\"} 2\n\
+             test_seconds_bucket{le=\"+Inf\"} 3\n\
+             test_seconds_sum 2.55\n\
+             test_seconds_count 3\n"
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use axum::{
+    Router,
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use crate::config::MetricsConfig;
+use crate::metrics_layer::server_metrics;
+use crate::utils::bearer_token::has_bearer_token;
+
+/// 재생용 HTTP 서버와 따로 /metrics 만 여는 서버. 스트림 이름이 라벨에 들어가므로 공개 포트에 두지 않는다.
+async fn require_token(
+    State(token): State<Arc<String>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    if !has_bearer_token(request.headers(), &token) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(next.run(request).await)
+}
+
+async fn get_metrics() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        server_metrics::render(),
+    )
+}
+
+pub async fn start_metrics_server(
+    config: &MetricsConfig,
+    shutdown: CancellationToken,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut app = Router::new().route("/metrics", get(get_metrics));
+    if let Some(token) = &config.token {
+        app = app.layer(middleware::from_fn_with_state(Arc::new(token.clone()), require_token));
+    }
+
+    let listener = tokio::net::TcpListener::bind(&config.bind).await?;
+    tracing::info!(bind = %config.bind, "Metrics listening");
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown.cancelled_owned())
+        .await?;
+    Ok(())
+}
+
+pub fn start_metrics_server_background(config: &'static MetricsConfig, shutdown: CancellationToken) {
+    tokio::spawn(async move {
+        if let Err(e) = start_metrics_server(config, shutdown).await {
+            tracing::error!(error = %e, "Metrics server error");
+        }
+    });
+}
@@ -0,0 +1,8 @@
+/*
+ 메트릭 레이어 (metrics_layer)
+ 서버 전체의 카운터, 게이지, 히스토그램을 모아 Prometheus 텍스트 형식으로 내보낸다.
+ [metrics] bind 에 따로 여는 /metrics 에서 읽는다.
+ */
+pub mod metric_types;
+pub mod metrics_server;
+pub mod server_metrics;
@@ -0,0 +1,91 @@
+use crate::metrics_layer::metric_types::{CounterVec, Gauge, Histogram};
+
+const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+pub static ACTIVE_PUBLISHERS: Gauge = Gauge::new(
+    "pang_active_publishers",
+    "Number of RTMP publishers currently being transcoded",
+);
+
+pub static ACTIVE_PLAYERS: Gauge = Gauge::new(
+    "pang_active_players",
+    "Number of clients playing an original stream",
+);
+
+pub static RTMP_PUBLISHES: CounterVec = CounterVec::new(
+    "pang_rtmp_publishes_total",
+    "RTMP publish attempts by result (accepted, rejected)",
+    &["result"],
+);
+
+pub static AUTH_REQUESTS: CounterVec = CounterVec::new(
+    "pang_auth_requests_total",
+    "Stream key authentications by outcome",
+    &["outcome"],
+);
+
+pub static AUTH_DURATION: Histogram = Histogram::new(
+    "pang_auth_duration_seconds",
+    "Time spent authenticating a stream key",
+    LATENCY_BUCKETS,
+);
+
+pub static INGEST_BYTES: CounterVec = CounterVec::new(
+    "pang_ingest_bytes_total",
+    "FLV payload bytes received per stream",
+    &["stream"],
+);
+
+pub static INGEST_TAGS: CounterVec = CounterVec::new(
+    "pang_ingest_flv_tags_total",
+    "FLV tags received per stream and tag type",
+    &["stream", "type"],
+);
+
+pub static PUSH_FAILURES: CounterVec = CounterVec::new(
+    "pang_push_failures_total",
+    "Buffers appsrc refused by GStreamer flow error",
+    &["error"],
+);
+
+pub static SEGMENTS_WRITTEN: CounterVec = CounterVec::new(
+    "pang_segments_written_total",
+    "Media segments written per rendition",
+    &["rendition"],
+);
+
+pub static HTTP_REQUESTS: CounterVec = CounterVec::new(
+    "pang_http_requests_total",
+    "HLS/DASH HTTP requests by route and status",
+    &["route", "status"],
+);
+
+pub static SEGMENT_SERVE_DURATION: Histogram = Histogram::new(
+    "pang_segment_serve_duration_seconds",
+    "Time spent serving a media segment",
+    LATENCY_BUCKETS,
+);
+
+pub static OUTPUTS_DELETED: CounterVec = CounterVec::new(
+    "pang_outputs_deleted_total",
+    "Ended stream output directories removed by the janitor per kind",
+    &["kind"],
+);
+
+/// Prometheus 텍스트 형식 (version 0.0.4)
+pub fn render() -> String {
+    let mut out = String::new();
+    ACTIVE_PUBLISHERS.render(&mut out);
+    ACTIVE_PLAYERS.render(&mut out);
+    RTMP_PUBLISHES.render(&mut out);
+    AUTH_REQUESTS.render(&mut out);
+    AUTH_DURATION.render(&mut out);
+    INGEST_BYTES.render(&mut out);
+    INGEST_TAGS.render(&mut out);
+    PUSH_FAILURES.render(&mut out);
+    SEGMENTS_WRITTEN.render(&mut out);
+    HTTP_REQUESTS.render(&mut out);
+    SEGMENT_SERVE_DURATION.render(&mut out);
+    OUTPUTS_DELETED.render(&mut out);
+    out
+}
@@ -0,0 +1,40 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+use bytes::Bytes;
+use futures_util::{stream, Stream, StreamExt};
+use crate::metrics_layer::server_metrics::ACTIVE_PLAYERS;
+use crate::play_layer::live_broadcast::Subscription;
+use crate::transform_layer::hls_convertor::HlsConvertor;
+
+/// HTTP-FLV 응답 본문. FLV 헤더 뒤에 구독한 태그를 FLV 태그로 감싸 이어 보낸다.
+/// hyper 가 본문을 가져갈 때만 다음 태그를 읽으므로 느린 클라이언트의 태그는 서버에 쌓이지 않고 GOP 단위로 버려진다.
+pub fn flv_body(
+    hls_convertor: Arc<HlsConvertor>,
+    subscription: Subscription,
+    name: String,
+) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    let header = Bytes::from(hls_convertor.create_flv_header());
+    tracing::info!(name, "HTTP-FLV play started");
+    ACTIVE_PLAYERS.inc();
+    let player = FlvPlayer { hls_convertor, subscription, name };
+
+    stream::once(async { Ok(header) }).chain(stream::unfold(player, |mut player| async move {
+        let tag = player.subscription.next().await?;
+        let data = player.hls_convertor.create_flv_tag(tag.tag_type, tag.timestamp, &tag.data);
+        Some((Ok(Bytes::from(data)), player))
+    }))
+}
+
+/// 응답 본문이 끝나거나 클라이언트가 끊겨 drop 될 때 재생 종료를 기록한다.
+struct FlvPlayer {
+    hls_convertor: Arc<HlsConvertor>,
+    subscription: Subscription,
+    name: String,
+}
+
+impl Drop for FlvPlayer {
+    fn drop(&mut self) {
+        ACTIVE_PLAYERS.dec();
+        tracing::info!(name = self.name, dropped_tags = self.subscription.dropped(), "HTTP-FLV play ended");
+    }
+}
@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use bytes::Bytes;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use crate::utils::flv_tag::{is_header_tag, is_keyframe};
+
+#[derive(Clone)]
+pub struct LiveTag {
+    pub tag_type: u8,
+    pub timestamp: u32,
+    pub data: Bytes,
+}
+
+impl LiveTag {
+    fn is_keyframe(&self) -> bool {
+        self.tag_type == 9 && is_keyframe(&self.data) && !self.is_header()
+    }
+
+    fn is_header(&self) -> bool {
+        is_header_tag(self.tag_type, &self.data)
+    }
+}
+
+/// 채널 하나의 원본 FLV 태그 방송. 송출자가 끝나 마지막 참조가 사라지면 구독자들의 수신도 끝난다.
+pub struct LiveBroadcast {
+    sender: broadcast::Sender<LiveTag>,
+    cache: Mutex<GopCache>,
+}
+
+/// 키프레임 없이 GOP 가 이보다 길어지면 캐시를 비운다.
+const MAX_GOP_TAGS: usize = 4096;
+
+#[derive(Default)]
+struct GopCache {
+    metadata: Option<LiveTag>,
+    video_header: Option<LiveTag>,
+    audio_header: Option<LiveTag>,
+    /// 마지막 키프레임부터 받은 영상, 오디오 태그
+    gop: Vec<LiveTag>,
+}
+
+impl GopCache {
+    fn update(&mut self, tag: &LiveTag) {
+        if tag.is_header() {
+            let slot = match tag.tag_type {
+                18 => &mut self.metadata,
+                9 => &mut self.video_header,
+                _ => &mut self.audio_header,
+            };
+            *slot = Some(tag.clone());
+            return;
+        }
+        if tag.is_keyframe() || self.gop.len() >= MAX_GOP_TAGS {
+            self.gop.clear();
+        }
+        // 오디오만 있는 스트림이 아니라면 GOP 는 키프레임으로 시작해야 한다.
+        if !self.gop.is_empty() || tag.is_keyframe() || self.video_header.is_none() {
+            self.gop.push(tag.clone());
+        }
+    }
+
+    fn tags(&self) -> VecDeque<LiveTag> {
+        [&self.metadata, &self.video_header, &self.audio_header].into_iter()
+            .flatten()
+            .chain(&self.gop)
+            .cloned()
+            .collect()
+    }
+}
+
+impl LiveBroadcast {
+    pub fn new(queue_size: usize) -> Self {
+        let (sender, _) = broadcast::channel(queue_size.max(1));
+        Self { sender, cache: Mutex::new(GopCache::default()) }
+    }
+
+    /// 구독자가 없거나 느려도 기다리지 않는다.
+    pub fn send(&self, tag_type: u8, timestamp: u32, data: Bytes) {
+        let tag = LiveTag { tag_type, timestamp, data };
+        // 캐시 갱신과 전송을 같은 잠금 안에서 해야 구독 시점의 캐시와 이후 태그가 겹치거나 빠지지 않는다.
+        let mut cache = self.cache.lock().unwrap();
+        cache.update(&tag);
+        let _ = self.sender.send(tag);
+    }
+
+    pub fn subscribe(&self) -> Subscription {
+        let cache = self.cache.lock().unwrap();
+        let cached = cache.tags();
+        let waiting_keyframe = cache.gop.is_empty() && cache.video_header.is_some();
+        Subscription { cached, receiver: self.sender.subscribe(), waiting_keyframe, dropped: 0 }
+    }
+}
+
+/// 구독 시점의 캐시를 먼저 내주고 이후 방송되는 태그를 이어서 내준다.
+pub struct Subscription {
+    cached: VecDeque<LiveTag>,
+    receiver: broadcast::Receiver<LiveTag>,
+    /// 태그를 놓쳤으면 디코딩이 깨지지 않도록 다음 키프레임까지 버린다.
+    waiting_keyframe: bool,
+    dropped: u64,
+}
+
+impl Subscription {
+    /// 다음 태그. 방송이 끝나면 None 을 돌려준다.
+    /// 따라가지 못해 밀려난 태그가 있으면 다음 키프레임까지 GOP 단위로 건너뛴다.
+    pub async fn next(&mut self) -> Option<LiveTag> {
+        if let Some(tag) = self.cached.pop_front() {
+            return Some(tag);
+        }
+        loop {
+            match self.receiver.recv().await {
+                Ok(tag) => {
+                    if self.waiting_keyframe && !tag.is_header() {
+                        if !tag.is_keyframe() {
+                            self.dropped += 1;
+                            continue;
+                        }
+                        self.waiting_keyframe = false;
+                    }
+                    return Some(tag);
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    self.dropped += skipped;
+                    self.waiting_keyframe = true;
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// 밀려나거나 키프레임을 기다리며 버린 태그 수
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(tag_type: u8, data: &'static [u8]) -> LiveTag {
+        LiveTag { tag_type, timestamp: 0, data: Bytes::from_static(data) }
+    }
+
+    const VIDEO_HEADER: &[u8] = &[0x17, 0];
+    const KEYFRAME: &[u8] = &[0x17, 1];
+    const INTER: &[u8] = &[0x27, 1];
+    const AUDIO_HEADER: &[u8] = &[0xaf, 0];
+    const AUDIO: &[u8] = &[0xaf, 1];
+
+    fn cached(cache: &GopCache) -> Vec<(u8, u8, u8)> {
+        cache.tags().iter().map(|t| (t.tag_type, t.data[0], t.data[1])).collect()
+    }
+
+    #[test]
+    fn caches_headers_and_gop_from_keyframe() {
+        let mut cache = GopCache::default();
+        cache.update(&tag(18, b"meta"));
+        cache.update(&tag(9, VIDEO_HEADER));
+        cache.update(&tag(8, AUDIO_HEADER));
+        // 키프레임 전의 태그는 GOP 를 시작할 수 없다.
+        cache.update(&tag(9, INTER));
+        cache.update(&tag(8, AUDIO));
+        cache.update(&tag(9, KEYFRAME));
+        cache.update(&tag(8, AUDIO));
+        cache.update(&tag(9, INTER));
+        assert_eq!(
+            cached(&cache),
+            [(18, b'm', b'e'), (9, 0x17, 0), (8, 0xaf, 0), (9, 0x17, 1), (8, 0xaf, 1), (9, 0x27, 1)]
+        );
+
+        cache.update(&tag(9, KEYFRAME));
+        assert_eq!(cached(&cache)[3..], [(9, 0x17, 1)]);
+    }
+
+    #[test]
+    fn resets_gop_without_keyframe() {
+        let mut cache = GopCache::default();
+        cache.update(&tag(9, VIDEO_HEADER));
+        cache.update(&tag(9, KEYFRAME));
+        for _ in 1..MAX_GOP_TAGS {
+            cache.update(&tag(9, INTER));
+        }
+        assert_eq!(cache.gop.len(), MAX_GOP_TAGS);
+        cache.update(&tag(9, INTER));
+        assert!(cache.gop.is_empty());
+        assert_eq!(cached(&cache), [(9, 0x17, 0)]);
+    }
+
+    #[test]
+    fn caches_audio_only_stream() {
+        let mut cache = GopCache::default();
+        cache.update(&tag(8, AUDIO_HEADER));
+        cache.update(&tag(8, AUDIO));
+        cache.update(&tag(8, AUDIO));
+        assert_eq!(cached(&cache), [(8, 0xaf, 0), (8, 0xaf, 1), (8, 0xaf, 1)]);
+    }
+
+    #[tokio::test]
+    async fn skips_to_next_keyframe_after_lag() {
+        let broadcast = LiveBroadcast::new(2);
+        broadcast.send(9, 0, Bytes::from_static(VIDEO_HEADER));
+        broadcast.send(9, 0, Bytes::from_static(KEYFRAME));
+        let mut subscription = broadcast.subscribe();
+        assert_eq!(subscription.next().await.unwrap().data, VIDEO_HEADER);
+        assert_eq!(subscription.next().await.unwrap().data, KEYFRAME);
+
+        // 큐보다 많이 밀려 앞의 태그를 놓쳤다.
+        for timestamp in 1..=3 {
+            broadcast.send(9, timestamp, Bytes::from_static(INTER));
+        }
+        broadcast.send(9, 4, Bytes::from_static(KEYFRAME));
+        let next = subscription.next().await.unwrap();
+        assert_eq!((next.timestamp, &next.data[..]), (4, KEYFRAME));
+        assert_eq!(subscription.dropped(), 3);
+
+        broadcast.send(9, 5, Bytes::from_static(INTER));
+        assert_eq!(subscription.next().await.unwrap().timestamp, 5);
+    }
+}
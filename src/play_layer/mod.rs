@@ -0,0 +1,11 @@
+/*
+ 재생 레이어 (play_layer)
+ 송출자에게서 받은 FLV 태그를 채널별로 메모리에서 방송해, 변환을 거치지 않은 원본을 바로 받아가려는 재생자에게 보낸다.
+ 새 재생자가 곧바로 시작할 수 있도록 메타데이터, 코덱 설정과 마지막 키프레임부터의 GOP 를 기억해 둔다.
+ */
+pub mod http_flv;
+pub mod live_broadcast;
+pub mod play_error;
+pub mod rtmp_player;
+pub mod session_stream;
+pub mod stream_hub;
@@ -0,0 +1,41 @@
+use std::fmt;
+
+/// RTMP 재생 세션 실패 원인
+#[derive(Debug)]
+pub enum PlayError {
+    Io(std::io::Error),
+    /// RTMP 메시지를 해석할 수 없다.
+    Protocol(String),
+    /// 재생하려는 채널이 송출 중이 아니다.
+    StreamNotFound(String),
+}
+
+impl fmt::Display for PlayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlayError::Io(e) => write!(f, "io error: {}", e),
+            PlayError::Protocol(reason) => write!(f, "protocol error: {}", reason),
+            PlayError::StreamNotFound(name) => write!(f, "stream not found: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for PlayError {}
+
+impl From<std::io::Error> for PlayError {
+    fn from(e: std::io::Error) -> Self {
+        PlayError::Io(e)
+    }
+}
+
+impl From<scuffle_rtmp::error::RtmpError> for PlayError {
+    fn from(e: scuffle_rtmp::error::RtmpError) -> Self {
+        PlayError::Protocol(e.to_string())
+    }
+}
+
+impl From<scuffle_amf0::Amf0Error> for PlayError {
+    fn from(e: scuffle_amf0::Amf0Error) -> Self {
+        PlayError::Protocol(e.to_string())
+    }
+}
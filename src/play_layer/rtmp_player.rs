@@ -0,0 +1,155 @@
+use bytes::BytesMut;
+use scuffle_rtmp::chunk::reader::ChunkReader;
+use scuffle_rtmp::chunk::writer::ChunkWriter;
+use scuffle_rtmp::chunk::{Chunk, CHUNK_SIZE, CHUNK_STREAM_ID_AUDIO, CHUNK_STREAM_ID_COMMAND, CHUNK_STREAM_ID_VIDEO};
+use scuffle_rtmp::messages::MessageType;
+use scuffle_rtmp::user_control_messages::EventMessageStreamBegin;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::metrics_layer::server_metrics::ACTIVE_PLAYERS;
+use crate::play_layer::live_broadcast::{LiveTag, Subscription};
+use crate::play_layer::play_error::PlayError;
+use crate::play_layer::session_stream::{SessionStream, HANDSHAKE_LEN};
+use crate::play_layer::stream_hub::StreamHub;
+use crate::utils::rtmp_command::{encode_on_status, read_chunk_size, RtmpCommand};
+
+/// scuffle-rtmp 가 명령, 오디오, 영상에 3, 4, 5 를 쓰므로 메타데이터는 6 번 chunk stream 으로 보낸다.
+const CHUNK_STREAM_ID_DATA: u32 = 6;
+
+/// scuffle-rtmp 세션이 play 명령에서 끝난 연결을 이어받아 채널의 원본 태그를 보낸다.
+pub async fn serve_play(stream: SessionStream, hub: &StreamHub) -> Result<(), PlayError> {
+    let (mut player, name) = RtmpPlayer::resume(stream)?;
+    let Some(broadcast) = hub.get(&name) else {
+        player.send_status("error", "NetStream.Play.StreamNotFound", &format!("{} is not live", name)).await?;
+        return Err(PlayError::StreamNotFound(name));
+    };
+    // 방송의 참조를 들고 있지 않아야 송출이 끝날 때 수신도 끝난다.
+    let mut subscription = broadcast.subscribe();
+    drop(broadcast);
+
+    tracing::info!(name, "Play started");
+    ACTIVE_PLAYERS.inc();
+    let result = player.play(&mut subscription).await;
+    ACTIVE_PLAYERS.dec();
+    tracing::info!(name, dropped_tags = subscription.dropped(), "Play ended");
+    result
+}
+
+struct RtmpPlayer {
+    stream: TcpStream,
+    reader: ChunkReader,
+    writer: ChunkWriter,
+    read_buf: BytesMut,
+    /// play 명령이 온 메시지 스트림 id
+    stream_id: u32,
+}
+
+impl RtmpPlayer {
+    /// 세션이 읽은 바이트를 처음부터 다시 해석해 play 명령을 찾는다.
+    /// 그 뒤에 이미 읽힌 바이트는 read_buf 에 남겨 이어서 처리한다.
+    fn resume(stream: SessionStream) -> Result<(Self, String), PlayError> {
+        let (stream, recorded) = stream.into_parts();
+        let recorded = recorded
+            .filter(|recorded| recorded.len() >= HANDSHAKE_LEN)
+            .ok_or_else(|| PlayError::Protocol("play command arrived after the recorded bytes".to_string()))?;
+        let mut player = Self {
+            stream,
+            reader: ChunkReader::default(),
+            writer: ChunkWriter::default(),
+            read_buf: BytesMut::from(&recorded[HANDSHAKE_LEN..]),
+            stream_id: 0,
+        };
+        // 세션이 핸드셰이크 직후 보낸 SetChunkSize 와 맞춘다.
+        player.writer.set_chunk_size(CHUNK_SIZE);
+
+        while let Some(chunk) = player.reader.read_chunk(&mut player.read_buf)? {
+            let msg_stream_id = chunk.message_header.msg_stream_id;
+            if let Some(command) = player.handle_chunk(chunk)?
+                && command.name == "play"
+            {
+                let name = command.string_arg(0)
+                    .ok_or_else(|| PlayError::Protocol("play command without a stream name".to_string()))?;
+                // 재생 URL 에 붙은 쿼리는 채널 이름이 아니다.
+                let name = name.split('?').next().unwrap_or(name).to_string();
+                player.stream_id = msg_stream_id;
+                return Ok((player, name));
+            }
+        }
+        Err(PlayError::Protocol("play command not found".to_string()))
+    }
+
+    /// 캐시된 태그부터 보내고, 송출이 끝나거나 재생자가 스트림을 닫을 때까지 이어서 보낸다.
+    async fn play(&mut self, subscription: &mut Subscription) -> Result<(), PlayError> {
+        let mut buf = Vec::new();
+        EventMessageStreamBegin { stream_id: self.stream_id }.write(&self.writer, &mut buf)?;
+        self.stream.write_all(&buf).await?;
+        self.send_status("status", "NetStream.Play.Reset", "Playing and resetting stream").await?;
+        self.send_status("status", "NetStream.Play.Start", "Started playing stream").await?;
+
+        loop {
+            tokio::select! {
+                tag = subscription.next() => match tag {
+                    Some(tag) => self.send_tag(tag).await?,
+                    None => {
+                        self.send_status("status", "NetStream.Play.UnpublishNotify", "Stream is now unpublished").await?;
+                        return Ok(());
+                    }
+                },
+                read = self.stream.read_buf(&mut self.read_buf) => {
+                    if read? == 0 || !self.handle_incoming()? {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    /// 재생자가 보낸 메시지를 처리한다. 스트림을 닫았으면 false 를 돌려준다.
+    fn handle_incoming(&mut self) -> Result<bool, PlayError> {
+        while let Some(chunk) = self.reader.read_chunk(&mut self.read_buf)? {
+            if let Some(command) = self.handle_chunk(chunk)?
+                && matches!(command.name.as_str(), "deleteStream" | "closeStream")
+            {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// 제어 메시지를 처리하고, AMF0 명령이면 해석해서 돌려준다.
+    fn handle_chunk(&mut self, chunk: Chunk) -> Result<Option<RtmpCommand>, PlayError> {
+        match chunk.message_header.msg_type_id {
+            MessageType::SetChunkSize => {
+                let size = read_chunk_size(&chunk.payload)
+                    .ok_or_else(|| PlayError::Protocol("short SetChunkSize message".to_string()))?;
+                if !self.reader.update_max_chunk_size(size) {
+                    return Err(PlayError::Protocol(format!("unsupported chunk size {}", size)));
+                }
+                Ok(None)
+            }
+            MessageType::CommandAMF0 => Ok(RtmpCommand::decode(&chunk.payload)?),
+            _ => Ok(None),
+        }
+    }
+
+    async fn send_tag(&mut self, tag: LiveTag) -> Result<(), PlayError> {
+        let (chunk_stream_id, message_type) = match tag.tag_type {
+            8 => (CHUNK_STREAM_ID_AUDIO, MessageType::Audio),
+            9 => (CHUNK_STREAM_ID_VIDEO, MessageType::Video),
+            _ => (CHUNK_STREAM_ID_DATA, MessageType::DataAMF0),
+        };
+        self.write_chunk(Chunk::new(chunk_stream_id, tag.timestamp, message_type, self.stream_id, tag.data)).await
+    }
+
+    async fn send_status(&mut self, level: &str, code: &str, description: &str) -> Result<(), PlayError> {
+        let payload = encode_on_status(level, code, description)?;
+        self.write_chunk(Chunk::new(CHUNK_STREAM_ID_COMMAND, 0, MessageType::CommandAMF0, self.stream_id, payload)).await
+    }
+
+    async fn write_chunk(&mut self, chunk: Chunk) -> Result<(), PlayError> {
+        let mut buf = Vec::new();
+        self.writer.write_chunk(&mut buf, chunk)?;
+        self.stream.write_all(&buf).await?;
+        Ok(())
+    }
+}
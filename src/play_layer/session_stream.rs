@@ -0,0 +1,171 @@
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use scuffle_rtmp::chunk::writer::ChunkWriter;
+use scuffle_rtmp::chunk::{Chunk, CHUNK_STREAM_ID_COMMAND, INIT_CHUNK_SIZE};
+use scuffle_rtmp::messages::MessageType;
+use scuffle_rtmp::protocol_control_messages::ProtocolControlMessageSetChunkSize;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+
+use crate::utils::rtmp_command::encode_on_status;
+
+/// 핸드셰이크(C0, C1, C2) 크기
+pub const HANDSHAKE_LEN: usize = 1 + 1536 * 2;
+/// play 명령은 핸드셰이크 직후 connect, createStream 다음에 오므로 이 이상은 기록하지 않는다.
+const RECORD_LIMIT: usize = HANDSHAKE_LEN + 16 * 1024;
+
+/// scuffle-rtmp 0.2 의 세션은 play 명령을 받으면 PlayNotSupported 로 끝난다.
+/// 그때 같은 연결에서 재생을 이어받을 수 있도록 세션이 읽은 앞부분을 기록해 둔다.
+/// 송출자는 금방 한도를 넘으므로 기록을 버린다.
+pub struct SessionStream {
+    inner: TcpStream,
+    recorded: Option<Vec<u8>>,
+    closer: SessionCloser,
+    /// 닫힌 뒤 아직 보내지 못한 바이트
+    pending: Vec<u8>,
+}
+
+impl SessionStream {
+    pub fn new(inner: TcpStream) -> Self {
+        Self { inner, recorded: Some(Vec::new()), closer: SessionCloser::default(), pending: Vec::new() }
+    }
+
+    pub fn closer(&self) -> SessionCloser {
+        self.closer.clone()
+    }
+
+    /// 연결과 지금까지 세션이 읽은 바이트. 한도를 넘었으면 None 이다.
+    pub fn into_parts(self) -> (TcpStream, Option<Vec<u8>>) {
+        (self.inner, self.recorded)
+    }
+
+    /// 닫을 때 남긴 메시지를 모두 보낸다.
+    fn poll_send_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.pending.append(&mut self.closer.0.lock().unwrap().outbox);
+        while !self.pending.is_empty() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending))?;
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.pending.drain(..n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// scuffle-rtmp 0.2 의 SessionHandler 는 onStatus 를 보낼 수도, 세션을 끝낼 수도 없다.
+/// 핸들러가 이것으로 연결을 닫으면 SessionStream 이 남긴 메시지를 보내고 읽기를 EOF 로 끝내서
+/// 세션이 클라이언트가 끊은 것처럼 정상 종료된다. 그 뒤 세션이 쓰는 응답은 버린다.
+#[derive(Clone, Default)]
+pub struct SessionCloser(Arc<Mutex<Closing>>);
+
+#[derive(Default)]
+struct Closing {
+    closed: bool,
+    outbox: Vec<u8>,
+}
+
+impl SessionCloser {
+    pub fn close(&self) {
+        self.0.lock().unwrap().closed = true;
+    }
+
+    /// publish 에 error 수준 onStatus 로 답하고 닫는다. 세션이 뒤이어 쓰는 NetStream.Publish.Start 는 나가지 않는다.
+    /// 세션이 정한 chunk 크기에 기대지 않도록 기본 크기로 되돌린 뒤 쓴다.
+    pub fn reject_publish(&self, stream_id: u32, code: &str, description: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut closing = self.0.lock().unwrap();
+        closing.closed = true;
+        let writer = ChunkWriter::default();
+        let mut buf = Vec::new();
+        ProtocolControlMessageSetChunkSize { chunk_size: INIT_CHUNK_SIZE as u32 }.write(&mut buf, &writer)?;
+        let payload = encode_on_status("error", code, description)?;
+        writer.write_chunk(&mut buf, Chunk::new(CHUNK_STREAM_ID_COMMAND, 0, MessageType::CommandAMF0, stream_id, payload))?;
+        closing.outbox.extend_from_slice(&buf);
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.0.lock().unwrap().closed
+    }
+}
+
+impl AsyncRead for SessionStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.closer.is_closed() {
+            return this.poll_send_pending(cx);
+        }
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Some(recorded) = &mut this.recorded {
+            recorded.extend_from_slice(&buf.filled()[filled..]);
+            if recorded.len() > RECORD_LIMIT {
+                this.recorded = None;
+            }
+        }
+        result
+    }
+}
+
+impl AsyncWrite for SessionStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.closer.is_closed() {
+            ready!(this.poll_send_pending(cx))?;
+            return Poll::Ready(Ok(buf.len()));
+        }
+        Pin::new(&mut this.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.closer.is_closed() {
+            ready!(this.poll_send_pending(cx))?;
+        }
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scuffle_rtmp::chunk::reader::ChunkReader;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use crate::utils::rtmp_command::RtmpCommand;
+
+    #[tokio::test]
+    async fn rejects_publish_then_ends_session() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap());
+        let (client, accepted) = tokio::join!(client, listener.accept());
+        let mut client = client.unwrap();
+        let mut stream = SessionStream::new(accepted.unwrap().0);
+
+        stream.closer().reject_publish(1, "NetStream.Publish.BadName", "stream key rejected").unwrap();
+        // 세션이 이어서 쓰는 응답은 버리고, 다음 읽기는 EOF 다.
+        stream.write_all(b"publish start").await.unwrap();
+        stream.flush().await.unwrap();
+        assert_eq!(stream.read(&mut [0; 16]).await.unwrap(), 0);
+        drop(stream);
+
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).await.unwrap();
+        let mut buf = bytes::BytesMut::from(&received[..]);
+        let mut reader = ChunkReader::default();
+        let set_chunk_size = reader.read_chunk(&mut buf).unwrap().unwrap();
+        assert_eq!(set_chunk_size.message_header.msg_type_id, MessageType::SetChunkSize);
+        let status = reader.read_chunk(&mut buf).unwrap().unwrap();
+        assert_eq!(status.message_header.msg_stream_id, 1);
+        let command = RtmpCommand::decode(&status.payload).unwrap().unwrap();
+        assert_eq!(command.name, "onStatus");
+        assert_eq!(command.status_field("code").as_deref(), Some("NetStream.Publish.BadName"));
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,35 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::play_layer::live_broadcast::LiveBroadcast;
+
+/// 송출 중인 채널의 원본 방송을 재생자와 공유한다.
+/// 채널 키는 LiveRegistry 와 같이 인증된 경로의 닉네임 부분이다.
+pub struct StreamHub {
+    broadcasts: Mutex<HashMap<String, Arc<LiveBroadcast>>>,
+    queue_size: usize,
+}
+
+impl StreamHub {
+    pub fn new(queue_size: usize) -> Self {
+        Self { broadcasts: Mutex::new(HashMap::new()), queue_size }
+    }
+
+    /// 같은 채널에 이전 방송이 있으면 새 방송으로 바꾼다.
+    pub fn publish(&self, channel: &str) -> Arc<LiveBroadcast> {
+        let broadcast = Arc::new(LiveBroadcast::new(self.queue_size));
+        self.broadcasts.lock().unwrap().insert(channel.to_string(), broadcast.clone());
+        broadcast
+    }
+
+    /// 같은 채널에 다른 송출이 이미 등록되었다면 그대로 둔다.
+    pub fn unpublish(&self, channel: &str, broadcast: &Arc<LiveBroadcast>) {
+        let mut broadcasts = self.broadcasts.lock().unwrap();
+        if broadcasts.get(channel).is_some_and(|b| Arc::ptr_eq(b, broadcast)) {
+            broadcasts.remove(channel);
+        }
+    }
+
+    pub fn get(&self, channel: &str) -> Option<Arc<LiveBroadcast>> {
+        self.broadcasts.lock().unwrap().get(channel).cloned()
+    }
+}
@@ -0,0 +1,8 @@
+/*
+ 릴레이 레이어 (relay_layer)
+ 송출자에게서 받은 FLV 태그를 설정되거나 인증 서버가 내려준 외부 RTMP 서버로 다시 송출한다.
+ 대상마다 별도 작업과 큐를 두어 느리거나 끊긴 대상이 HLS 변환이나 다른 대상에 영향을 주지 않는다.
+ */
+pub mod relay;
+pub mod relay_error;
+mod rtmp_publisher;
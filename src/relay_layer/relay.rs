@@ -0,0 +1,249 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use bytes::Bytes;
+use serde::Serialize;
+use tokio::sync::mpsc;
+use tracing::Instrument;
+use crate::config::{RelayConfig, RelayTargetConfig};
+use crate::relay_layer::relay_error::RelayError;
+use crate::relay_layer::rtmp_publisher::{RtmpPublisher, RtmpUrl};
+use crate::utils::flv_tag::{is_aac_sequence_header, is_avc_sequence_header, is_keyframe};
+
+/// 관리자 API 에 노출하는 릴레이 대상 하나의 상태
+#[derive(Debug, Clone, Serialize)]
+pub struct RelayStatus {
+    pub name: String,
+    /// 스트림 키를 뺀 대상 주소
+    pub destination: String,
+    pub state: RelayState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub reconnects: u32,
+    pub bytes_sent: u64,
+    /// 큐가 가득 차서 버린 태그 수
+    pub dropped_tags: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RelayState {
+    Connecting,
+    Publishing,
+    /// 연결이 끊겨 재연결을 기다리는 중
+    Retrying,
+    Stopped,
+}
+
+struct RelayTag {
+    tag_type: u8,
+    timestamp: u32,
+    data: Bytes,
+}
+
+/// 릴레이 대상 하나. drop 하면 대상 서버에 송출 종료를 알리고 작업이 끝난다.
+pub struct Relay {
+    sender: mpsc::Sender<RelayTag>,
+    status: Arc<Mutex<RelayStatus>>,
+    /// 태그를 버렸으면 작업이 다음 키프레임부터 다시 보내도록 알린다.
+    resync: Arc<AtomicBool>,
+}
+
+impl Relay {
+    /// tokio 런타임 안에서 호출해야 한다.
+    pub fn spawn(target: &RelayTargetConfig, config: &RelayConfig) -> Result<Self, RelayError> {
+        let url = RtmpUrl::parse(&target.url)?;
+        let status = Arc::new(Mutex::new(RelayStatus {
+            name: target.name.clone(),
+            destination: url.destination().to_string(),
+            state: RelayState::Connecting,
+            last_error: None,
+            reconnects: 0,
+            bytes_sent: 0,
+            dropped_tags: 0,
+        }));
+        let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+        let resync = Arc::new(AtomicBool::new(false));
+
+        let worker = RelayWorker {
+            url,
+            receiver,
+            status: status.clone(),
+            resync: resync.clone(),
+            headers: SequenceHeaders::default(),
+            connect_timeout: Duration::from_millis(config.connect_timeout),
+            retry_backoff: Duration::from_millis(config.retry_backoff),
+            max_backoff: Duration::from_millis(config.max_backoff),
+            write_timeout: Duration::from_millis(config.write_timeout),
+        };
+        let span = tracing::info_span!("relay", name = %target.name);
+        tokio::spawn(worker.run().instrument(span));
+        Ok(Self { sender, status, resync })
+    }
+
+    /// 대상이 느리거나 끊겨 있어도 기다리지 않는다.
+    pub fn send(&self, tag_type: u8, timestamp: u32, data: Bytes) {
+        if self.sender.try_send(RelayTag { tag_type, timestamp, data }).is_err() {
+            self.status.lock().unwrap().dropped_tags += 1;
+            self.resync.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn status(&self) -> Arc<Mutex<RelayStatus>> {
+        self.status.clone()
+    }
+}
+
+/// 재연결한 대상 서버가 바로 디코딩할 수 있도록 마지막 메타데이터와 코덱 설정을 기억해 둔다.
+#[derive(Default)]
+struct SequenceHeaders {
+    metadata: Option<RelayTag>,
+    video: Option<RelayTag>,
+    audio: Option<RelayTag>,
+}
+
+impl SequenceHeaders {
+    fn update(&mut self, tag: &RelayTag) {
+        let slot = match tag.tag_type {
+            18 => &mut self.metadata,
+            9 if is_avc_sequence_header(&tag.data) => &mut self.video,
+            8 if is_aac_sequence_header(&tag.data) => &mut self.audio,
+            _ => return,
+        };
+        *slot = Some(RelayTag { tag_type: tag.tag_type, timestamp: tag.timestamp, data: tag.data.clone() });
+    }
+
+    fn tags(&self) -> impl Iterator<Item = &RelayTag> {
+        [&self.metadata, &self.video, &self.audio].into_iter().flatten()
+    }
+}
+
+struct RelayWorker {
+    url: RtmpUrl,
+    receiver: mpsc::Receiver<RelayTag>,
+    status: Arc<Mutex<RelayStatus>>,
+    resync: Arc<AtomicBool>,
+    headers: SequenceHeaders,
+    connect_timeout: Duration,
+    retry_backoff: Duration,
+    max_backoff: Duration,
+    write_timeout: Duration,
+}
+
+impl RelayWorker {
+    /// 연결이 끊기면 retry_backoff 부터 두 배씩 늘려가며 다시 연결한다.
+    /// 송출자가 끊겨 큐가 닫히면 끝난다.
+    async fn run(mut self) {
+        let mut backoff = self.retry_backoff;
+        loop {
+            self.set_state(RelayState::Connecting, None);
+            let connected = tokio::time::timeout(self.connect_timeout, RtmpPublisher::connect(&self.url, self.write_timeout)).await
+                .unwrap_or(Err(RelayError::Timeout));
+            let error = match connected {
+                Ok(publisher) => {
+                    backoff = self.retry_backoff;
+                    tracing::info!(destination = self.url.destination(), "Relay publishing");
+                    self.set_state(RelayState::Publishing, None);
+                    match self.forward(publisher).await {
+                        Some(error) => error,
+                        None => break,
+                    }
+                }
+                Err(error) => error,
+            };
+
+            tracing::warn!(destination = self.url.destination(), %error, retry_in_ms = backoff.as_millis() as u64, "Relay failed");
+            self.set_state(RelayState::Retrying, Some(error.to_string()));
+            if !self.wait(backoff).await {
+                break;
+            }
+            backoff = (backoff * 2).min(self.max_backoff);
+            self.status.lock().unwrap().reconnects += 1;
+        }
+        self.set_state(RelayState::Stopped, None);
+    }
+
+    /// 큐의 태그를 대상 서버로 보낸다. 연결이 끊기면 오류를, 큐가 닫히면 None 을 돌려준다.
+    async fn forward(&mut self, mut publisher: RtmpPublisher) -> Option<RelayError> {
+        for tag in self.headers.tags() {
+            if let Err(e) = publisher.send_tag(tag.tag_type, tag.timestamp, tag.data.clone()).await {
+                return Some(e);
+            }
+        }
+
+        // 새 연결은 키프레임부터 보내야 대상 서버가 디코딩할 수 있다.
+        let mut waiting_keyframe = true;
+        while let Some(tag) = self.receiver.recv().await {
+            self.headers.update(&tag);
+            if self.resync.swap(false, Ordering::Relaxed) {
+                waiting_keyframe = true;
+            }
+            if waiting_keyframe && tag.tag_type != 18 {
+                if tag.tag_type == 9 && is_keyframe(&tag.data) {
+                    waiting_keyframe = false;
+                } else {
+                    continue;
+                }
+            }
+
+            let size = tag.data.len() as u64;
+            if let Err(e) = publisher.send_tag(tag.tag_type, tag.timestamp, tag.data).await {
+                return Some(e);
+            }
+            self.status.lock().unwrap().bytes_sent += size;
+        }
+        publisher.close().await;
+        None
+    }
+
+    /// 재연결을 기다리는 동안에도 큐를 비워 송출자 쪽이 태그를 버리지 않게 하고 코덱 설정은 갱신한다.
+    /// 큐가 닫히면 false 를 돌려준다.
+    async fn wait(&mut self, duration: Duration) -> bool {
+        let sleep = tokio::time::sleep(duration);
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep => return true,
+                tag = self.receiver.recv() => match tag {
+                    Some(tag) => self.headers.update(&tag),
+                    None => return false,
+                },
+            }
+        }
+    }
+
+    fn set_state(&self, state: RelayState, error: Option<String>) {
+        let mut status = self.status.lock().unwrap();
+        status.state = state;
+        if error.is_some() {
+            status.last_error = error;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::relay_layer::rtmp_publisher::tests::spawn_server;
+
+    #[tokio::test]
+    async fn reconnect_after_write_timeout() {
+        let (url, _received) = spawn_server(true).await;
+        let target = RelayTargetConfig { name: "stalled".to_string(), url };
+        let config = RelayConfig { write_timeout: 200, retry_backoff: 50, ..RelayConfig::default() };
+        let relay = Relay::spawn(&target, &config).unwrap();
+
+        let keyframe = Bytes::from(vec![0x17; 64 * 1024]);
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+        loop {
+            let status = relay.status().lock().unwrap().clone();
+            if status.reconnects > 0 {
+                assert_eq!(status.last_error.as_deref(), Some("timed out"));
+                break;
+            }
+            assert!(tokio::time::Instant::now() < deadline, "relay did not reconnect: {:?}", status);
+            relay.send(9, 0, keyframe.clone());
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt;
+
+/// 릴레이 대상 연결, 송출 실패 원인
+#[derive(Debug)]
+pub enum RelayError {
+    /// 대상 URL 을 해석할 수 없다.
+    InvalidUrl(String),
+    Io(std::io::Error),
+    /// 대상 서버가 connect, createStream, publish 를 거절했다.
+    Rejected(String),
+    /// RTMP 메시지를 해석할 수 없다.
+    Protocol(String),
+    Timeout,
+}
+
+impl fmt::Display for RelayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayError::InvalidUrl(reason) => write!(f, "invalid relay url: {}", reason),
+            RelayError::Io(e) => write!(f, "io error: {}", e),
+            RelayError::Rejected(reason) => write!(f, "rejected by destination: {}", reason),
+            RelayError::Protocol(reason) => write!(f, "protocol error: {}", reason),
+            RelayError::Timeout => write!(f, "timed out"),
+        }
+    }
+}
+
+impl std::error::Error for RelayError {}
+
+impl From<std::io::Error> for RelayError {
+    fn from(e: std::io::Error) -> Self {
+        RelayError::Io(e)
+    }
+}
+
+impl From<scuffle_rtmp::error::RtmpError> for RelayError {
+    fn from(e: scuffle_rtmp::error::RtmpError) -> Self {
+        RelayError::Protocol(e.to_string())
+    }
+}
+
+impl From<scuffle_amf0::Amf0Error> for RelayError {
+    fn from(e: scuffle_amf0::Amf0Error) -> Self {
+        RelayError::Protocol(e.to_string())
+    }
+}
@@ -0,0 +1,367 @@
+use std::io;
+use std::time::Duration;
+use bytes::{Bytes, BytesMut};
+use ring::rand::{SecureRandom, SystemRandom};
+use scuffle_amf0::{Amf0Object, Amf0Value};
+use scuffle_rtmp::chunk::reader::ChunkReader;
+use scuffle_rtmp::chunk::writer::ChunkWriter;
+use scuffle_rtmp::chunk::{Chunk, CHUNK_STREAM_ID_AUDIO, CHUNK_STREAM_ID_COMMAND, CHUNK_STREAM_ID_VIDEO};
+use scuffle_rtmp::messages::MessageType;
+use scuffle_rtmp::protocol_control_messages::ProtocolControlMessageSetChunkSize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::relay_layer::relay_error::RelayError;
+use crate::utils::rtmp_command::{read_chunk_size, RtmpCommand};
+
+/// rtmp://host[:port]/app/stream_key 를 나눈 것
+pub struct RtmpUrl {
+    host: String,
+    port: u16,
+    /// 스트림 키를 뺀 "rtmp://host[:port]/app"
+    tc_url: String,
+    app: String,
+    stream_key: String,
+}
+
+impl RtmpUrl {
+    pub fn parse(url: &str) -> Result<Self, RelayError> {
+        let invalid = |reason: &str| RelayError::InvalidUrl(reason.to_string());
+        let rest = url.strip_prefix("rtmp://").ok_or_else(|| invalid("only rtmp:// urls are supported"))?;
+        let (authority, path) = rest.split_once('/').ok_or_else(|| invalid("missing app"))?;
+        let (app, stream_key) = path.rsplit_once('/').ok_or_else(|| invalid("missing stream key"))?;
+        if authority.is_empty() || app.is_empty() || stream_key.is_empty() {
+            return Err(invalid("expected rtmp://host[:port]/app/stream_key"));
+        }
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !authority.ends_with(']') => {
+                (host, port.parse().map_err(|_| invalid("invalid port"))?)
+            }
+            _ => (authority, 1935),
+        };
+        Ok(Self {
+            host: host.trim_start_matches('[').trim_end_matches(']').to_string(),
+            port,
+            tc_url: format!("rtmp://{}/{}", authority, app),
+            app: app.to_string(),
+            stream_key: stream_key.to_string(),
+        })
+    }
+
+    /// 스트림 키가 노출되지 않도록 로그와 관리자 API 에는 이 주소만 쓴다.
+    pub fn destination(&self) -> &str {
+        &self.tc_url
+    }
+}
+
+/// 외부 RTMP 서버에 publish 하는 최소한의 클라이언트.
+/// 단순 핸드셰이크 후 connect, createStream, publish 를 보내고 이후에는 FLV 태그를 그대로 전달한다.
+/// 대상 서버가 읽지 않아 write_timeout 안에 보내지 못하면 Timeout 으로 실패한다.
+pub struct RtmpPublisher {
+    stream: TcpStream,
+    writer: ChunkWriter,
+    reader: ChunkReader,
+    read_buf: BytesMut,
+    /// createStream 으로 받은 메시지 스트림 id
+    stream_id: u32,
+    stream_key: String,
+    next_transaction: f64,
+    write_timeout: Duration,
+}
+
+const RTMP_VERSION: u8 = 3;
+const HANDSHAKE_SIZE: usize = 1536;
+const CHUNK_SIZE: u32 = 4096;
+/// scuffle-rtmp 가 명령, 오디오, 영상에 3, 4, 5 를 쓰므로 메타데이터는 6 번 chunk stream 으로 보낸다.
+const CHUNK_STREAM_ID_DATA: u32 = 6;
+
+impl RtmpPublisher {
+    /// 핸드셰이크부터 NetStream.Publish.Start 를 받을 때까지 진행한다.
+    pub async fn connect(url: &RtmpUrl, write_timeout: Duration) -> Result<Self, RelayError> {
+        let stream = TcpStream::connect((url.host.as_str(), url.port)).await?;
+        stream.set_nodelay(true)?;
+        let mut publisher = Self {
+            stream,
+            writer: ChunkWriter::default(),
+            reader: ChunkReader::default(),
+            read_buf: BytesMut::new(),
+            stream_id: 0,
+            stream_key: url.stream_key.clone(),
+            next_transaction: 1.0,
+            write_timeout,
+        };
+        publisher.handshake().await?;
+        publisher.set_chunk_size().await?;
+
+        let connect: Amf0Object = [
+            ("app".into(), Amf0Value::String(url.app.clone().into())),
+            ("type".into(), Amf0Value::String("nonprivate".into())),
+            ("flashVer".into(), Amf0Value::String("FMLE/3.0 (compatible; pang-streaming-server)".into())),
+            ("tcUrl".into(), Amf0Value::String(url.tc_url.clone().into())),
+        ].into_iter().collect();
+        let transaction = publisher.command(0, "connect", Amf0Value::Object(connect), &[]).await?;
+        publisher.wait_result(transaction).await?;
+
+        let stream_key = Amf0Value::String(url.stream_key.clone().into());
+        // 일부 서비스는 releaseStream/FCPublish 를 기대하지만 응답은 기다리지 않는다.
+        publisher.command(0, "releaseStream", Amf0Value::Null, std::slice::from_ref(&stream_key)).await?;
+        publisher.command(0, "FCPublish", Amf0Value::Null, std::slice::from_ref(&stream_key)).await?;
+        let transaction = publisher.command(0, "createStream", Amf0Value::Null, &[]).await?;
+        let result = publisher.wait_result(transaction).await?;
+        publisher.stream_id = match result.last() {
+            Some(Amf0Value::Number(stream_id)) => *stream_id as u32,
+            _ => return Err(RelayError::Protocol("createStream result has no stream id".to_string())),
+        };
+
+        let live = Amf0Value::String("live".into());
+        publisher.command(publisher.stream_id, "publish", Amf0Value::Null, &[stream_key, live]).await?;
+        publisher.wait_publish_start().await?;
+        Ok(publisher)
+    }
+
+    /// FLV 태그 하나를 보낸다. 대상 서버가 보낸 메시지도 함께 읽어 소켓 버퍼가 차지 않게 한다.
+    pub async fn send_tag(&mut self, tag_type: u8, timestamp: u32, data: Bytes) -> Result<(), RelayError> {
+        let (chunk_stream_id, message_type) = match tag_type {
+            8 => (CHUNK_STREAM_ID_AUDIO, MessageType::Audio),
+            9 => (CHUNK_STREAM_ID_VIDEO, MessageType::Video),
+            _ => (CHUNK_STREAM_ID_DATA, MessageType::DataAMF0),
+        };
+        self.write_chunk(Chunk::new(chunk_stream_id, timestamp, message_type, self.stream_id, data)).await?;
+        self.drain_incoming()
+    }
+
+    /// 대상 서버에 송출 종료를 알린다. 실패해도 연결은 닫힌다.
+    pub async fn close(mut self) {
+        let stream_key = Amf0Value::String(self.stream_key.clone().into());
+        let _ = self.command(0, "FCUnpublish", Amf0Value::Null, &[stream_key]).await;
+        let stream_id = Amf0Value::Number(self.stream_id as f64);
+        let _ = self.command(0, "deleteStream", Amf0Value::Null, &[stream_id]).await;
+        let _ = self.stream.shutdown().await;
+    }
+
+    /// C1 의 버전 필드를 0 으로 두어 서버가 단순 핸드셰이크로 처리하게 한다.
+    async fn handshake(&mut self) -> Result<(), RelayError> {
+        let mut c0c1 = vec![0u8; 1 + HANDSHAKE_SIZE];
+        c0c1[0] = RTMP_VERSION;
+        SystemRandom::new()
+            .fill(&mut c0c1[9..])
+            .map_err(|_| RelayError::Protocol("failed to generate handshake bytes".to_string()))?;
+        self.stream.write_all(&c0c1).await?;
+
+        let mut s0s1s2 = vec![0u8; 1 + HANDSHAKE_SIZE * 2];
+        self.stream.read_exact(&mut s0s1s2).await?;
+        if s0s1s2[0] != RTMP_VERSION {
+            return Err(RelayError::Protocol(format!("unsupported RTMP version {}", s0s1s2[0])));
+        }
+        // C2 는 S1 을 그대로 돌려준다.
+        self.stream.write_all(&s0s1s2[1..=HANDSHAKE_SIZE]).await?;
+        Ok(())
+    }
+
+    async fn set_chunk_size(&mut self) -> Result<(), RelayError> {
+        let mut buf = Vec::new();
+        ProtocolControlMessageSetChunkSize { chunk_size: CHUNK_SIZE }.write(&mut buf, &self.writer)?;
+        self.stream.write_all(&buf).await?;
+        self.writer.set_chunk_size(CHUNK_SIZE as usize);
+        Ok(())
+    }
+
+    /// 명령을 보내고 응답을 맞춰볼 transaction id 를 돌려준다.
+    async fn command(&mut self, msg_stream_id: u32, name: &str, object: Amf0Value<'_>, args: &[Amf0Value<'_>]) -> Result<f64, RelayError> {
+        let transaction = self.next_transaction;
+        self.next_transaction += 1.0;
+
+        let payload = RtmpCommand::encode(name, transaction, object, args)?;
+        let chunk = Chunk::new(CHUNK_STREAM_ID_COMMAND, 0, MessageType::CommandAMF0, msg_stream_id, payload);
+        self.write_chunk(chunk).await?;
+        Ok(transaction)
+    }
+
+    async fn write_chunk(&mut self, chunk: Chunk) -> Result<(), RelayError> {
+        let mut buf = Vec::new();
+        self.writer.write_chunk(&mut buf, chunk)?;
+        tokio::time::timeout(self.write_timeout, self.stream.write_all(&buf)).await
+            .map_err(|_| RelayError::Timeout)??;
+        Ok(())
+    }
+
+    async fn wait_result(&mut self, transaction: f64) -> Result<Vec<Amf0Value<'static>>, RelayError> {
+        loop {
+            let command = self.read_command().await?;
+            match command.name.as_str() {
+                "_result" if command.transaction == transaction => return Ok(command.values),
+                "_error" if command.transaction == transaction => return Err(RelayError::Rejected(command.status_text())),
+                _ => {}
+            }
+        }
+    }
+
+    async fn wait_publish_start(&mut self) -> Result<(), RelayError> {
+        loop {
+            let command = self.read_command().await?;
+            if command.name == "onStatus" && command.status_field("code").as_deref() == Some("NetStream.Publish.Start") {
+                return Ok(());
+            }
+            if command.is_error() {
+                return Err(RelayError::Rejected(command.status_text()));
+            }
+        }
+    }
+
+    async fn read_command(&mut self) -> Result<RtmpCommand, RelayError> {
+        loop {
+            while let Some(chunk) = self.reader.read_chunk(&mut self.read_buf)? {
+                if let Some(command) = self.handle_chunk(chunk)? {
+                    return Ok(command);
+                }
+            }
+            if self.stream.read_buf(&mut self.read_buf).await? == 0 {
+                return Err(connection_closed());
+            }
+        }
+    }
+
+    /// 기다리지 않고 이미 도착한 메시지만 처리한다. publish 중에 받은 오류 상태는 실패로 돌려준다.
+    fn drain_incoming(&mut self) -> Result<(), RelayError> {
+        loop {
+            match self.stream.try_read_buf(&mut self.read_buf) {
+                Ok(0) => return Err(connection_closed()),
+                Ok(_) => continue,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        while let Some(chunk) = self.reader.read_chunk(&mut self.read_buf)? {
+            if let Some(command) = self.handle_chunk(chunk)?
+                && command.is_error()
+            {
+                return Err(RelayError::Rejected(command.status_text()));
+            }
+        }
+        Ok(())
+    }
+
+    /// 제어 메시지를 처리하고, AMF0 명령이면 해석해서 돌려준다.
+    fn handle_chunk(&mut self, chunk: Chunk) -> Result<Option<RtmpCommand>, RelayError> {
+        match chunk.message_header.msg_type_id {
+            MessageType::SetChunkSize => {
+                let size = read_chunk_size(&chunk.payload)
+                    .ok_or_else(|| RelayError::Protocol("short SetChunkSize message".to_string()))?;
+                if !self.reader.update_max_chunk_size(size) {
+                    return Err(RelayError::Protocol(format!("unsupported chunk size {}", size)));
+                }
+                Ok(None)
+            }
+            MessageType::CommandAMF0 => match RtmpCommand::decode(&chunk.payload)? {
+                Some(command) => Ok(Some(command)),
+                None => Err(RelayError::Protocol("command without a name".to_string())),
+            },
+            _ => Ok(None),
+        }
+    }
+}
+
+fn connection_closed() -> RelayError {
+    RelayError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed by destination"))
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use scuffle_rtmp::ServerSession;
+    use scuffle_rtmp::session::server::{ServerSessionError, SessionData, SessionHandler};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    pub type ReceivedTag = (u8, u32, Bytes);
+
+    /// 받은 태그를 채널로 넘기는 scuffle-rtmp 송출 서버. stall 이면 첫 태그를 받은 뒤 더 읽지 않는다.
+    struct TestHandler {
+        tags: mpsc::UnboundedSender<ReceivedTag>,
+        stall: bool,
+    }
+
+    impl SessionHandler for TestHandler {
+        async fn on_publish(&mut self, _stream_id: u32, _app_name: &str, _stream_key: &str) -> Result<(), ServerSessionError> {
+            Ok(())
+        }
+
+        async fn on_unpublish(&mut self, _stream_id: u32) -> Result<(), ServerSessionError> {
+            Ok(())
+        }
+
+        async fn on_data(&mut self, _stream_id: u32, data: SessionData) -> Result<(), ServerSessionError> {
+            let tag = match data {
+                SessionData::Video { timestamp, data } => (9, timestamp, data),
+                SessionData::Audio { timestamp, data } => (8, timestamp, data),
+                SessionData::Amf0 { timestamp, data } => (18, timestamp, data),
+            };
+            let _ = self.tags.send(tag);
+            if self.stall {
+                std::future::pending::<()>().await;
+            }
+            Ok(())
+        }
+    }
+
+    /// 127.0.0.1 의 빈 포트에서 연결을 받는 서버를 띄우고 rtmp://127.0.0.1:포트/live/test 를 돌려준다.
+    pub async fn spawn_server(stall: bool) -> (String, mpsc::UnboundedReceiver<ReceivedTag>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tags, received) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = TestHandler { tags: tags.clone(), stall };
+                tokio::spawn(async move {
+                    let _ = ServerSession::new(stream, handler).run().await;
+                });
+            }
+        });
+        (format!("rtmp://127.0.0.1:{}/live/test", port), received)
+    }
+
+    #[test]
+    fn parse_url() {
+        let url = RtmpUrl::parse("rtmp://example.com:1936/live/secret").unwrap();
+        assert_eq!(url.host, "example.com");
+        assert_eq!(url.port, 1936);
+        assert_eq!(url.app, "live");
+        assert_eq!(url.stream_key, "secret");
+        assert_eq!(url.destination(), "rtmp://example.com:1936/live");
+
+        assert_eq!(RtmpUrl::parse("rtmp://example.com/live/secret").unwrap().port, 1935);
+        assert!(RtmpUrl::parse("http://example.com/live/secret").is_err());
+        assert!(RtmpUrl::parse("rtmp://example.com/secret").is_err());
+    }
+
+    #[tokio::test]
+    async fn publish_tags() {
+        let (url, mut received) = spawn_server(false).await;
+        let mut publisher = RtmpPublisher::connect(&RtmpUrl::parse(&url).unwrap(), Duration::from_secs(5)).await.unwrap();
+
+        let video = Bytes::from_static(&[0x17, 0x01, 0x00, 0x00, 0x00, 0xaa]);
+        let audio = Bytes::from_static(&[0xaf, 0x01, 0xbb]);
+        publisher.send_tag(9, 0, video.clone()).await.unwrap();
+        publisher.send_tag(8, 23, audio.clone()).await.unwrap();
+
+        assert_eq!(received.recv().await, Some((9, 0, video)));
+        assert_eq!(received.recv().await, Some((8, 23, audio)));
+        publisher.close().await;
+    }
+
+    #[tokio::test]
+    async fn send_tag_times_out_when_destination_stops_reading() {
+        let (url, mut received) = spawn_server(true).await;
+        let mut publisher = RtmpPublisher::connect(&RtmpUrl::parse(&url).unwrap(), Duration::from_millis(200)).await.unwrap();
+
+        let payload = Bytes::from(vec![0u8; 64 * 1024]);
+        let mut result = Ok(());
+        for timestamp in 0..1024 {
+            result = publisher.send_tag(9, timestamp, payload.clone()).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        assert!(matches!(result, Err(RelayError::Timeout)), "{:?}", result);
+        assert!(received.recv().await.is_some());
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::Instrument;
+use crate::config::RetentionConfig;
+use crate::metrics_layer::server_metrics::OUTPUTS_DELETED;
+use crate::transform_layer::playlist::live_registry::LiveRegistry;
+
+/// 출력 디렉터리에 남은 플레이리스트로 판단한 종류. 디스크가 부족할 때 앞쪽 종류부터 지운다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum OutputKind {
+    Live,
+    Dvr,
+    Recording,
+}
+
+impl OutputKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OutputKind::Live => "live",
+            OutputKind::Dvr => "dvr",
+            OutputKind::Recording => "recording",
+        }
+    }
+}
+
+/// save_dir 아래의 "닉네임/시작시각" 디렉터리 하나
+struct Output {
+    key: String,
+    path: PathBuf,
+    kind: OutputKind,
+    /// 마지막으로 파일이 쓰인 시각
+    modified: SystemTime,
+    size: u64,
+    /// 송출 중이거나 재연결을 기다리는 방송. 사용량에는 들어가지만 지우지 않는다.
+    live: bool,
+}
+
+/// 끝난 방송의 출력 디렉터리를 보관 기간과 디스크 사용량에 따라 지운다.
+/// 송출 중이거나 재연결을 기다리는 방송의 디렉터리는 건드리지 않는다.
+pub struct Janitor {
+    root: PathBuf,
+    config: RetentionConfig,
+    live_registry: Arc<LiveRegistry>,
+}
+
+impl Janitor {
+    pub fn new(root: &str, config: RetentionConfig, live_registry: Arc<LiveRegistry>) -> Self {
+        Self { root: PathBuf::from(root), config, live_registry }
+    }
+
+    /// 이전 실행이 남긴 디렉터리를 먼저 정리하고, 이후 interval 마다 정리하는 작업을 띄운다.
+    pub async fn start(self) {
+        let janitor = Arc::new(self);
+        janitor.clone().sweep().await;
+
+        let interval = Duration::from_millis(janitor.config.interval.max(1000));
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                janitor.clone().sweep().await;
+            }
+        }.instrument(tracing::info_span!("janitor")));
+    }
+
+    async fn sweep(self: Arc<Self>) {
+        let janitor = self.clone();
+        if let Err(e) = tokio::task::spawn_blocking(move || janitor.sweep_blocking()).await {
+            tracing::error!(error = %e, "Janitor sweep panicked");
+        }
+    }
+
+    fn sweep_blocking(&self) {
+        let live = self.live_registry.output_keys();
+        let outputs = match self.scan(&live) {
+            Ok(outputs) => outputs,
+            Err(e) => {
+                tracing::warn!(root = %self.root.display(), error = %e, "Failed to scan output directories");
+                return;
+            }
+        };
+        let mut usage: u64 = outputs.iter().map(|o| o.size).sum();
+        let now = SystemTime::now();
+
+        let (expired, mut kept): (Vec<_>, Vec<_>) = outputs.into_iter()
+            .filter(|o| !o.live)
+            .partition(|o| self.is_expired(o, now));
+        for output in expired {
+            if self.remove(&output, "expired") {
+                usage -= output.size;
+            }
+        }
+
+        let Some(high_water) = self.config.high_water else {
+            return;
+        };
+        if usage <= high_water {
+            return;
+        }
+        let low_water = self.config.low_water.unwrap_or(high_water / 10 * 9);
+        // 보관 기간이 0 인 종류는 영구 보관이므로 사용량 때문에도 지우지 않는다.
+        kept.retain(|o| self.retention(o.kind) > 0);
+        kept.sort_by_key(|o| (o.kind, o.modified));
+        for output in kept {
+            if usage <= low_water {
+                break;
+            }
+            if self.remove(&output, "disk usage") {
+                usage -= output.size;
+            }
+        }
+        if usage > low_water {
+            tracing::warn!(usage, low_water, "Disk usage is still above the low-water mark");
+        }
+    }
+
+    /// 종류별 보관 기간(ms). 0 이면 지우지 않는다.
+    fn retention(&self, kind: OutputKind) -> u64 {
+        match kind {
+            OutputKind::Live => self.config.live,
+            OutputKind::Dvr => self.config.dvr,
+            OutputKind::Recording => self.config.recording,
+        }
+    }
+
+    fn is_expired(&self, output: &Output, now: SystemTime) -> bool {
+        let retention = self.retention(output.kind);
+        retention > 0
+            && now.duration_since(output.modified).unwrap_or_default() > Duration::from_millis(retention)
+    }
+
+    /// 비어 버린 닉네임 디렉터리도 지운다.
+    fn remove(&self, output: &Output, reason: &str) -> bool {
+        if let Err(e) = fs::remove_dir_all(&output.path) {
+            tracing::warn!(key = output.key, error = %e, "Failed to remove output directory");
+            return false;
+        }
+        if let Some(parent) = output.path.parent() {
+            let _ = fs::remove_dir(parent);
+        }
+        self.live_registry.forget_output(&output.key);
+        OUTPUTS_DELETED.inc(&[output.kind.as_str()]);
+        tracing::info!(key = output.key, kind = output.kind.as_str(), bytes = output.size, reason, "Removed output directory");
+        true
+    }
+
+    /// 출력 디렉터리를 모두 모은다. 송출 중인 디렉터리는 live 로 표시한다.
+    fn scan(&self, live: &HashSet<String>) -> io::Result<Vec<Output>> {
+        let mut outputs = Vec::new();
+        for nickname in fs::read_dir(&self.root)? {
+            let nickname = nickname?;
+            if !nickname.file_type()?.is_dir() {
+                continue;
+            }
+            let Some(entries) = not_found_as_none(fs::read_dir(nickname.path()))? else {
+                continue;
+            };
+            for started_at in entries {
+                let started_at = started_at?;
+                if !started_at.file_type()?.is_dir() {
+                    continue;
+                }
+                let key = format!("{}/{}", nickname.file_name().to_string_lossy(), started_at.file_name().to_string_lossy());
+                let path = started_at.path();
+                // 훑는 사이에 지워진 디렉터리는 건너뛴다.
+                let Some((size, modified)) = not_found_as_none(measure(&path))? else {
+                    continue;
+                };
+                outputs.push(Output {
+                    live: live.contains(&key),
+                    key,
+                    kind: not_found_as_none(output_kind(&path))?.unwrap_or(OutputKind::Live),
+                    path,
+                    modified,
+                    size,
+                });
+            }
+        }
+        Ok(outputs)
+    }
+}
+
+/// 방송이 끝날 때 남기는 vod.m3u8, dvr.m3u8 로 종류를 정한다.
+fn output_kind(path: &Path) -> io::Result<OutputKind> {
+    if path.join("vod.m3u8").exists() {
+        return Ok(OutputKind::Recording);
+    }
+    for rendition in fs::read_dir(path)? {
+        if rendition?.path().join("dvr.m3u8").exists() {
+            return Ok(OutputKind::Dvr);
+        }
+    }
+    Ok(OutputKind::Live)
+}
+
+/// 디렉터리 아래 파일들의 전체 크기와 가장 최근 수정 시각.
+/// 재는 사이에 지워진 파일은 0 바이트로 본다.
+fn measure(path: &Path) -> io::Result<(u64, SystemTime)> {
+    let mut size = 0;
+    let mut modified = fs::metadata(path)?.modified()?;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let measured = match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => not_found_as_none(measure(&entry.path()))?,
+            Ok(metadata) => Some((metadata.len(), metadata.modified()?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        if let Some((entry_size, entry_modified)) = measured {
+            size += entry_size;
+            modified = modified.max(entry_modified);
+        }
+    }
+    Ok((size, modified))
+}
+
+fn not_found_as_none<T>(result: io::Result<T>) -> io::Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_output(root: &Path, key: &str, size: usize) {
+        let dir = root.join(key).join("720p");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("segment0.ts"), vec![0u8; size]).unwrap();
+    }
+
+    #[test]
+    fn keeps_live_outputs_and_permanent_recordings() {
+        let root = std::env::temp_dir().join(format!("janitor-{}", std::process::id()));
+        write_output(&root, "alice/1", 600);
+        write_output(&root, "bob/1", 300);
+        write_output(&root, "bob/2", 300);
+        write_output(&root, "carol/1", 300);
+        fs::write(root.join("carol/1/vod.m3u8"), "").unwrap();
+
+        let live_registry = Arc::new(LiveRegistry::default());
+        live_registry.register_channel("alice", "alice/1".to_string());
+        // 녹화본은 기본값(recording = 0)대로 영구 보관한다.
+        let config = RetentionConfig {
+            high_water: Some(1000),
+            low_water: Some(700),
+            ..RetentionConfig::default()
+        };
+        Janitor::new(root.to_str().unwrap(), config, live_registry).sweep_blocking();
+
+        let exists = |key: &str| root.join(key).exists();
+        let kept = (exists("alice/1"), exists("bob/1"), exists("bob/2"), exists("carol/1"));
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(kept, (true, false, false, true));
+    }
+}
@@ -0,0 +1,62 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use tokio::fs;
+use crate::storage_layer::segment_store::{SegmentStore, StoreFuture, StoreResult};
+
+/// save_dir 아래에 파일로 저장한다.
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: &str) -> std::io::Result<Self> {
+        std::fs::create_dir_all(root)?;
+        Ok(Self { root: PathBuf::from(root) })
+    }
+
+    /// key 는 create_store 가 감싼 CheckedStore 에서 이미 검사되었다.
+    fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+
+    /// 읽는 쪽이 반쯤 쓰인 파일을 보지 않도록 임시 파일에 쓴 뒤 교체한다.
+    async fn write(&self, key: &str, data: &[u8]) -> StoreResult<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, data).await?;
+        fs::rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+}
+
+impl SegmentStore for LocalStore {
+    fn put_segment<'a>(&'a self, key: &'a str, data: Vec<u8>) -> StoreFuture<'a, ()> {
+        Box::pin(async move { self.write(key, &data).await })
+    }
+
+    fn put_playlist<'a>(&'a self, key: &'a str, playlist: String) -> StoreFuture<'a, ()> {
+        Box::pin(async move { self.write(key, playlist.as_bytes()).await })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            match fs::remove_file(self.path(key)).await {
+                Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            }
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            match fs::read(self.path(key)).await {
+                Ok(data) => Ok(Some(data)),
+                Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+}
@@ -0,0 +1,11 @@
+/*
+ 저장소 레이어 (storage_layer)
+ 세그먼트, init 세그먼트, 플레이리스트를 저장하고 읽어오는 백엔드를 추상화한다.
+ 세그먼트 writer 와 HTTP 핸들러가 같은 SegmentStore 를 사용한다.
+ 로컬 저장소에서는 janitor 가 끝난 방송의 출력 디렉터리를 보관 기간에 따라 지운다.
+ */
+pub mod segment_store;
+pub mod local_store;
+pub mod s3_store;
+pub mod store_queue;
+pub mod janitor;
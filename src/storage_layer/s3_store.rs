@@ -0,0 +1,205 @@
+use std::time::{Duration, SystemTime};
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode, Url};
+use sha2::{Digest, Sha256};
+use crate::config::S3Config;
+use crate::storage_layer::segment_store::{SegmentStore, StoreFuture, StoreResult};
+use crate::utils::hex::encode_hex;
+use crate::utils::time::format_amz_date;
+
+/// S3 호환 오브젝트 스토리지(AWS S3, MinIO 등)에 path-style 요청으로 저장한다.
+/// 요청은 AWS Signature Version 4 로 서명한다.
+pub struct S3Store {
+    client: Client,
+    config: S3Config,
+    endpoint: Url,
+    host: String,
+}
+
+impl S3Store {
+    pub fn new(config: S3Config) -> Result<Self, Box<dyn std::error::Error>> {
+        let endpoint = Url::parse(&config.endpoint)?;
+        let host = match (endpoint.host_str(), endpoint.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(format!("invalid s3 endpoint: {}", config.endpoint).into()),
+        };
+        // 스토리지가 응답하지 않으면 저장 큐 전체가 멈추므로 요청마다 제한 시간을 둔다.
+        let client = Client::builder()
+            .connect_timeout(Duration::from_millis(config.connect_timeout))
+            .timeout(Duration::from_millis(config.timeout))
+            .build()?;
+        Ok(Self {
+            client,
+            config,
+            endpoint,
+            host,
+        })
+    }
+
+    fn object_path(&self, key: &str) -> String {
+        let key = match &self.config.prefix {
+            Some(prefix) => format!("{}/{}", prefix.trim_end_matches('/'), key),
+            None => key.to_string(),
+        };
+        format!("/{}/{}", self.config.bucket, uri_encode(&key))
+    }
+
+    /// AWS Signature Version 4 Authorization 헤더. host, x-amz-content-sha256, x-amz-date 만 서명한다.
+    fn authorization(&self, method: &Method, path: &str, payload_hash: &str, amz_date: &str, date: &str) -> String {
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            method, path, self.host, payload_hash, amz_date, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            encode_hex(&Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let signing_key = [date, self.config.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", self.config.secret_key).into_bytes(), |key, part| hmac(&key, part.as_bytes()));
+        let signature = encode_hex(&hmac(&signing_key, string_to_sign.as_bytes()));
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+            self.config.access_key, scope, signature
+        )
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> StoreResult<reqwest::Response> {
+        let path = self.object_path(key);
+        let (amz_date, date) = format_amz_date(SystemTime::now());
+        let payload_hash = encode_hex(&Sha256::digest(&body));
+        let authorization = self.authorization(&method, &path, &payload_hash, &amz_date, &date);
+
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+        let mut request = self.client
+            .request(method, url)
+            .header("x-amz-date", amz_date)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("authorization", authorization);
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+        Ok(request.body(body).send().await?)
+    }
+
+    async fn put(&self, key: &str, body: Vec<u8>, content_type: &str) -> StoreResult<()> {
+        let response = self.send(Method::PUT, key, body, Some(content_type)).await?;
+        if !response.status().is_success() {
+            return Err(format!("s3 put {} failed: {}", key, response.status()).into());
+        }
+        Ok(())
+    }
+}
+
+impl SegmentStore for S3Store {
+    fn put_segment<'a>(&'a self, key: &'a str, data: Vec<u8>) -> StoreFuture<'a, ()> {
+        Box::pin(async move { self.put(key, data, content_type(key)).await })
+    }
+
+    fn put_playlist<'a>(&'a self, key: &'a str, playlist: String) -> StoreFuture<'a, ()> {
+        Box::pin(async move { self.put(key, playlist.into_bytes(), content_type(key)).await })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let response = self.send(Method::DELETE, key, Vec::new(), None).await?;
+            if !response.status().is_success() && response.status() != StatusCode::NOT_FOUND {
+                return Err(format!("s3 delete {} failed: {}", key, response.status()).into());
+            }
+            Ok(())
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            let response = self.send(Method::GET, key, Vec::new(), None).await?;
+            match response.status() {
+                StatusCode::NOT_FOUND => Ok(None),
+                status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+                status => Err(format!("s3 get {} failed: {}", key, status).into()),
+            }
+        })
+    }
+}
+
+fn content_type(key: &str) -> &'static str {
+    match key.rsplit_once('.').map(|(_, ext)| ext) {
+        Some("m3u8") => "application/vnd.apple.mpegurl",
+        Some("mpd") => "application/dash+xml",
+        Some("ts") => "video/mp2t",
+        Some("m4s") => "video/iso.segment",
+        Some("mp4") => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// SigV4 규칙에 맞게 경로를 인코딩한다. '/' 는 그대로 둔다.
+fn uri_encode(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn store(prefix: Option<&str>) -> S3Store {
+        S3Store::new(S3Config {
+            endpoint: "http://localhost:9000".to_string(),
+            bucket: "pang".to_string(),
+            region: "us-east-1".to_string(),
+            access_key: "AKIDEXAMPLE".to_string(),
+            secret_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            prefix: prefix.map(str::to_string),
+            connect_timeout: 3000,
+            timeout: 10000,
+        }).unwrap()
+    }
+
+    #[test]
+    fn encodes_object_path() {
+        assert_eq!(store(None).object_path("live/a b.ts"), "/pang/live/a%20b.ts");
+        assert_eq!(store(Some("hls/")).object_path("live/index.m3u8"), "/pang/hls/live/index.m3u8");
+    }
+
+    #[test]
+    fn signs_request() {
+        let (amz_date, date) = format_amz_date(UNIX_EPOCH + Duration::from_secs(1_704_067_200));
+        assert_eq!((amz_date.as_str(), date.as_str()), ("20240101T000000Z", "20240101"));
+        let payload_hash = encode_hex(&Sha256::digest(b"hello"));
+        assert_eq!(payload_hash, "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+
+        let store = store(None);
+        let path = store.object_path("live/a b.ts");
+        assert_eq!(
+            store.authorization(&Method::PUT, &path, &payload_hash, &amz_date, &date),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20240101/us-east-1/s3/aws4_request, \
+             SignedHeaders=host;x-amz-content-sha256;x-amz-date, \
+             Signature=7a67b484c92eb4d5510735f8474eb38672de63dded08b232e7ec74192c75bd36"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use crate::config::{Config, StorageConfig};
+use crate::storage_layer::local_store::LocalStore;
+use crate::storage_layer::s3_store::S3Store;
+
+pub type StoreResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = StoreResult<T>> + Send + 'a>>;
+
+/// 세그먼트 저장소. key 는 "닉네임/시작시각/화질/파일이름" 형태의 상대 경로이다.
+pub trait SegmentStore: Send + Sync {
+    fn put_segment<'a>(&'a self, key: &'a str, data: Vec<u8>) -> StoreFuture<'a, ()>;
+
+    fn put_playlist<'a>(&'a self, key: &'a str, playlist: String) -> StoreFuture<'a, ()>;
+
+    fn delete<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()>;
+
+    /// 없는 key 는 Ok(None) 을 돌려준다.
+    fn get<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Vec<u8>>>;
+}
+
+/// 모든 저장소는 CheckedStore 로 감싸서 key 를 검사한 뒤에 접근한다.
+pub fn create_store(config: &Config) -> Result<Arc<dyn SegmentStore>, Box<dyn Error>> {
+    match &config.storage {
+        StorageConfig::Local => Ok(Arc::new(CheckedStore(LocalStore::new(&config.hls.save_dir)?))),
+        StorageConfig::S3(s3_config) => Ok(Arc::new(CheckedStore(S3Store::new(s3_config.clone())?))),
+    }
+}
+
+/// 저장소 밖이나 버킷의 다른 위치를 가리키는 key
+#[derive(Debug)]
+pub struct InvalidKey(pub String);
+
+impl fmt::Display for InvalidKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid store key: {}", self.0)
+    }
+}
+
+impl Error for InvalidKey {}
+
+/// 빈 조각, ".", "..", 역슬래시나 제어 문자가 있는 key 는 거절한다.
+/// HTTP 경로 조각으로 만든 key 도 그대로 들어오므로 저장소마다 따로 검사하지 않고 여기서 한 번에 막는다.
+pub fn check_key(key: &str) -> Result<(), InvalidKey> {
+    let valid = !key.chars().any(|c| c == '\\' || c.is_control())
+        && key.split('/').all(|part| !part.is_empty() && part != "." && part != "..");
+    if valid { Ok(()) } else { Err(InvalidKey(key.to_string())) }
+}
+
+/// key 를 검사한 뒤 감싼 저장소에 넘긴다.
+struct CheckedStore<S>(S);
+
+impl<S: SegmentStore> SegmentStore for CheckedStore<S> {
+    fn put_segment<'a>(&'a self, key: &'a str, data: Vec<u8>) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            check_key(key)?;
+            self.0.put_segment(key, data).await
+        })
+    }
+
+    fn put_playlist<'a>(&'a self, key: &'a str, playlist: String) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            check_key(key)?;
+            self.0.put_playlist(key, playlist).await
+        })
+    }
+
+    fn delete<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            check_key(key)?;
+            self.0.delete(key).await
+        })
+    }
+
+    fn get<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            check_key(key)?;
+            self.0.get(key).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_output_keys() {
+        assert!(check_key("pang/2024-01-01T00:00:00.000Z/720p/segment_00000.ts").is_ok());
+        assert!(check_key("pang/2024-01-01T00:00:00.000Z/dash/audio/init.mp4").is_ok());
+    }
+
+    #[test]
+    fn rejects_keys_outside_output() {
+        for key in ["../secret", "pang/../../other/key", "/pang/vod.m3u8", "pang//vod.m3u8", "pang/./vod.m3u8", "pang\\..\\x", "pang/\nvod.m3u8", ""] {
+            assert!(check_key(key).is_err(), "{:?}", key);
+        }
+    }
+
+    #[tokio::test]
+    async fn checked_store_rejects_before_backend() {
+        struct Unreachable;
+        impl SegmentStore for Unreachable {
+            fn put_segment<'a>(&'a self, _: &'a str, _: Vec<u8>) -> StoreFuture<'a, ()> { unreachable!() }
+            fn put_playlist<'a>(&'a self, _: &'a str, _: String) -> StoreFuture<'a, ()> { unreachable!() }
+            fn delete<'a>(&'a self, _: &'a str) -> StoreFuture<'a, ()> { unreachable!() }
+            fn get<'a>(&'a self, _: &'a str) -> StoreFuture<'a, Option<Vec<u8>>> { unreachable!() }
+        }
+        let error = CheckedStore(Unreachable).get("pang/../../bucket-root").await.unwrap_err();
+        assert!(error.is::<InvalidKey>());
+    }
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use crate::storage_layer::segment_store::SegmentStore;
+use tracing::Instrument;
+use crate::utils::log_error::LogError;
+
+/// GStreamer 스트리밍 스레드에서 저장소에 쓰기 위한 큐.
+/// 작업은 순서대로 처리되므로 세그먼트가 플레이리스트보다 먼저 올라간다.
+/// 플레이리스트는 큐에서 꺼낼 때 렌더링하므로 그 앞의 저장 뒤 콜백이 반영된 내용이 기록된다.
+/// 큐가 가득 차면 저장소가 따라올 때까지 스트리밍 스레드가 기다린다.
+#[derive(Clone)]
+pub struct StoreQueue {
+    sender: mpsc::Sender<StoreOp>,
+}
+
+/// 저장에 성공한 뒤 큐 작업에서 실행된다. 돌려준 key 들은 이어서 지운다.
+type OnStored = Box<dyn FnOnce() -> Vec<String> + Send>;
+
+enum StoreOp {
+    Segment(String, Vec<u8>, Option<OnStored>),
+    Playlist(String, Box<dyn FnOnce() -> String + Send>),
+    /// 앞선 작업이 모두 끝난 뒤 실행한다.
+    Run(Box<dyn FnOnce() + Send>),
+}
+
+const QUEUE_SIZE: usize = 64;
+
+impl StoreQueue {
+    /// 반환된 JoinHandle 은 모든 StoreQueue 가 drop 되고 남은 작업을 다 처리하면 끝난다.
+    /// after 가 있으면 그 작업이 끝난 뒤부터 처리한다.
+    pub fn spawn(store: Arc<dyn SegmentStore>, runtime: &Handle, after: Option<JoinHandle<()>>) -> (Self, JoinHandle<()>) {
+        let (sender, mut receiver) = mpsc::channel(QUEUE_SIZE);
+        let task = runtime.spawn(async move {
+            if let Some(after) = after {
+                let _ = after.await;
+            }
+            while let Some(op) = receiver.recv().await {
+                match op {
+                    StoreOp::Segment(key, data, on_stored) => {
+                        let stored = store.put_segment(&key, data).await
+                            .log_error(&format!("Failed to store {}", key));
+                        if let (Some(()), Some(on_stored)) = (stored, on_stored) {
+                            for key in on_stored() {
+                                store.delete(&key).await
+                                    .log_error(&format!("Failed to delete {}", key));
+                            }
+                        }
+                    }
+                    StoreOp::Playlist(key, render) => {
+                        store.put_playlist(&key, render()).await
+                            .log_error(&format!("Failed to store {}", key));
+                    }
+                    StoreOp::Run(f) => f(),
+                }
+            }
+        }.in_current_span());
+        (Self { sender }, task)
+    }
+
+    pub fn put_segment(&self, key: String, data: Vec<u8>) -> bool {
+        self.send(StoreOp::Segment(key, data, None))
+    }
+
+    /// 세그먼트가 저장된 뒤에만 on_stored 를 실행한다. 저장에 실패하면 실행하지 않는다.
+    pub fn put_segment_then(&self, key: String, data: Vec<u8>, on_stored: impl FnOnce() -> Vec<String> + Send + 'static) -> bool {
+        self.send(StoreOp::Segment(key, data, Some(Box::new(on_stored))))
+    }
+
+    pub fn put_playlist(&self, key: String, playlist: String) -> bool {
+        self.send(StoreOp::Playlist(key, Box::new(move || playlist)))
+    }
+
+    /// 큐에서 꺼낼 때 render 로 플레이리스트를 만든다.
+    pub fn put_rendered_playlist(&self, key: String, render: impl FnOnce() -> String + Send + 'static) -> bool {
+        self.send(StoreOp::Playlist(key, Box::new(render)))
+    }
+
+    pub fn run(&self, f: impl FnOnce() + Send + 'static) -> bool {
+        self.send(StoreOp::Run(Box::new(f)))
+    }
+
+    /// tokio 런타임 밖(스트리밍 스레드)에서만 호출해야 한다.
+    fn send(&self, op: StoreOp) -> bool {
+        self.sender.blocking_send(op).is_ok()
+    }
+}
@@ -0,0 +1,46 @@
+use futures_util::StreamExt;
+use gstreamer::MessageView;
+use gstreamer::prelude::GstObjectExt;
+use gstreamer_app::gst;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+/// 파이프라인 버스를 감시한다. 경고는 로그만 남기고, 첫 오류에서 on_error 를 호출한 뒤 끝난다.
+/// EOS 가 모든 sink 에 도달하면 drained 를 취소한다. 반환된 토큰을 취소하면 감시를 멈춘다.
+pub fn watch_bus(
+    stream_id: u32,
+    bus: gst::Bus,
+    drained: CancellationToken,
+    on_error: impl FnOnce(String) + Send + 'static,
+) -> CancellationToken {
+    let token = CancellationToken::new();
+    let cancelled = token.clone();
+    tokio::spawn(async move {
+        let mut messages = bus.stream();
+        loop {
+            let message = tokio::select! {
+                _ = cancelled.cancelled() => return,
+                message = messages.next() => match message {
+                    Some(message) => message,
+                    None => return,
+                },
+            };
+
+            let source = message.src().map(|s| s.path_string().to_string()).unwrap_or_default();
+            match message.view() {
+                MessageView::Error(err) => {
+                    let reason = format!("{}: {}", source, err.error());
+                    tracing::error!(stream_id, %source, error = %err.error(), debug = ?err.debug(), "Pipeline error");
+                    on_error(reason);
+                    return;
+                }
+                MessageView::Eos(_) => drained.cancel(),
+                MessageView::Warning(warning) => {
+                    tracing::warn!(stream_id, %source, error = %warning.error(), debug = ?warning.debug(), "Pipeline warning");
+                }
+                _ => {}
+            }
+        }
+    }.in_current_span());
+    token
+}
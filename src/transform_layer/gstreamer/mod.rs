@@ -1 +1,3 @@
-pub mod push;
\ No newline at end of file
+pub mod bus_watch;
+pub mod push;
+pub mod slate_switch;
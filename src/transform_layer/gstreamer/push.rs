@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use gstreamer_app::gst;
+use crate::metrics_layer::server_metrics::PUSH_FAILURES;
 use crate::transform_layer::hls_convertor::Pipeline;
 use gstreamer;
 
@@ -24,15 +25,17 @@ pub fn push_to_gstreamer(
         match pipeline_info.app_src().push_buffer(buffer) {
             Ok(_) => {}
             Err(gst::FlowError::Flushing) => {
-                println!("Pipeline is flushing for stream {}", stream_id);
+                PUSH_FAILURES.inc(&["flushing"]);
+                tracing::debug!(stream_id, "Pipeline is flushing");
             }
             Err(e) => {
-                eprintln!("Failed to push buffer to AppSrc: {:?}", e);
+                PUSH_FAILURES.inc(&[&format!("{:?}", e).to_lowercase()]);
+                tracing::warn!(stream_id, error = ?e, "Failed to push buffer to AppSrc");
                 return Err(format!("GStreamer push error: {:?}", e).into());
             }
         }
     } else {
-        eprintln!("No pipeline found for stream {}", stream_id);
+        tracing::warn!(stream_id, "No pipeline found");
     }
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,156 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use gstreamer::prelude::{ElementExt, ElementExtManual, ObjectExt, PadExt};
+use gstreamer_app::gst;
+use gstreamer_app::glib::BoolError;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+use crate::transform_layer::ingest::Ingest;
+
+/// 영상, 오디오 input-selector 들의 입력을 원본과 슬레이트 사이에서 한꺼번에 바꾼다.
+#[derive(Clone, Default)]
+pub struct SlateSwitch {
+    inputs: Vec<SelectorInput>,
+    timeline: Arc<Mutex<Timeline>>,
+}
+
+#[derive(Clone)]
+struct SelectorInput {
+    selector: gst::Element,
+    live: gst::Pad,
+    slate: gst::Pad,
+}
+
+/// 입력을 확인하는 주기
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+impl SlateSwitch {
+    /// 원본 체인의 끝과 슬레이트 체인의 끝을 selector 에 연결한다. 처음에는 원본이 선택된다.
+    pub fn add(&mut self, selector: &gst::Element, live_tail: &gst::Element, slate_tail: &gst::Element) -> Result<(), BoolError> {
+        live_tail.link(selector)?;
+        slate_tail.link(selector)?;
+        let peer = |tail: &gst::Element| tail.static_pad("src").and_then(|pad| pad.peer());
+        let (Some(live), Some(slate)) = (peer(live_tail), peer(slate_tail)) else {
+            return Err(gstreamer_app::glib::bool_error!("input-selector pads are not linked"));
+        };
+        selector.set_property("active-pad", &live);
+        self.inputs.push(SelectorInput { selector: selector.clone(), live, slate });
+        Ok(())
+    }
+
+    /// 슬레이트로 바꾸면 원본이 다시 들어올 때 타임스탬프를 새로 맞춘다.
+    pub fn select(&self, slate: bool) {
+        if slate {
+            self.timeline.lock().unwrap().resync();
+        }
+        for input in &self.inputs {
+            let pad = if slate { &input.slate } else { &input.live };
+            input.selector.set_property("active-pad", pad);
+        }
+    }
+
+    /// FLV 타임스탬프(ms)를 파이프라인 running time 에 맞춘다. running_time 이 없으면 0 으로 본다.
+    pub fn rebase(&self, timestamp: u32, running_time: Option<gst::ClockTime>) -> u32 {
+        let running_time = running_time.map_or(0, gst::ClockTime::mseconds);
+        self.timeline.lock().unwrap().rebase(timestamp, running_time)
+    }
+
+    /// ingest 에 timeout 동안 태그가 없으면 슬레이트로, 다시 들어오면 원본으로 바꾼다.
+    /// 반환된 토큰을 취소하면 감시를 멈춘다.
+    pub fn watch(&self, stream_id: u32, ingest: Arc<Ingest>, timeout: Duration) -> CancellationToken {
+        let token = CancellationToken::new();
+        let cancelled = token.clone();
+        let switch = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            let mut on_slate = false;
+            loop {
+                tokio::select! {
+                    _ = cancelled.cancelled() => return,
+                    _ = interval.tick() => {}
+                }
+
+                let idle = ingest.idle_for() >= timeout;
+                if idle != on_slate {
+                    on_slate = idle;
+                    switch.select(on_slate);
+                    if on_slate {
+                        tracing::warn!(stream_id, timeout_ms = timeout.as_millis() as u64, "No data from publisher, switched to slate");
+                    } else {
+                        tracing::info!(stream_id, "Publisher data resumed, switched back from slate");
+                    }
+                }
+            }
+        }.in_current_span());
+        token
+    }
+}
+
+/// 슬레이트는 live 소스라 running time 으로 타임스탬프가 찍히는데, 원본 FLV 타임스탬프는 송출자가 정한다.
+/// 원본이 멈췄다 돌아오거나 새 송출자가 0 부터 보내도 출력이 뒤로 가지 않도록
+/// 다시 맞출 때마다 첫 태그를 그 시점의 running time 에 놓고 이후 태그는 같은 간격을 유지한다.
+#[derive(Default)]
+struct Timeline {
+    /// 원본 타임스탬프에 더할 값(ms). None 이면 다음 태그에서 정한다.
+    offset: Option<i64>,
+    /// 지금까지 내보낸 가장 큰 타임스탬프
+    last: u32,
+}
+
+impl Timeline {
+    fn rebase(&mut self, timestamp: u32, running_time: u64) -> u32 {
+        let last = self.last;
+        let offset = *self.offset.get_or_insert_with(|| (running_time as i64).max(last as i64) - timestamp as i64);
+        let rebased = (timestamp as i64 + offset).clamp(0, u32::MAX as i64) as u32;
+        self.last = self.last.max(rebased);
+        rebased
+    }
+
+    fn resync(&mut self) {
+        self.offset = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn starts_at_running_time() {
+        let mut timeline = Timeline::default();
+        assert_eq!(timeline.rebase(0, 120), 120);
+        assert_eq!(timeline.rebase(40, 180), 160);
+    }
+
+    #[test]
+    fn keeps_going_forward_after_new_publisher() {
+        let mut timeline = Timeline::default();
+        timeline.rebase(0, 0);
+        assert_eq!(timeline.rebase(10_000, 10_000), 10_000);
+        // 송출자가 끊기고 20초 동안 슬레이트를 내보낸 뒤 새 송출자가 0 부터 보낸다.
+        timeline.resync();
+        assert_eq!(timeline.rebase(0, 30_000), 30_000);
+        assert_eq!(timeline.rebase(33, 30_040), 30_033);
+    }
+
+    #[test]
+    fn skips_stalled_gap() {
+        let mut timeline = Timeline::default();
+        timeline.rebase(0, 0);
+        timeline.rebase(5_000, 5_000);
+        // 같은 송출자가 멈췄다가 이어서 보내면 슬레이트가 채운 시간만큼 뒤로 민다.
+        timeline.resync();
+        assert_eq!(timeline.rebase(5_033, 12_000), 12_000);
+        assert_eq!(timeline.rebase(5_066, 12_010), 12_033);
+    }
+
+    #[test]
+    fn never_goes_backwards() {
+        let mut timeline = Timeline::default();
+        timeline.rebase(0, 0);
+        timeline.rebase(8_000, 8_000);
+        // running time 이 앞선 출력보다 작게 읽혀도 마지막 타임스탬프부터 잇는다.
+        timeline.resync();
+        assert_eq!(timeline.rebase(100, 7_500), 8_000);
+    }
+}
@@ -1,22 +1,130 @@
 use std::collections::HashMap;
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use futures_util::future::join_all;
 use gstreamer::prelude::{ElementExt, ElementExtManual, GstBinExtManual};
-use gstreamer_app::{gst, AppSrc};
+use gstreamer_app::{gst, AppSink, AppSrc};
 use gstreamer_app::prelude::Cast;
-use crate::transform_layer::pads::dynamic_pads::setup_dynamic_pads;
-use crate::transform_layer::pipelines::pipeline_elements::{create_audio, create_output, create_source, create_video};
+use serde::Serialize;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+use crate::config::{OutputMode, RenditionConfig};
+use crate::relay_layer::relay::RelayStatus;
+use crate::transform_layer::gstreamer::bus_watch::watch_bus;
+use crate::transform_layer::gstreamer::slate_switch::SlateSwitch;
+use crate::transform_layer::ingest::Ingest;
+use crate::transform_layer::pads::dynamic_pads::{setup_dynamic_pads, RenditionLinks};
+use crate::transform_layer::pipelines::pipeline_elements::{MAX_FILES, create_audio, create_dash_audio, create_dash_output, create_dash_video_split, create_output, create_rendition_audio, create_rendition_video, create_source, create_video};
+use crate::transform_layer::pipelines::slate_elements::{create_input_selector, create_slate_audio, create_slate_video};
+use crate::transform_layer::pipelines::thumbnail_elements::{create_sprite_encoder, create_sprite_tiles, create_thumbnail};
+use crate::transform_layer::playlist::dash_manifest::{render_manifest, split_representations, AUDIO_REPRESENTATION};
+use crate::transform_layer::playlist::live_playlist::LivePlaylist;
+use crate::transform_layer::playlist::live_registry::LiveRegistry;
+use crate::transform_layer::playlist::master_playlist::render_master_playlist;
+use crate::transform_layer::playlist::media_playlist::MediaPlaylist;
+use crate::transform_layer::playlist::sprite_track::SpriteTrack;
+use crate::storage_layer::segment_store::SegmentStore;
+use crate::storage_layer::store_queue::StoreQueue;
+use crate::transform_layer::sinks::fmp4_writer::Fmp4Writer;
+use crate::transform_layer::sinks::segment_output::SegmentOutput;
+use crate::transform_layer::sinks::sprite_writer::SpriteWriter;
+use crate::transform_layer::sinks::thumbnail_writer::ThumbnailWriter;
+use crate::transform_layer::sinks::ts_writer::TsWriter;
+use crate::transform_layer::stop_signal::StopSignal;
 use crate::utils::log_error::LogError;
+use crate::utils::time::format_rfc3339;
+use crate::webhook_layer::stream_event::{EventKind, StreamEvent};
+use crate::webhook_layer::webhook_sender::WebhookSender;
+
+/// 파이프라인을 내릴 때 EOS 가 모든 sink 에 닿아 마지막 세그먼트가 기록되길 기다리는 최대 시간
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub struct HlsConvertor {
     pipelines: Arc<Mutex<HashMap<u32, Pipeline>>>,
-    output_dir: String,
+    store: Arc<dyn SegmentStore>,
     segment_delay: u32,
+    output_mode: OutputMode,
+    part_duration: Option<u32>,
+    live_registry: Arc<LiveRegistry>,
+    webhooks: WebhookSender,
+    /// 채널별 마지막 파이프라인 오류 원인
+    failures: Arc<Mutex<HashMap<String, String>>>,
+    reconnect_grace: Duration,
+    /// 채널 -> 송출자의 재연결을 기다리는 스트림
+    suspended: Arc<Mutex<HashMap<String, SuspendedStream>>>,
+    /// stream id -> 내린 파이프라인의 플레이리스트를 마무리하는 작업. 녹화했다면 VOD 플레이리스트 key 를 돌려준다.
+    finalizing: Arc<Mutex<HashMap<u32, JoinHandle<Option<String>>>>>,
 }
 
 pub struct Pipeline {
     pipeline: gst::Pipeline,
     app_src: AppSrc,
+    channel: String,
+    bus_watch: CancellationToken,
+    /// 슬레이트를 쓰면 원본과 슬레이트를 바꾸는 스위치와 입력이 끊겼는지 감시하는 작업
+    slate: Option<SlateSwitch>,
+    slate_watch: Option<CancellationToken>,
+    /// EOS 가 모든 appsink 에 도달하면 취소된다.
+    drained: CancellationToken,
+    /// 세그먼트와 플레이리스트 저장 작업. 파이프라인이 내려가면 남은 작업을 마치고 끝난다.
+    store_task: JoinHandle<()>,
+    stream_name: String,
+    app_name: String,
+    renditions: Vec<String>,
+    ingest: Arc<Ingest>,
+    stop: StopSignal,
+    /// 켜져 있으면 파이프라인이 끝나도 플레이리스트에 ENDLIST 를 붙이지 않는다.
+    keep_open: Arc<AtomicBool>,
+    relays: Vec<Arc<Mutex<RelayStatus>>>,
+}
+
+struct SuspendedStream {
+    stream_id: u32,
+    stream_name: String,
+    renditions: Vec<String>,
+    /// 파이프라인을 내리지 않고 슬레이트를 내보내는 중이면 true. 재연결되면 새 송출을 같은 파이프라인에 이어 붙인다.
+    on_slate: bool,
+    /// true 를 보내면 재연결된 것이고, false 를 보내거나 drop 하면 바로 종료한다.
+    wake: oneshot::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+/// 변환을 시작한 결과
+pub struct Conversion {
+    /// 재연결 유예 시간 안에 다시 연결되어 이전 플레이리스트를 이어 쓰는지
+    pub resumed: bool,
+    /// 출력 위치. 이어 쓰면 인증 결과와 달리 이전 스트림의 경로이다.
+    pub stream_name: String,
+    /// 슬레이트를 내보내던 파이프라인에 이어 붙였는지. 이때는 FLV 헤더를 다시 보내지 않는다.
+    pub spliced: bool,
+}
+
+/// 관리자 API 에 노출하는 송출 중인 스트림 정보
+#[derive(Debug, Serialize)]
+pub struct StreamInfo {
+    pub id: u32,
+    /// 인증된 스트림 경로 ("닉네임/시작시각")
+    pub stream_name: String,
+    pub channel: String,
+    pub app_name: String,
+    pub publisher_addr: String,
+    pub started_at: String,
+    pub bytes_received: u64,
+    pub ingest_bitrate_kbps: u64,
+    pub video_codec: Option<&'static str>,
+    pub audio_codec: Option<&'static str>,
+    pub pipeline_state: String,
+    pub renditions: Vec<String>,
+    /// 이 채널에서 마지막으로 기록된 파이프라인 오류
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_failure: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub relays: Vec<RelayStatus>,
 }
 
 impl Pipeline {
@@ -26,16 +134,38 @@ impl Pipeline {
 }
 
 impl HlsConvertor {
-    pub fn new(output_dir: String) -> Result<Self, Box<dyn Error>> {
+    pub fn new(store: Arc<dyn SegmentStore>, webhooks: WebhookSender) -> Result<Self, Box<dyn Error>> {
         let config = crate::config::get_config();
         let segment_delay = config.server.segment_delay;
-        std::fs::create_dir_all(&output_dir)
-            .log_error("Failed to create output directory: ");
+        let output_mode = config.hls.output_mode;
+        config.hls.validate_renditions()?;
+        if config.dash.enabled && output_mode != OutputMode::Fmp4 {
+            return Err("dash requires hls.output_mode = \"fmp4\"".into());
+        }
+        if config.dash.enabled && config.hls.renditions.iter().any(|r| r.name == AUDIO_REPRESENTATION) {
+            return Err(format!("rendition name \"{}\" is reserved for the dash audio track", AUDIO_REPRESENTATION).into());
+        }
+        let part_duration = if config.hls.low_latency.enabled {
+            if output_mode != OutputMode::Fmp4 {
+                return Err("hls.low_latency requires hls.output_mode = \"fmp4\"".into());
+            }
+            Some(config.hls.low_latency.part_duration)
+        } else {
+            None
+        };
 
         Ok(Self {
             pipelines: Arc::new(Mutex::new(HashMap::new())),
-            output_dir,
+            store,
             segment_delay,
+            output_mode,
+            part_duration,
+            live_registry: Arc::new(LiveRegistry::default()),
+            webhooks,
+            failures: Arc::new(Mutex::new(HashMap::new())),
+            reconnect_grace: Duration::from_millis(config.server.reconnect_grace),
+            suspended: Arc::new(Mutex::new(HashMap::new())),
+            finalizing: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
@@ -43,75 +173,613 @@ impl HlsConvertor {
         self.pipelines.clone()
     }
 
+    pub fn live_registry(&self) -> Arc<LiveRegistry> {
+        self.live_registry.clone()
+    }
+
+    pub fn store(&self) -> Arc<dyn SegmentStore> {
+        self.store.clone()
+    }
+
+    /// 송출 중인 스트림 목록 (id 순)
+    pub fn streams(&self) -> Vec<StreamInfo> {
+        let pipelines = self.pipelines.lock().unwrap();
+        let mut streams: Vec<_> = pipelines.iter()
+            .map(|(id, pipeline)| self.stream_info(*id, pipeline))
+            .collect();
+        streams.sort_by_key(|s| s.id);
+        streams
+    }
+
+    pub fn stream(&self, stream_id: u32) -> Option<StreamInfo> {
+        let pipelines = self.pipelines.lock().unwrap();
+        pipelines.get(&stream_id).map(|pipeline| self.stream_info(stream_id, pipeline))
+    }
+
+    fn stream_info(&self, stream_id: u32, pipeline: &Pipeline) -> StreamInfo {
+        StreamInfo {
+            id: stream_id,
+            stream_name: pipeline.stream_name.clone(),
+            channel: pipeline.channel.clone(),
+            app_name: pipeline.app_name.clone(),
+            publisher_addr: pipeline.ingest.publisher_addr.to_string(),
+            started_at: format_rfc3339(pipeline.ingest.started_at),
+            bytes_received: pipeline.ingest.bytes_received(),
+            ingest_bitrate_kbps: pipeline.ingest.bitrate_kbps(),
+            video_codec: pipeline.ingest.video_codec(),
+            audio_codec: pipeline.ingest.audio_codec(),
+            pipeline_state: format!("{:?}", pipeline.pipeline.current_state()).to_lowercase(),
+            renditions: pipeline.renditions.clone(),
+            last_failure: self.failures.lock().unwrap().get(&pipeline.channel).cloned(),
+            relays: pipeline.relays.iter().map(|status| status.lock().unwrap().clone()).collect(),
+        }
+    }
+
+    /// 관리자 API 에서 볼 수 있도록 스트림의 릴레이 상태를 등록한다.
+    pub fn set_relays(&self, stream_id: u32, relays: Vec<Arc<Mutex<RelayStatus>>>) {
+        if let Some(pipeline) = self.pipelines.lock().unwrap().get_mut(&stream_id) {
+            pipeline.relays = relays;
+        }
+    }
+
+    /// 파이프라인을 멈추고 송출자의 RTMP 세션을 닫도록 알린다.
+    pub fn kick_stream(&self, stream_id: u32) -> bool {
+        let stop = match self.pipelines.lock().unwrap().get(&stream_id) {
+            Some(pipeline) => pipeline.stop.clone(),
+            None => return false,
+        };
+        stop.stop("kicked by admin".to_string());
+        self.stop_hls_conversion(stream_id);
+        true
+    }
+
+    /// stream_id 는 서버 전체에서 유일해야 한다.
+    /// 같은 채널이 재연결을 기다리는 중이었다면 그 출력 위치와 플레이리스트를 이어 쓴다.
+    /// record 가 켜져 있으면 세그먼트를 지우지 않고 방송이 끝날 때 VOD 플레이리스트를 남긴다.
+    /// 파이프라인 오류나 강제 종료로 변환이 중단되면 stop 에 사유가 기록된다.
     pub fn start_hls_conversion(
         &self,
         stream_id: u32,
+        app_name: &str,
         stream_name: &str,
-        stream_host: &str,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let output_path = format!("{}/{}", self.output_dir, stream_name);
+        ingest: Arc<Ingest>,
+        record: bool,
+        stop: StopSignal,
+    ) -> Result<Conversion, Box<dyn Error + Send + Sync>> {
+        let renditions = crate::config::get_config().hls.renditions_for_app(app_name)?;
 
-        //로컬 테스트용 - daedyu
-        if !self.output_dir.starts_with("s3://") {
-            std::fs::create_dir_all(&output_path)?;
+        let channel = channel_name(stream_name);
+        let suspended = match self.take_suspended(channel, &renditions) {
+            Some(suspended) if suspended.on_slate => {
+                return match self.splice(suspended, stream_id, app_name, ingest.clone(), stop.clone()) {
+                    Some(conversion) => Ok(conversion),
+                    None => self.start_hls_conversion(stream_id, app_name, stream_name, ingest, record, stop),
+                };
+            }
+            suspended => suspended,
+        };
+        let (stream_name, wake, previous) = match suspended {
+            Some(suspended) => (suspended.stream_name, Some(suspended.wake), Some(suspended.task)),
+            None => (stream_name.to_string(), None, None),
+        };
+        let stream_name = stream_name.as_str();
+        let resumed = wake.is_some();
+        if !resumed {
+            self.live_registry.register_channel(channel, stream_name.to_string());
+            for rendition in &renditions {
+                let live_playlist = Arc::new(LivePlaylist::new(self.new_playlist(record)));
+                self.live_registry.register_playlist(channel, &rendition.name, live_playlist);
+            }
+            if crate::config::get_config().dash.enabled {
+                let representations = renditions.iter().map(|r| r.name.as_str()).chain([AUDIO_REPRESENTATION]);
+                for representation in representations {
+                    let mut playlist = MediaPlaylist::new(self.segment_delay, MAX_FILES as usize);
+                    if record {
+                        playlist = playlist.with_archive();
+                    }
+                    self.live_registry.register_dash_playlist(channel, representation, Arc::new(LivePlaylist::new(playlist)));
+                }
+            }
+            let sprites = &crate::config::get_config().hls.sprites;
+            if sprites.enabled {
+                self.live_registry.register_sprite_track(channel, Arc::new(Mutex::new(SpriteTrack::new(sprites))));
+            }
         }
 
-        let root_playlist = format!("{}/{}/", stream_host, stream_name);
-        let pipeline = self.create_hls_pipeline(
+        // on_publish 는 tokio 런타임 안에서 호출되므로 현재 런타임에 저장 작업을 맡긴다.
+        // 이 함수에서 만드는 작업과 GStreamer 콜백은 호출한 쪽의 스트림 span 을 이어받는다.
+        // 이어 쓰는 경우 이전 파이프라인의 쓰기와 불연속 표시가 끝난 뒤에 새 세그먼트를 반영한다.
+        let (store_queue, store_task) = StoreQueue::spawn(self.store.clone(), &tokio::runtime::Handle::current(), previous);
+        let keep_open = Arc::new(AtomicBool::new(false));
+        let (gst_pipeline, app_src, slate_switch) = match self.create_hls_pipeline(
+            stream_id,
+            channel,
+            &renditions,
+            stream_name,
+            store_queue,
+            keep_open.clone(),
+        ) {
+            Ok(pipeline) => pipeline,
+            Err(e) => {
+                // 이어 쓰려던 스트림은 기다리던 작업이 마무리하고 Unpublish 이벤트를 보낸다.
+                match wake {
+                    Some(wake) => { let _ = wake.send(false); }
+                    None => self.live_registry.remove_channel(channel),
+                }
+                return Err(e);
+            }
+        };
+        if let Some(wake) = wake {
+            let _ = wake.send(true);
+        }
+
+        let on_error = {
+            let pipelines = self.pipelines.clone();
+            let live_registry = self.live_registry.clone();
+            let store = self.store.clone();
+            let finalizing = self.finalizing.clone();
+            let failures = self.failures.clone();
+            let webhooks = self.webhooks.clone();
+            let gst_pipeline = gst_pipeline.clone();
+            let channel = channel.to_string();
+            let stream_name = stream_name.to_string();
+            move |reason: String| {
+                // 새 송출을 이어 붙이면 stream id 와 StopSignal 이 바뀌므로 GStreamer 파이프라인으로 찾는다.
+                let failed = {
+                    let mut pipelines = pipelines.lock().unwrap();
+                    let stream_id = pipelines.iter().find(|(_, p)| p.pipeline == gst_pipeline).map(|(id, _)| *id);
+                    stream_id.and_then(|id| pipelines.remove(&id).map(|p| (id, p)))
+                };
+                let Some((stream_id, failed)) = failed else { return };
+                failed.stop.stop(format!("pipeline failed: {}", reason));
+                // 오류로 멈춘 파이프라인은 EOS 가 흘러가지 않으므로 기다리지 않는다.
+                finalizing.lock().unwrap().insert(stream_id, finalize(failed, &live_registry, store.clone(), None));
+                tracing::error!(stream_id, %reason, "GStreamer HLS conversion failed");
+
+                failures.lock().unwrap().insert(channel, reason.clone());
+                webhooks.send(StreamEvent::new(EventKind::Error, &stream_name).with_reason(reason));
+            }
+        };
+        let bus = gst_pipeline.bus().expect("pipeline always has a bus");
+        let drained = CancellationToken::new();
+        let slate_timeout = Duration::from_millis(crate::config::get_config().slate.timeout);
+        let pipeline = Pipeline {
+            bus_watch: watch_bus(stream_id, bus, drained.clone(), on_error),
+            slate_watch: slate_switch.as_ref().map(|switch| switch.watch(stream_id, ingest.clone(), slate_timeout)),
+            slate: slate_switch,
+            drained,
+            store_task,
+            pipeline: gst_pipeline,
+            app_src,
+            channel: channel.to_string(),
+            stream_name: stream_name.to_string(),
+            app_name: app_name.to_string(),
+            renditions: renditions.iter().map(|r| r.name.clone()).collect(),
+            ingest,
+            stop,
+            keep_open,
+            relays: Vec::new(),
+        };
+
+        let mut pipelines = self.pipelines.lock().unwrap();
+        pipelines.insert(stream_id, pipeline);
+        tracing::info!(
             stream_id,
-            &root_playlist,
-            &output_path,
-            self.segment_delay,
-        )?;
+            resumed,
+            record,
+            playlist = %format!("{}/{}/master.m3u8", crate::config::get_config().server.host, channel),
+            "HLS conversion started"
+        );
+        Ok(Conversion { resumed, stream_name: stream_name.to_string(), spliced: false })
+    }
+
+    /// 슬레이트를 내보내던 파이프라인에 새 송출자를 붙인다. 새 태그의 타임스탬프는 SlateSwitch 가 이어지게 맞춘다.
+    /// 유예 중 파이프라인이 실패하거나 강제 종료돼 이미 마무리 중이면 기다리던 스트림을 끝내고 None 을 돌려준다.
+    fn splice(
+        &self,
+        suspended: SuspendedStream,
+        stream_id: u32,
+        app_name: &str,
+        ingest: Arc<Ingest>,
+        stop: StopSignal,
+    ) -> Option<Conversion> {
         let mut pipelines = self.pipelines.lock().unwrap();
+        let Some(mut pipeline) = pipelines.remove(&suspended.stream_id) else {
+            let _ = suspended.wake.send(false);
+            return None;
+        };
+        if let Some(slate) = &pipeline.slate {
+            let slate_timeout = Duration::from_millis(crate::config::get_config().slate.timeout);
+            slate.select(false);
+            pipeline.slate_watch = Some(slate.watch(stream_id, ingest.clone(), slate_timeout));
+        }
+        pipeline.app_name = app_name.to_string();
+        pipeline.ingest = ingest;
+        pipeline.stop = stop;
+        pipeline.relays = Vec::new();
         pipelines.insert(stream_id, pipeline);
-        println!("HLS conversion started for stream {} (key: {})", stream_id, stream_name);
-        println!("Playlist available at: {}/playlist.m3u8", output_path);
-        Ok(())
+        let _ = suspended.wake.send(true);
+        tracing::info!(stream_id, previous_stream_id = suspended.stream_id, "Publisher reconnected, spliced into running pipeline");
+        Some(Conversion { resumed: true, stream_name: suspended.stream_name, spliced: true })
+    }
+
+    /// 채널에서 재연결을 기다리던 스트림을 꺼낸다. 다시 인증하면 경로의 시작 시각이 바뀔 수 있어 채널로 찾는다.
+    /// 화질 구성이 달라 이어 쓸 수 없으면 기다리던 스트림을 바로 끝낸다.
+    /// 꺼낸 스트림은 새 파이프라인이 뜬 뒤 wake 로 결과를 알려야 한다.
+    fn take_suspended(&self, channel: &str, renditions: &[RenditionConfig]) -> Option<SuspendedStream> {
+        let suspended = self.suspended.lock().unwrap().remove(channel)?;
+        if !same_renditions(&suspended.renditions, renditions) {
+            // 새 송출이 채널을 등록하기 전에 이전 파이프라인의 출력을 챙겨 둔다.
+            if suspended.on_slate {
+                finalize_on_slate(&self.pipelines, &self.finalizing, &self.live_registry, &self.store, suspended.stream_id);
+            }
+            let _ = suspended.wake.send(false);
+            return None;
+        }
+        Some(suspended)
+    }
+
+    /// 송출자가 끊겼을 때 reconnect_grace 동안 플레이리스트를 열어 두고 재연결을 기다린다.
+    /// 슬레이트를 쓰면 파이프라인을 그대로 두고 슬레이트를 내보내며, 아니면 파이프라인만 내린다.
+    /// 그 안에 재연결이 없으면 플레이리스트를 마무리하고 reason 으로 Unpublish 이벤트를 보낸다.
+    /// 유예 시간이 0 이거나 파이프라인이 이미 내려갔다면 false 를 돌려주며, 이때는 호출한 쪽이 종료를 처리한다.
+    pub fn suspend_hls_conversion(&self, stream_id: u32, reason: String) -> bool {
+        if self.reconnect_grace.is_zero() {
+            return false;
+        }
+        let mut pipelines = self.pipelines.lock().unwrap();
+        let Some(pipeline) = pipelines.get_mut(&stream_id) else {
+            return false;
+        };
+        let channel = pipeline.channel.clone();
+        let stream_name = pipeline.stream_name.clone();
+        let renditions = pipeline.renditions.clone();
+        let outputs = ChannelOutputs::of(&self.live_registry, &channel);
+        let store_task = match &pipeline.slate {
+            Some(slate) => {
+                if let Some(slate_watch) = pipeline.slate_watch.take() {
+                    slate_watch.cancel();
+                }
+                slate.select(true);
+                tracing::warn!(stream_id, "Publisher disconnected, switched to slate");
+                None
+            }
+            None => {
+                let pipeline = pipelines.remove(&stream_id).expect("pipeline was found above");
+                pipeline.keep_open.store(true, Ordering::SeqCst);
+                Some(stop_pipeline(pipeline, Some(Instant::now() + DRAIN_TIMEOUT)))
+            }
+        };
+        drop(pipelines);
+        let on_slate = store_task.is_none();
+
+        let (wake, mut woken) = oneshot::channel();
+        // 만료 처리가 자기 항목을 찾을 수 있도록 항목을 넣을 때까지 잠가 둔다.
+        let mut suspended = self.suspended.lock().unwrap();
+        let task = tokio::spawn({
+            let suspended = self.suspended.clone();
+            let pipelines = self.pipelines.clone();
+            let finalizing = self.finalizing.clone();
+            let store = self.store.clone();
+            let live_registry = self.live_registry.clone();
+            let webhooks = self.webhooks.clone();
+            let grace = self.reconnect_grace;
+            let channel = channel.clone();
+            let stream_name = stream_name.clone();
+            async move {
+                let resumed = tokio::select! {
+                    resumed = &mut woken => resumed.unwrap_or(false),
+                    _ = tokio::time::sleep(grace) => {
+                        let expired = {
+                            let mut suspended = suspended.lock().unwrap();
+                            let expired = match suspended.get(&channel) {
+                                Some(s) if s.stream_id == stream_id => suspended.remove(&channel).is_some(),
+                                _ => false,
+                            };
+                            // 잠근 채로 내려야 같은 채널의 새 송출이 등록된 뒤 채널을 지우지 않는다.
+                            if expired && on_slate {
+                                finalize_on_slate(&pipelines, &finalizing, &live_registry, &store, stream_id);
+                            }
+                            expired
+                        };
+                        // 만료와 동시에 재연결이 항목을 가져갔다면 그쪽의 결정을 따른다.
+                        if expired { false } else { woken.await.unwrap_or(false) }
+                    }
+                };
+                let archive = match store_task {
+                    // 이어 붙였다면 파이프라인이 끊긴 적이 없다.
+                    None if resumed => return,
+                    None => {
+                        // 만료, 화질 변경, 유예 중 실패나 강제 종료 중 어느 쪽이든 마무리 작업이 남아 있다.
+                        let finalized = finalizing.lock().unwrap().remove(&stream_id);
+                        match finalized {
+                            Some(finalized) => finalized.await.ok().flatten(),
+                            None => None,
+                        }
+                    }
+                    Some(store_task) => {
+                        let _ = store_task.await;
+                        if resumed {
+                            for (_, playlist) in outputs.playlists.iter().chain(&outputs.dash_playlists) {
+                                playlist.update(|p| p.mark_discontinuity());
+                            }
+                            return;
+                        }
+                        let archive = finalize_playlists(store.as_ref(), &stream_name, outputs).await;
+                        live_registry.remove_output(&channel, &stream_name);
+                        archive
+                    }
+                };
+                tracing::info!(stream_id, "Publisher did not reconnect, stream ended");
+                webhooks.send(StreamEvent::new(EventKind::Unpublish, &stream_name).with_reason(reason).with_archive(archive));
+            }.in_current_span()
+        });
+        suspended.insert(channel, SuspendedStream { stream_id, stream_name, renditions, on_slate, wake, task });
+        tracing::info!(stream_id, grace_ms = self.reconnect_grace.as_millis() as u64, "Waiting for publisher to reconnect");
+        true
+    }
+
+    fn new_playlist(&self, record: bool) -> MediaPlaylist {
+        let mut media_playlist = MediaPlaylist::new(self.segment_delay, MAX_FILES as usize);
+        if let Some(part_duration) = self.part_duration {
+            media_playlist = media_playlist.with_low_latency(part_duration as f64 / 1000.0);
+        }
+        if record {
+            media_playlist = media_playlist.with_archive();
+        }
+        let dvr_window = crate::config::get_config().hls.dvr.window;
+        if dvr_window > 0 {
+            media_playlist = media_playlist.with_dvr(dvr_window as f64 / 1000.0);
+        }
+        media_playlist
     }
 
     fn create_hls_pipeline(
         &self,
         stream_id: u32,
-        root_playlist: &str,
-        output_path: &str,
-        segment_delay: u32,
-    ) -> Result<Pipeline, Box<dyn Error + Send + Sync>> {
+        channel: &str,
+        renditions: &[RenditionConfig],
+        output_key: &str,
+        store_queue: StoreQueue,
+        keep_open: Arc<AtomicBool>,
+    ) -> Result<(gst::Pipeline, AppSrc, Option<SlateSwitch>), Box<dyn Error + Send + Sync>> {
+        let segment_delay = self.segment_delay;
         let pipeline = gst::Pipeline::new();
 
         let (app_src, flvdemux) = create_source(stream_id)?;
-        let video_elements = create_video(stream_id)?;
-        let audio_elements = create_audio(stream_id)?;
-        let (mpeg_ts_mux, hls_sink) = create_output(
-            stream_id,
-            root_playlist,
-            output_path,
-            segment_delay
-        )?;
-
-        pipeline.add_many(&[
-            &app_src, &flvdemux,
-            &video_elements.0, &video_elements.1,
-            &audio_elements.0, &audio_elements.1,
-            &mpeg_ts_mux, &hls_sink,
-        ])?;
+        let video_chain = create_video(stream_id)?;
+        let audio_chain = create_audio(stream_id)?;
+
+        pipeline.add_many([&app_src, &flvdemux])?;
+        pipeline.add_many(&video_chain)?;
+        pipeline.add_many(&audio_chain)?;
 
         app_src.link(&flvdemux)?;
-        mpeg_ts_mux.link(&hls_sink)?;
+        let slate = &crate::config::get_config().slate;
+        let slate_switch = if slate.enabled {
+            // 디코더와 tee 사이에 input-selector 를 넣어 원본이 끊기면 슬레이트를 내보낸다.
+            let mut switch = SlateSwitch::default();
+            let slate_video = create_slate_video(stream_id, slate, &renditions[0])?;
+            let slate_audio = create_slate_audio(stream_id)?;
+            for (kind, chain, slate_chain) in [("video", &video_chain, slate_video), ("audio", &audio_chain, slate_audio)] {
+                let selector = create_input_selector(stream_id, kind)?;
+                pipeline.add_many(&slate_chain)?;
+                pipeline.add_many([&selector])?;
+                gst::Element::link_many(&slate_chain)?;
+                gst::Element::link_many(&chain[..chain.len() - 1])?;
+                switch.add(&selector, &chain[chain.len() - 2], &slate_chain[slate_chain.len() - 1])?;
+                selector.link(&chain[chain.len() - 1])?;
+            }
+            Some(switch)
+        } else {
+            gst::Element::link_many(&video_chain)?;
+            gst::Element::link_many(&audio_chain)?;
+            None
+        };
+
+        let video_tee = &video_chain[video_chain.len() - 1];
+        let audio_tee = &audio_chain[audio_chain.len() - 1];
+
+        // 화질 중 하나라도 플레이리스트를 처음 기록하면 채널에 한 번만 알린다.
+        // 재연결로 파이프라인을 새로 만들어도 채널 등록은 그대로이므로 다시 알리지 않는다.
+        let playlist_ready: Arc<dyn Fn() + Send + Sync> = {
+            let live_registry = self.live_registry.clone();
+            let webhooks = self.webhooks.clone();
+            let channel = channel.to_string();
+            let stream_name = output_key.to_string();
+            Arc::new(move || {
+                if live_registry.mark_playlist_ready(&channel, &stream_name) {
+                    webhooks.send(StreamEvent::new(EventKind::PlaylistReady, &stream_name));
+                }
+            })
+        };
+
+        let dash = crate::config::get_config().dash.enabled;
+        let mut rendition_links = Vec::with_capacity(renditions.len());
+        for rendition in renditions {
+            let rendition_video = create_rendition_video(stream_id, rendition, segment_delay)?;
+            let rendition_audio = create_rendition_audio(stream_id, rendition)?;
+            let (mux, sink) = create_output(
+                stream_id,
+                rendition,
+                self.output_mode,
+                segment_delay,
+                self.part_duration,
+            )?;
+
+            // 재연결된 스트림이면 이전 파이프라인의 플레이리스트를 이어 쓴다.
+            let live_playlist = self.live_registry.playlist(channel, &rendition.name)
+                .ok_or("playlist is not registered")?;
+
+            let output = SegmentOutput::new(
+                format!("{}/{}", output_key, rendition.name),
+                store_queue.clone(),
+                live_playlist,
+            )
+                .with_first_playlist_hook(playlist_ready.clone())
+                .with_keep_open(keep_open.clone());
+            let app_sink = sink.downcast_ref::<AppSink>().ok_or("output sink is not an appsink")?;
+            match self.output_mode {
+                OutputMode::Ts => TsWriter::new(output, segment_delay).attach(app_sink),
+                OutputMode::Fmp4 => Fmp4Writer::new(output, segment_delay, self.part_duration.is_some()).attach(app_sink),
+            }
+
+            pipeline.add_many(&rendition_video)?;
+            pipeline.add_many(&rendition_audio)?;
+            pipeline.add_many([&mux, &sink])?;
 
-        setup_dynamic_pads(&flvdemux, video_elements, audio_elements, &mpeg_ts_mux);
+            video_tee.link(&rendition_video[0])?;
+            gst::Element::link_many(&rendition_video)?;
+            audio_tee.link(&rendition_audio[0])?;
+            gst::Element::link_many(&rendition_audio)?;
+            mux.link(&sink)?;
+
+            let mut video_tail = rendition_video[rendition_video.len() - 1].clone();
+            if dash {
+                // 인코딩한 영상을 나눠 영상만 담은 DASH 출력을 하나 더 만든다.
+                let (tee, hls_queue, dash_queue) = create_dash_video_split(stream_id, rendition)?;
+                let (dash_mux, dash_sink) = create_dash_output(stream_id, &rendition.name, segment_delay)?;
+                pipeline.add_many([&tee, &hls_queue, &dash_queue, &dash_mux, &dash_sink])?;
+                video_tail.link(&tee)?;
+                tee.link(&hls_queue)?;
+                tee.link(&dash_queue)?;
+                dash_mux.link(&dash_sink)?;
+                self.attach_dash_writer(channel, &rendition.name, output_key, &store_queue, &keep_open, &dash_sink)?;
+                rendition_links.push(RenditionLinks { video_tail: Some(dash_queue), audio_tail: None, mux: dash_mux });
+                video_tail = hls_queue;
+            }
+
+            rendition_links.push(RenditionLinks {
+                video_tail: Some(video_tail),
+                audio_tail: Some(rendition_audio[rendition_audio.len() - 1].clone()),
+                mux,
+            });
+        }
+
+        if dash {
+            let bitrate = renditions.iter().map(|r| r.audio_bitrate).max().unwrap_or_default();
+            let dash_audio = create_dash_audio(stream_id, bitrate)?;
+            let (dash_mux, dash_sink) = create_dash_output(stream_id, AUDIO_REPRESENTATION, segment_delay)?;
+            pipeline.add_many(&dash_audio)?;
+            pipeline.add_many([&dash_mux, &dash_sink])?;
+            audio_tee.link(&dash_audio[0])?;
+            gst::Element::link_many(&dash_audio)?;
+            dash_mux.link(&dash_sink)?;
+            self.attach_dash_writer(channel, AUDIO_REPRESENTATION, output_key, &store_queue, &keep_open, &dash_sink)?;
+            rendition_links.push(RenditionLinks {
+                video_tail: None,
+                audio_tail: Some(dash_audio[dash_audio.len() - 1].clone()),
+                mux: dash_mux,
+            });
+        }
+
+        let sprites = &crate::config::get_config().hls.sprites;
+        if let Some(sprite_track) = self.live_registry.sprite_track(channel) {
+            let tile_chain = create_sprite_tiles(stream_id, sprites)?;
+            let encoder_chain = create_sprite_encoder(stream_id, sprites)?;
+            pipeline.add_many(&tile_chain)?;
+            pipeline.add_many(&encoder_chain)?;
+            gst::Element::link_many(&tile_chain)?;
+            gst::Element::link_many(&encoder_chain)?;
+            video_tee.link(&tile_chain[0])?;
+
+            // 재연결된 스트림이면 이어 쓰는 플레이리스트의 길이부터 시간을 센다.
+            let time_base = self.live_registry.playlist(channel, &renditions[0].name)
+                .map_or(0.0, |p| p.read(|p| p.total_duration()));
+            let encoder = encoder_chain[0].clone().downcast::<AppSrc>()
+                .map_err(|_| "sprite source is not an appsrc")?;
+            let tile_sink = tile_chain[tile_chain.len() - 1].downcast_ref::<AppSink>()
+                .ok_or("sprite tile sink is not an appsink")?;
+            let sheet_sink = encoder_chain[encoder_chain.len() - 1].downcast_ref::<AppSink>()
+                .ok_or("sprite sheet sink is not an appsink")?;
+            SpriteWriter::new(sprite_track, sprites, time_base, encoder)
+                .attach(tile_sink, sheet_sink, output_key, store_queue.clone());
+        }
+
+        let thumbnail = &crate::config::get_config().hls.thumbnail;
+        if thumbnail.enabled {
+            let thumbnail_chain = create_thumbnail(stream_id, thumbnail)?;
+            pipeline.add_many(&thumbnail_chain)?;
+            gst::Element::link_many(&thumbnail_chain)?;
+            video_tee.link(&thumbnail_chain[0])?;
+            let app_sink = thumbnail_chain[thumbnail_chain.len() - 1].downcast_ref::<AppSink>()
+                .ok_or("thumbnail sink is not an appsink")?;
+            ThumbnailWriter::new(channel, output_key, self.live_registry.clone(), store_queue).attach(app_sink);
+        }
+
+        setup_dynamic_pads(&flvdemux, video_chain[0].clone(), audio_chain[0].clone(), rendition_links);
         pipeline.set_state(gst::State::Playing)?;
 
         let app_src_element = app_src.downcast::<AppSrc>().unwrap();
-        Ok(Pipeline { pipeline, app_src: app_src_element })
+        Ok((pipeline, app_src_element, slate_switch))
+    }
+
+    /// DASH Representation 하나의 세그먼트를 "출력위치/dash/이름" 아래에 기록한다.
+    fn attach_dash_writer(
+        &self,
+        channel: &str,
+        representation: &str,
+        output_key: &str,
+        store_queue: &StoreQueue,
+        keep_open: &Arc<AtomicBool>,
+        sink: &gst::Element,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let playlist = self.live_registry.dash_playlist(channel, representation)
+            .ok_or("dash playlist is not registered")?;
+        let output = SegmentOutput::new(format!("{}/dash/{}", output_key, representation), store_queue.clone(), playlist)
+            .with_keep_open(keep_open.clone());
+        let app_sink = sink.downcast_ref::<AppSink>().ok_or("dash sink is not an appsink")?;
+        Fmp4Writer::new(output, self.segment_delay, false).attach(app_sink);
+        Ok(())
     }
 
+    /// 파이프라인을 내리고, 남은 저장이 끝나면 플레이리스트에 ENDLIST 를 붙이는 작업을 시작한다.
     pub fn stop_hls_conversion(&self, stream_id: u32) {
-        let mut pipelines = self.pipelines.lock().unwrap();
-        if let Some(pipeline_info) = pipelines.remove(&stream_id) {
-            let _ = pipeline_info.app_src.end_of_stream();
-            let _ = pipeline_info.pipeline.set_state(gst::State::Null);
-            println!("GStreamer HLS conversion stopped for stream {}", stream_id);
+        let Some(pipeline) = self.pipelines.lock().unwrap().remove(&stream_id) else {
+            return;
+        };
+        let finalized = finalize(pipeline, &self.live_registry, self.store.clone(), Some(Instant::now() + DRAIN_TIMEOUT));
+        self.finalizing.lock().unwrap().insert(stream_id, finalized);
+        tracing::info!(stream_id, "GStreamer HLS conversion stopped");
+    }
+
+    /// 내린 파이프라인의 마무리 작업을 꺼낸다. 작업은 녹화했다면 VOD 플레이리스트 key 를 돌려준다.
+    pub fn take_finalizing(&self, stream_id: u32) -> Option<JoinHandle<Option<String>>> {
+        self.finalizing.lock().unwrap().remove(&stream_id)
+    }
+
+    /// 서버 종료 시 모든 파이프라인에 EOS 를 보내 마지막 세그먼트와 ENDLIST 가 기록되길 기다린다.
+    /// timeout 안에 끝나지 않은 파이프라인은 그대로 내린다.
+    pub async fn shutdown(&self, timeout: Duration) {
+        let pipelines: Vec<(u32, Pipeline)> = self.pipelines.lock().unwrap().drain().collect();
+        let deadline = Instant::now() + timeout;
+        join_all(pipelines.into_iter().map(|(stream_id, pipeline)| self.drain(stream_id, pipeline, deadline))).await;
+
+        // 재연결을 기다리던 스트림도 바로 마무리한다.
+        let suspended: Vec<SuspendedStream> = self.suspended.lock().unwrap().drain().map(|(_, s)| s).collect();
+        let tasks = suspended.into_iter().map(|s| {
+            let _ = s.wake.send(false);
+            s.task
+        });
+        if tokio::time::timeout_at(deadline, join_all(tasks)).await.is_err() {
+            tracing::warn!("Suspended streams were not finalized before shutdown deadline");
+        }
+    }
+
+    async fn drain(&self, stream_id: u32, pipeline: Pipeline, deadline: Instant) {
+        pipeline.stop.stop("server shutting down".to_string());
+        let finalized = finalize(pipeline, &self.live_registry, self.store.clone(), Some(deadline));
+        if tokio::time::timeout_at(deadline, finalized).await.is_err() {
+            tracing::warn!(stream_id, "Pending storage writes dropped at shutdown");
+        }
+        tracing::info!(stream_id, "GStreamer HLS conversion finalized");
+    }
+
+    /// 슬레이트를 쓰는 파이프라인이면 원본 타임스탬프를 파이프라인 running time 에 맞춰 돌려준다.
+    pub fn rebase_timestamp(&self, stream_id: u32, timestamp: u32) -> u32 {
+        match self.pipelines.lock().unwrap().get(&stream_id) {
+            Some(Pipeline { slate: Some(slate), pipeline, .. }) => slate.rebase(timestamp, pipeline.current_running_time()),
+            _ => timestamp,
         }
     }
 
@@ -142,4 +810,148 @@ impl HlsConvertor {
         tag.extend_from_slice(&tag_size.to_be_bytes());
         tag
     }
-}
\ No newline at end of file
+}
+
+/// 파이프라인을 내리고, 남은 쓰기를 마치면 플레이리스트를 마무리하는 작업을 돌려준다.
+/// drain_deadline 은 stop_pipeline 과 같다.
+/// 마무리가 끝날 때까지 채널을 등록해 두어 정리 작업이 쓰는 중인 출력을 지우지 않게 한다.
+fn finalize(
+    pipeline: Pipeline,
+    live_registry: &Arc<LiveRegistry>,
+    store: Arc<dyn SegmentStore>,
+    drain_deadline: Option<Instant>,
+) -> JoinHandle<Option<String>> {
+    let channel = pipeline.channel.clone();
+    let stream_name = pipeline.stream_name.clone();
+    let outputs = ChannelOutputs::of(live_registry, &channel);
+    let live_registry = live_registry.clone();
+    let store_task = stop_pipeline(pipeline, drain_deadline);
+    tokio::spawn(async move {
+        let _ = store_task.await;
+        let archive = finalize_playlists(store.as_ref(), &stream_name, outputs).await;
+        live_registry.remove_output(&channel, &stream_name);
+        archive
+    }.in_current_span())
+}
+
+/// 재연결을 기다리며 슬레이트를 내보내던 파이프라인을 내린다. 이미 내려갔다면 아무것도 하지 않는다.
+/// 마무리 작업은 finalizing 에 남겨 기다리던 작업이 꺼내 Unpublish 이벤트를 보낸다.
+fn finalize_on_slate(
+    pipelines: &Mutex<HashMap<u32, Pipeline>>,
+    finalizing: &Mutex<HashMap<u32, JoinHandle<Option<String>>>>,
+    live_registry: &Arc<LiveRegistry>,
+    store: &Arc<dyn SegmentStore>,
+    stream_id: u32,
+) {
+    let Some(pipeline) = pipelines.lock().unwrap().remove(&stream_id) else { return };
+    finalizing.lock().unwrap().insert(stream_id, finalize(pipeline, live_registry, store.clone(), Some(Instant::now() + DRAIN_TIMEOUT)));
+}
+
+/// 방송이 끝날 때 마무리할 채널의 메모리 출력들
+struct ChannelOutputs {
+    started_at: SystemTime,
+    playlists: Vec<(String, Arc<LivePlaylist>)>,
+    dash_playlists: Vec<(String, Arc<LivePlaylist>)>,
+    sprite_track: Option<Arc<Mutex<SpriteTrack>>>,
+}
+
+impl ChannelOutputs {
+    fn of(live_registry: &LiveRegistry, channel: &str) -> Self {
+        Self {
+            started_at: live_registry.started_at(channel).unwrap_or_else(SystemTime::now),
+            playlists: live_registry.playlists(channel),
+            dash_playlists: live_registry.dash_playlists(channel),
+            sprite_track: live_registry.sprite_track(channel),
+        }
+    }
+}
+
+/// 플레이리스트(DVR 이 켜져 있으면 dvr.m3u8 도)에 ENDLIST 를 붙여 저장하고, 녹화했다면 화질별 vod.m3u8 과 이를 묶는 vod.m3u8 을 기록한다.
+/// DASH 를 켰다면 남은 세그먼트로 static 매니페스트(dash/manifest.mpd)를 남긴다.
+/// 마스터 VOD 플레이리스트는 탐색 미리보기(thumbnails.vtt)가 있으면 함께 가리킨다.
+/// 녹화본이 있으면 마스터 VOD 플레이리스트의 key 를 돌려준다.
+async fn finalize_playlists(store: &dyn SegmentStore, output_key: &str, outputs: ChannelOutputs) -> Option<String> {
+    let config = crate::config::get_config();
+    // 녹화하지 않았다면 윈도우 밖 세그먼트는 이미 지워졌으므로 static 매니페스트를 남기지 않는다.
+    let dash_recorded = outputs.dash_playlists.iter().any(|(_, playlist)| playlist.read(|p| p.archived_segments().is_some()));
+    if dash_recorded {
+        for (_, playlist) in &outputs.dash_playlists {
+            playlist.update(|p| p.end());
+        }
+        let (video, audio) = split_representations(&config.hls.renditions, outputs.dash_playlists);
+        if !video.is_empty() {
+            let key = format!("{}/dash/manifest.mpd", output_key);
+            let manifest = render_manifest(outputs.started_at, config.server.segment_delay, &video, audio);
+            store.put_playlist(&key, manifest).await
+                .log_error(&format!("Failed to store {}", key));
+        }
+    }
+
+    let mut recorded = Vec::new();
+    for (rendition, playlist) in outputs.playlists {
+        playlist.update(|p| p.end());
+        let key = format!("{}/{}/playlist.m3u8", output_key, rendition);
+        store.put_playlist(&key, playlist.render()).await
+            .log_error(&format!("Failed to store {}", key));
+
+        if let Some(dvr) = playlist.read(|p| p.render_dvr()) {
+            let key = format!("{}/{}/dvr.m3u8", output_key, rendition);
+            store.put_playlist(&key, dvr).await
+                .log_error(&format!("Failed to store {}", key));
+        }
+        if let Some(vod) = playlist.read(|p| p.render_vod()) {
+            let key = format!("{}/{}/vod.m3u8", output_key, rendition);
+            store.put_playlist(&key, vod).await
+                .log_error(&format!("Failed to store {}", key));
+            recorded.push(rendition);
+        }
+    }
+    if recorded.is_empty() {
+        return None;
+    }
+
+    let renditions = config.hls.renditions.iter()
+        .filter(|r| recorded.contains(&r.name));
+    let key = format!("{}/vod.m3u8", output_key);
+    let thumbnails = outputs.sprite_track
+        .is_some_and(|track| !track.lock().unwrap().is_empty())
+        .then_some("thumbnails.vtt");
+    store.put_playlist(&key, render_master_playlist(renditions, "vod.m3u8", thumbnails)).await
+        .log_error(&format!("Failed to store {}", key))?;
+    Some(key)
+}
+
+/// 채널의 플레이리스트는 남겨 두고 파이프라인만 내린다.
+/// drain_deadline 이 있으면 EOS 를 보내고 마지막 세그먼트가 기록되길 그때까지 기다린 뒤 내린다.
+/// 돌려준 작업은 남은 저장까지 마치면 끝난다.
+fn stop_pipeline(pipeline: Pipeline, drain_deadline: Option<Instant>) -> JoinHandle<()> {
+    if let Some(slate_watch) = &pipeline.slate_watch {
+        slate_watch.cancel();
+    }
+    // 슬레이트가 선택돼 있으면 원본의 EOS 가 input-selector 에서 막힌다.
+    if let Some(slate) = &pipeline.slate {
+        slate.select(false);
+    }
+    let _ = pipeline.app_src.end_of_stream();
+    tokio::spawn(async move {
+        // EOS 는 bus 감시가 받아 drained 를 취소하므로 감시는 기다린 뒤에 멈춘다.
+        if let Some(deadline) = drain_deadline
+            && tokio::time::timeout_at(deadline, pipeline.drained.cancelled()).await.is_err() {
+            tracing::warn!("Pipeline did not reach EOS before deadline, last segment dropped");
+        }
+        pipeline.bus_watch.cancel();
+        // Null 로 바꾸면 스트리밍 스레드가 끝나길 기다리므로 런타임 워커를 막지 않게 따로 돌린다.
+        let gst_pipeline = pipeline.pipeline;
+        let _ = tokio::task::spawn_blocking(move || gst_pipeline.set_state(gst::State::Null)).await;
+        let _ = pipeline.store_task.await;
+    }.in_current_span())
+}
+
+fn same_renditions(suspended: &[String], renditions: &[RenditionConfig]) -> bool {
+    suspended.len() == renditions.len() && suspended.iter().zip(renditions).all(|(a, b)| *a == b.name)
+}
+
+/// 인증된 경로("닉네임/시작시각")에서 HTTP 에 노출되는 채널 이름(닉네임)을 꺼낸다.
+fn channel_name(stream_name: &str) -> &str {
+    stream_name.split('/').next().unwrap_or(stream_name)
+}
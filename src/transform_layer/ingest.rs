@@ -0,0 +1,147 @@
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+/// 송출자 한 명에게서 받은 데이터 통계. Handler 가 갱신하고 관리자 API 가 읽는다.
+pub struct Ingest {
+    pub publisher_addr: SocketAddr,
+    pub started_at: SystemTime,
+    bytes: AtomicU64,
+    created: Instant,
+    /// created 부터 마지막 태그를 받기까지의 시간(ms)
+    last_tag: AtomicU64,
+    recent: Mutex<RateWindow>,
+    video_codec: AtomicU8,
+    audio_codec: AtomicU8,
+}
+
+const UNKNOWN_CODEC: u8 = u8::MAX;
+/// 비트레이트를 재는 구간(초)
+const RATE_WINDOW_SECS: u64 = 10;
+
+/// 초 단위 칸에 받은 바이트를 모아 최근 RATE_WINDOW_SECS 초의 비트레이트를 잰다.
+#[derive(Default)]
+struct RateWindow {
+    /// (created 부터의 초, 그 1초 동안 받은 바이트)
+    buckets: [(u64, u64); RATE_WINDOW_SECS as usize],
+}
+
+impl RateWindow {
+    fn record(&mut self, now: Duration, bytes: u64) {
+        let sec = now.as_secs();
+        let bucket = &mut self.buckets[(sec % RATE_WINDOW_SECS) as usize];
+        if bucket.0 != sec {
+            *bucket = (sec, 0);
+        }
+        bucket.1 += bytes;
+    }
+
+    /// 지금 채우는 칸은 지난 만큼만 구간에 넣는다.
+    fn kbps(&self, now: Duration) -> u64 {
+        let sec = now.as_secs();
+        let bytes: u64 = self.buckets.iter()
+            .filter(|(bucket_sec, _)| *bucket_sec <= sec && sec - bucket_sec < RATE_WINDOW_SECS)
+            .map(|(_, bytes)| bytes)
+            .sum();
+        let window = (now.as_millis() as u64).min((RATE_WINDOW_SECS - 1) * 1000 + now.subsec_millis() as u64);
+        if window == 0 {
+            return 0;
+        }
+        bytes * 8 / window
+    }
+}
+
+impl Ingest {
+    pub fn new(publisher_addr: SocketAddr) -> Self {
+        Self {
+            publisher_addr,
+            started_at: SystemTime::now(),
+            bytes: AtomicU64::new(0),
+            created: Instant::now(),
+            last_tag: AtomicU64::new(0),
+            recent: Mutex::default(),
+            video_codec: AtomicU8::new(UNKNOWN_CODEC),
+            audio_codec: AtomicU8::new(UNKNOWN_CODEC),
+        }
+    }
+
+    /// FLV 태그 하나를 기록한다. 코덱 id 는 태그 본문의 첫 바이트에 들어 있다.
+    pub fn record_tag(&self, tag_type: u8, payload: &[u8]) {
+        let now = self.created.elapsed();
+        self.bytes.fetch_add(payload.len() as u64, Ordering::Relaxed);
+        self.recent.lock().unwrap().record(now, payload.len() as u64);
+        self.last_tag.store(now.as_millis() as u64, Ordering::Relaxed);
+        match (tag_type, payload.first()) {
+            (9, Some(&flags)) => self.video_codec.store(flags & 0x0f, Ordering::Relaxed),
+            (8, Some(&flags)) => self.audio_codec.store(flags >> 4, Ordering::Relaxed),
+            _ => {}
+        }
+    }
+
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// 마지막 태그를 받은 뒤 지난 시간. 아직 태그가 없으면 송출 시작부터 잰다.
+    pub fn idle_for(&self) -> Duration {
+        let last_tag = Duration::from_millis(self.last_tag.load(Ordering::Relaxed));
+        self.created.elapsed().saturating_sub(last_tag)
+    }
+
+    /// 최근 RATE_WINDOW_SECS 초 동안의 수신 비트레이트 (kbps)
+    pub fn bitrate_kbps(&self) -> u64 {
+        self.recent.lock().unwrap().kbps(self.created.elapsed())
+    }
+
+    pub fn video_codec(&self) -> Option<&'static str> {
+        match self.video_codec.load(Ordering::Relaxed) {
+            2 => Some("h263"),
+            4 | 5 => Some("vp6"),
+            7 => Some("h264"),
+            12 => Some("h265"),
+            _ => None,
+        }
+    }
+
+    pub fn audio_codec(&self) -> Option<&'static str> {
+        match self.audio_codec.load(Ordering::Relaxed) {
+            2 | 14 => Some("mp3"),
+            10 => Some("aac"),
+            11 => Some("speex"),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(window: &mut RateWindow, from_ms: u64, to_ms: u64, bytes_per_100ms: u64) {
+        for ms in (from_ms..to_ms).step_by(100) {
+            window.record(Duration::from_millis(ms), bytes_per_100ms);
+        }
+    }
+
+    #[test]
+    fn measures_recent_seconds() {
+        let mut window = RateWindow::default();
+        // 1000 바이트 / 100ms = 80 kbps
+        feed(&mut window, 0, 20_000, 1000);
+        assert_eq!(window.kbps(Duration::from_millis(20_000)), 80);
+        // 평균이면 40 kbps 로 보이겠지만 최근 10초만 본다.
+        feed(&mut window, 20_000, 40_000, 0);
+        assert_eq!(window.kbps(Duration::from_millis(40_000)), 0);
+        feed(&mut window, 40_000, 50_000, 500);
+        assert_eq!(window.kbps(Duration::from_millis(50_000)), 40);
+    }
+
+    #[test]
+    fn counts_partial_window_from_start() {
+        let mut window = RateWindow::default();
+        feed(&mut window, 0, 2_000, 1000);
+        assert_eq!(window.kbps(Duration::from_millis(2_000)), 80);
+        assert_eq!(window.kbps(Duration::ZERO), 0);
+    }
+}
@@ -1,4 +1,8 @@
 pub mod hls_convertor;
 mod pipelines;
 pub mod pads;
-pub mod gstreamer;
\ No newline at end of file
+pub mod gstreamer;
+pub mod playlist;
+mod sinks;
+pub mod ingest;
+pub mod stop_signal;
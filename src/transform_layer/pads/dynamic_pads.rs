@@ -2,49 +2,54 @@ use gst::Element;
 use gstreamer::prelude::{ElementExt, ElementExtManual, GstObjectExt, PadExt};
 use gstreamer_app::gst;
 
+/// 화질 단계 하나가 먹서(mux)에 연결될 때 필요한 요소들.
+/// 트랙이 실제로 존재할 때만 먹서 패드를 요청하도록 pad-added 시점에 연결한다.
+/// DASH 출력처럼 한 트랙만 담는 먹서는 다른 쪽 tail 이 None 이다.
+pub struct RenditionLinks {
+    pub video_tail: Option<Element>,
+    pub audio_tail: Option<Element>,
+    pub mux: Element,
+}
+
 pub fn setup_dynamic_pads(
     flvdemux: &Element,
-    video_elements: (Element, Element),
-    audio_elements: (Element, Element),
-    mpeg_ts_mux: &Element,
+    video_entry: Element,
+    audio_entry: Element,
+    renditions: Vec<RenditionLinks>,
 ) {
-    let (video_queue, h264_parse) = video_elements;
-    let (audio_queue, aac_parse) = audio_elements;
-    let mux_clone = mpeg_ts_mux.clone();
-
+    // pad-added 는 GStreamer 스트리밍 스레드에서 호출되므로 스트림 span 을 붙잡아 둔다.
+    let span = tracing::Span::current();
     flvdemux.connect_pad_added(move |_, pad| {
+        let _enter = span.enter();
         let pad_name = pad.name();
 
         match pad_name.as_str() {
             name if name.starts_with("video") => {
-                link_video_pipeline(pad, &video_queue, &h264_parse, &mux_clone);
+                let tails = renditions.iter().filter_map(|r| Some((r.video_tail.as_ref()?, &r.mux)));
+                if link_branch(pad, &video_entry, tails) {
+                    tracing::info!(renditions = renditions.len(), "Video pipeline connected");
+                }
             }
             name if name.starts_with("audio") => {
-                link_audio_pipeline(pad, &audio_queue, &aac_parse, &mux_clone);
+                let tails = renditions.iter().filter_map(|r| Some((r.audio_tail.as_ref()?, &r.mux)));
+                if link_branch(pad, &audio_entry, tails) {
+                    tracing::info!(renditions = renditions.len(), "Audio pipeline connected");
+                }
             }
-            _ => eprintln!("unknown pad: {}", pad_name)
+            _ => tracing::warn!(pad = %pad_name, "unknown pad")
         }
     });
 }
 
-fn link_video_pipeline(pad: &gst::Pad, queue: &Element, parser: &Element, mux: &Element) {
-    let sink_pad = queue.static_pad("sink").unwrap();
-    if sink_pad.is_linked() { return; }
+fn link_branch<'a>(
+    pad: &gst::Pad,
+    entry: &Element,
+    mut tails: impl Iterator<Item = (&'a Element, &'a Element)>,
+) -> bool {
+    let sink_pad = entry.static_pad("sink").unwrap();
+    if sink_pad.is_linked() { return false; }
 
-    if pad.link(&sink_pad).is_ok()
-        && queue.link(parser).is_ok()
-        && parser.link(mux).is_ok() {
-        println!("Video pipeline connected");
-    }
+    // 데이터가 흐르기 전에 먹서 쪽을 먼저 연결해 둔다.
+    tails.all(|(tail, mux)| tail.link(mux).is_ok())
+        && pad.link(&sink_pad).is_ok()
 }
-
-fn link_audio_pipeline(pad: &gst::Pad, queue: &Element, parser: &Element, mux: &Element) {
-    let sink_pad = queue.static_pad("sink").unwrap();
-    if sink_pad.is_linked() { return; }
-
-    if pad.link(&sink_pad).is_ok()
-        && queue.link(parser).is_ok()
-        && parser.link(mux).is_ok() {
-        println!("Audio pipeline connected");
-    }
-}
\ No newline at end of file
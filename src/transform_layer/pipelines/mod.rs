@@ -1 +1,3 @@
-pub mod pipeline_elements;
\ No newline at end of file
+pub mod pipeline_elements;
+pub mod slate_elements;
+pub mod thumbnail_elements;
@@ -1,54 +1,240 @@
 use gstreamer_app::glib::BoolError;
 use gstreamer_app::gst;
+use crate::config::{OutputMode, RenditionConfig};
 
 pub fn create_source(stream_id: u32) -> Result<(gst::Element, gst::Element), BoolError> {
     let app_src = gst::ElementFactory::make("appsrc")
-        .property("name", &format!("appsrc-{}", stream_id))
+        .property("name", format!("appsrc-{}", stream_id))
         .property("format", gst::Format::Time)
         .build()?;
     let flvdemux = gst::ElementFactory::make("flvdemux")
-        .property("name", &format!("flvdemux-{}", stream_id))
+        .property("name", format!("flvdemux-{}", stream_id))
         .build()?;
     Ok((app_src, flvdemux))
 }
 
-pub fn create_video(stream_id: u32) -> Result<(gst::Element, gst::Element), BoolError>  {
+/// 원본 영상을 한 번만 디코딩한 뒤 tee 로 각 화질 단계에 나눠준다.
+/// queue -> h264parse -> avdec_h264 -> tee 순서로 반환한다.
+pub fn create_video(stream_id: u32) -> Result<Vec<gst::Element>, BoolError>  {
     let video_queue = gst::ElementFactory::make("queue")
-        .property("name", &format!("videoqueue-{}", stream_id))
+        .property("name", format!("videoqueue-{}", stream_id))
         .build()?;
 
     let h264parse = gst::ElementFactory::make("h264parse")
-        .property("name", &format!("h264parse-{}", stream_id))
+        .property("name", format!("h264parse-{}", stream_id))
         .property("config-interval", -1i32)
         .build()?;
 
-    Ok((video_queue, h264parse))
+    let decoder = gst::ElementFactory::make("avdec_h264")
+        .property("name", format!("videodec-{}", stream_id))
+        .build()?;
+
+    let tee = gst::ElementFactory::make("tee")
+        .property("name", format!("videotee-{}", stream_id))
+        .build()?;
+
+    Ok(vec![video_queue, h264parse, decoder, tee])
 }
 
-pub fn create_audio(stream_id: u32) -> Result<(gst::Element, gst::Element), BoolError> {
+/// 화질 단계마다 오디오 비트레이트가 다르므로 오디오도 한 번 디코딩해서 나눠준다.
+/// queue -> aacparse -> avdec_aac -> audioconvert -> audioresample -> tee 순서로 반환한다.
+pub fn create_audio(stream_id: u32) -> Result<Vec<gst::Element>, BoolError> {
     let audio_queue = gst::ElementFactory::make("queue")
-        .property("name", &format!("audioqueue-{}", stream_id))
+        .property("name", format!("audioqueue-{}", stream_id))
+        .build()?;
+
+    let aac_parse = gst::ElementFactory::make("aacparse")
+        .property("name", format!("aacparse-{}", stream_id))
+        .build()?;
+
+    let decoder = gst::ElementFactory::make("avdec_aac")
+        .property("name", format!("audiodec-{}", stream_id))
+        .build()?;
+
+    let audioconvert = gst::ElementFactory::make("audioconvert")
+        .property("name", format!("audioconvert-{}", stream_id))
+        .build()?;
+
+    let audioresample = gst::ElementFactory::make("audioresample")
+        .property("name", format!("audioresample-{}", stream_id))
+        .build()?;
+
+    let tee = gst::ElementFactory::make("tee")
+        .property("name", format!("audiotee-{}", stream_id))
+        .build()?;
+
+    Ok(vec![audio_queue, aac_parse, decoder, audioconvert, audioresample, tee])
+}
+
+/// 화질 단계 하나의 영상 인코딩 체인.
+/// queue -> videoscale -> videorate -> capsfilter -> x264enc -> capsfilter -> h264parse 순서로 반환한다.
+pub fn create_rendition_video(stream_id: u32, rendition: &RenditionConfig, segment_delay: u32) -> Result<Vec<gst::Element>, BoolError> {
+    let name = &rendition.name;
+
+    let queue = gst::ElementFactory::make("queue")
+        .property("name", format!("videoqueue-{}-{}", stream_id, name))
+        .build()?;
+
+    let videoscale = gst::ElementFactory::make("videoscale")
+        .property("name", format!("videoscale-{}-{}", stream_id, name))
+        .build()?;
+
+    let videorate = gst::ElementFactory::make("videorate")
+        .property("name", format!("videorate-{}-{}", stream_id, name))
+        .build()?;
+
+    let raw_caps = gst::Caps::builder("video/x-raw")
+        .field("width", rendition.width as i32)
+        .field("height", rendition.height as i32)
+        .field("framerate", gst::Fraction::new(rendition.fps as i32, 1))
+        .build();
+    let raw_capsfilter = gst::ElementFactory::make("capsfilter")
+        .property("name", format!("videocaps-{}-{}", stream_id, name))
+        .property("caps", &raw_caps)
+        .build()?;
+
+    // 키프레임 간격을 지정하지 않으면 세그먼트 경계마다 키프레임이 오도록 세그먼트 길이에 맞춘다.
+    let keyframe_interval = rendition.keyframe_interval.unwrap_or(segment_delay);
+    let encoder = gst::ElementFactory::make("x264enc")
+        .property("name", format!("x264enc-{}-{}", stream_id, name))
+        .property("bitrate", rendition.video_bitrate)
+        .property("key-int-max", rendition.fps * keyframe_interval)
+        .property_from_str("speed-preset", "veryfast")
+        .property_from_str("tune", "zerolatency")
+        .build()?;
+
+    let h264_caps = gst::Caps::builder("video/x-h264")
+        .field("profile", rendition.profile.as_str())
+        .build();
+    let h264_capsfilter = gst::ElementFactory::make("capsfilter")
+        .property("name", format!("h264caps-{}-{}", stream_id, name))
+        .property("caps", &h264_caps)
+        .build()?;
+
+    let h264parse = gst::ElementFactory::make("h264parse")
+        .property("name", format!("h264parse-{}-{}", stream_id, name))
+        .property("config-interval", -1i32)
+        .build()?;
+
+    Ok(vec![queue, videoscale, videorate, raw_capsfilter, encoder, h264_capsfilter, h264parse])
+}
+
+/// 화질 단계 하나의 오디오 인코딩 체인.
+/// queue -> avenc_aac -> aacparse 순서로 반환한다.
+pub fn create_rendition_audio(stream_id: u32, rendition: &RenditionConfig) -> Result<Vec<gst::Element>, BoolError> {
+    let name = &rendition.name;
+
+    let queue = gst::ElementFactory::make("queue")
+        .property("name", format!("audioqueue-{}-{}", stream_id, name))
+        .build()?;
+
+    // avenc_aac 의 bitrate 타입은 libav 버전에 따라 다르므로 문자열로 넘긴다.
+    let encoder = gst::ElementFactory::make("avenc_aac")
+        .property("name", format!("avenc_aac-{}-{}", stream_id, name))
+        .property_from_str("bitrate", &(rendition.audio_bitrate * 1000).to_string())
+        .build()?;
+
+    let aac_parse = gst::ElementFactory::make("aacparse")
+        .property("name", format!("aacparse-{}-{}", stream_id, name))
+        .build()?;
+
+    Ok(vec![queue, encoder, aac_parse])
+}
+
+/// DASH 는 영상과 오디오를 따로 받으므로 화질 영상 체인 끝을 tee 로 나눠 HLS 먹서와 영상만 담는 DASH 먹서에 보낸다.
+/// (tee, HLS 쪽 queue, DASH 쪽 queue) 를 반환한다. 두 queue 는 pad-added 때 각 먹서에 연결된다.
+pub fn create_dash_video_split(stream_id: u32, rendition: &RenditionConfig) -> Result<(gst::Element, gst::Element, gst::Element), BoolError> {
+    let name = &rendition.name;
+
+    let tee = gst::ElementFactory::make("tee")
+        .property("name", format!("dashtee-{}-{}", stream_id, name))
+        .build()?;
+
+    let hls_queue = gst::ElementFactory::make("queue")
+        .property("name", format!("hlsvideoqueue-{}-{}", stream_id, name))
+        .build()?;
+
+    let dash_queue = gst::ElementFactory::make("queue")
+        .property("name", format!("dashvideoqueue-{}-{}", stream_id, name))
+        .build()?;
+
+    Ok((tee, hls_queue, dash_queue))
+}
+
+/// DASH 오디오 AdaptationSet 하나의 인코딩 체인. 오디오는 화질과 관계없이 한 번만 인코딩한다.
+/// queue -> avenc_aac -> aacparse 순서로 반환한다.
+pub fn create_dash_audio(stream_id: u32, bitrate: u32) -> Result<Vec<gst::Element>, BoolError> {
+    let queue = gst::ElementFactory::make("queue")
+        .property("name", format!("dashaudioqueue-{}", stream_id))
+        .build()?;
+
+    let encoder = gst::ElementFactory::make("avenc_aac")
+        .property("name", format!("dashavenc_aac-{}", stream_id))
+        .property_from_str("bitrate", &(bitrate * 1000).to_string())
         .build()?;
 
     let aac_parse = gst::ElementFactory::make("aacparse")
-        .property("name", &format!("aacparse-{}", stream_id))
+        .property("name", format!("dashaacparse-{}", stream_id))
+        .build()?;
+
+    Ok(vec![queue, encoder, aac_parse])
+}
+
+/// DASH Representation 하나의 출력. 부분 세그먼트 없이 세그먼트 길이의 프래그먼트만 만든다.
+pub fn create_dash_output(stream_id: u32, representation: &str, segment_delay: u32) -> Result<(gst::Element, gst::Element), BoolError> {
+    let fmp4mux = gst::ElementFactory::make("isofmp4mux")
+        .property("name", format!("dashmux-{}-{}", stream_id, representation))
+        .property("fragment-duration", gst::ClockTime::from_seconds(segment_delay as u64))
         .build()?;
 
-    Ok((audio_queue, aac_parse))
+    let appsink = create_app_sink(format!("dashsink-{}-{}", stream_id, representation))?;
+
+    Ok((fmp4mux, appsink))
+}
+
+/// 라이브 윈도우에 유지할 세그먼트 개수
+pub const MAX_FILES: u32 = 5;
+
+/// part_duration(ms) 가 있으면 fMP4 프래그먼트를 LL-HLS 부분 세그먼트 길이의 chunk 로 나눈다.
+/// 세그먼트 파일과 플레이리스트는 appsink 뒤의 writer 가 저장소에 직접 기록한다.
+pub fn create_output(stream_id: u32, rendition: &RenditionConfig, output_mode: OutputMode, segment_delay: u32, part_duration: Option<u32>) -> Result<(gst::Element, gst::Element), BoolError> {
+    match output_mode {
+        OutputMode::Ts => create_ts_output(stream_id, rendition),
+        OutputMode::Fmp4 => create_fmp4_output(stream_id, rendition, segment_delay, part_duration),
+    }
 }
 
-pub fn create_output(stream_id: u32, root_playlist: &str, output_path: &str, segment_delay: u32) -> Result<(gst::Element, gst::Element), BoolError> {
+fn create_ts_output(stream_id: u32, rendition: &RenditionConfig) -> Result<(gst::Element, gst::Element), BoolError> {
+    let name = &rendition.name;
+
     let mpegtsmux = gst::ElementFactory::make("mpegtsmux")
-        .property("name", &format!("mpegtsmux-{}", stream_id))
+        .property("name", format!("mpegtsmux-{}-{}", stream_id, name))
+        .property("alignment", 7i32)
         .build()?;
 
-    let hlssink = gst::ElementFactory::make("hlssink")
-        .property("playlist-root", root_playlist)
-        .property("playlist-location", &format!("{}/playlist.m3u8", output_path))
-        .property("location", &format!("{}/segment_%05d.ts", output_path))
-        .property("target-duration", segment_delay)
-        .property("max-files", 5u32)
+    let appsink = create_app_sink(format!("tssink-{}-{}", stream_id, name))?;
+
+    Ok((mpegtsmux, appsink))
+}
+
+fn create_fmp4_output(stream_id: u32, rendition: &RenditionConfig, segment_delay: u32, part_duration: Option<u32>) -> Result<(gst::Element, gst::Element), BoolError> {
+    let name = &rendition.name;
+
+    let fmp4mux = gst::ElementFactory::make("isofmp4mux")
+        .property("name", format!("fmp4mux-{}-{}", stream_id, name))
+        .property("fragment-duration", gst::ClockTime::from_seconds(segment_delay as u64))
+        .property_if_some("chunk-duration", part_duration.map(|ms| gst::ClockTime::from_mseconds(ms as u64)))
         .build()?;
 
-    Ok((mpegtsmux, hlssink))
-}
\ No newline at end of file
+    let appsink = create_app_sink(format!("fmp4sink-{}-{}", stream_id, name))?;
+
+    Ok((fmp4mux, appsink))
+}
+
+fn create_app_sink(name: String) -> Result<gst::Element, BoolError> {
+    gst::ElementFactory::make("appsink")
+        .property("name", name)
+        .property("buffer-list", true)
+        .property("sync", false)
+        .build()
+}
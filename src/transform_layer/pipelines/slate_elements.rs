@@ -0,0 +1,86 @@
+use gstreamer_app::glib::BoolError;
+use gstreamer_app::gst;
+use crate::config::{RenditionConfig, SlateConfig};
+
+/// 원본 디코더와 슬레이트 중 하나를 골라 tee 로 보내는 input-selector.
+/// 비활성 입력은 기다리지 않고 버리도록 sync-streams 를 끈다.
+/// 원본 타임스탬프는 SlateSwitch 가 슬레이트의 running time 에 맞춰 appsrc 에 넣는다.
+pub fn create_input_selector(stream_id: u32, kind: &str) -> Result<gst::Element, BoolError> {
+    gst::ElementFactory::make("input-selector")
+        .property("name", format!("{}selector-{}", kind, stream_id))
+        .property("sync-streams", false)
+        .build()
+}
+
+/// 슬레이트 영상. 이미지가 있으면 imagefreeze 로 반복하고, 없으면 테스트 패턴을 만든다.
+/// source -> (decoder -> imagefreeze) -> videoconvert -> videoscale -> capsfilter 순서로 반환한다.
+pub fn create_slate_video(stream_id: u32, config: &SlateConfig, rendition: &RenditionConfig) -> Result<Vec<gst::Element>, BoolError> {
+    let mut chain = match &config.image {
+        Some(image) => {
+            let filesrc = gst::ElementFactory::make("filesrc")
+                .property("name", format!("slatesrc-{}", stream_id))
+                .property("location", image)
+                .build()?;
+            let decoder_name = if image.to_lowercase().ends_with(".png") { "pngdec" } else { "jpegdec" };
+            let decoder = gst::ElementFactory::make(decoder_name)
+                .property("name", format!("slatedec-{}", stream_id))
+                .build()?;
+            let imagefreeze = gst::ElementFactory::make("imagefreeze")
+                .property("name", format!("slatefreeze-{}", stream_id))
+                .property("is-live", true)
+                .build()?;
+            vec![filesrc, decoder, imagefreeze]
+        }
+        None => {
+            let videotestsrc = gst::ElementFactory::make("videotestsrc")
+                .property("name", format!("slatesrc-{}", stream_id))
+                .property("is-live", true)
+                .property_from_str("pattern", &config.pattern)
+                .build()?;
+            vec![videotestsrc]
+        }
+    };
+
+    let videoconvert = gst::ElementFactory::make("videoconvert")
+        .property("name", format!("slateconvert-{}", stream_id))
+        .build()?;
+
+    let videoscale = gst::ElementFactory::make("videoscale")
+        .property("name", format!("slatescale-{}", stream_id))
+        .build()?;
+
+    // 원본 디코더 출력과 같은 형식으로 맞춰 전환할 때 다시 협상할 일을 줄인다.
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("format", "I420")
+        .field("width", rendition.width as i32)
+        .field("height", rendition.height as i32)
+        .field("framerate", gst::Fraction::new(rendition.fps as i32, 1))
+        .build();
+    let capsfilter = gst::ElementFactory::make("capsfilter")
+        .property("name", format!("slatecaps-{}", stream_id))
+        .property("caps", &caps)
+        .build()?;
+
+    chain.extend([videoconvert, videoscale, capsfilter]);
+    Ok(chain)
+}
+
+/// 슬레이트 오디오(무음).
+/// audiotestsrc -> audioconvert -> audioresample 순서로 반환한다.
+pub fn create_slate_audio(stream_id: u32) -> Result<Vec<gst::Element>, BoolError> {
+    let audiotestsrc = gst::ElementFactory::make("audiotestsrc")
+        .property("name", format!("slateaudiosrc-{}", stream_id))
+        .property("is-live", true)
+        .property_from_str("wave", "silence")
+        .build()?;
+
+    let audioconvert = gst::ElementFactory::make("audioconvert")
+        .property("name", format!("slateaudioconvert-{}", stream_id))
+        .build()?;
+
+    let audioresample = gst::ElementFactory::make("audioresample")
+        .property("name", format!("slateaudioresample-{}", stream_id))
+        .build()?;
+
+    Ok(vec![audiotestsrc, audioconvert, audioresample])
+}
@@ -0,0 +1,122 @@
+use gstreamer_app::glib::BoolError;
+use gstreamer_app::gst;
+use crate::config::{SpriteConfig, ThumbnailConfig};
+
+/// 디코딩된 영상에서 interval 마다 한 장씩 골라 JPEG 으로 만드는 분기.
+/// 인코딩이 밀리지 않도록 queue 는 새 프레임이 오면 오래된 프레임을 버린다.
+/// queue -> videorate -> videoscale -> videoconvert -> capsfilter -> jpegenc -> appsink 순서로 반환한다.
+pub fn create_thumbnail(stream_id: u32, config: &ThumbnailConfig) -> Result<Vec<gst::Element>, BoolError> {
+    let queue = gst::ElementFactory::make("queue")
+        .property("name", format!("thumbnailqueue-{}", stream_id))
+        .property("max-size-buffers", 1u32)
+        .property_from_str("leaky", "downstream")
+        .build()?;
+
+    let videorate = gst::ElementFactory::make("videorate")
+        .property("name", format!("thumbnailrate-{}", stream_id))
+        .property("drop-only", true)
+        .build()?;
+
+    let videoscale = gst::ElementFactory::make("videoscale")
+        .property("name", format!("thumbnailscale-{}", stream_id))
+        .build()?;
+
+    let videoconvert = gst::ElementFactory::make("videoconvert")
+        .property("name", format!("thumbnailconvert-{}", stream_id))
+        .build()?;
+
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("width", config.width as i32)
+        .field("pixel-aspect-ratio", gst::Fraction::new(1, 1))
+        .field("framerate", gst::Fraction::new(1000, config.interval.max(1) as i32))
+        .build();
+    let capsfilter = gst::ElementFactory::make("capsfilter")
+        .property("name", format!("thumbnailcaps-{}", stream_id))
+        .property("caps", &caps)
+        .build()?;
+
+    let encoder = gst::ElementFactory::make("jpegenc")
+        .property("name", format!("jpegenc-{}", stream_id))
+        .property("quality", config.quality.min(100) as i32)
+        .build()?;
+
+    let appsink = gst::ElementFactory::make("appsink")
+        .property("name", format!("thumbnailsink-{}", stream_id))
+        .property("sync", false)
+        .property("max-buffers", 1u32)
+        .property("drop", true)
+        .build()?;
+
+    Ok(vec![queue, videorate, videoscale, videoconvert, capsfilter, encoder, appsink])
+}
+
+/// 스프라이트 시트에 넣을 타일을 interval 마다 한 장씩 RGBx 로 내보내는 분기.
+/// queue -> videorate -> videoscale -> videoconvert -> capsfilter -> appsink 순서로 반환한다.
+pub fn create_sprite_tiles(stream_id: u32, config: &SpriteConfig) -> Result<Vec<gst::Element>, BoolError> {
+    let queue = gst::ElementFactory::make("queue")
+        .property("name", format!("spritequeue-{}", stream_id))
+        .property("max-size-buffers", 1u32)
+        .property_from_str("leaky", "downstream")
+        .build()?;
+
+    let videorate = gst::ElementFactory::make("videorate")
+        .property("name", format!("spriterate-{}", stream_id))
+        .property("drop-only", true)
+        .build()?;
+
+    let videoscale = gst::ElementFactory::make("videoscale")
+        .property("name", format!("spritescale-{}", stream_id))
+        .property("add-borders", true)
+        .build()?;
+
+    let videoconvert = gst::ElementFactory::make("videoconvert")
+        .property("name", format!("spriteconvert-{}", stream_id))
+        .build()?;
+
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("format", "RGBx")
+        .field("width", config.width as i32)
+        .field("height", config.height as i32)
+        .field("pixel-aspect-ratio", gst::Fraction::new(1, 1))
+        .field("framerate", gst::Fraction::new(1000, config.interval.max(1) as i32))
+        .build();
+    let capsfilter = gst::ElementFactory::make("capsfilter")
+        .property("name", format!("spritecaps-{}", stream_id))
+        .property("caps", &caps)
+        .build()?;
+
+    let appsink = gst::ElementFactory::make("appsink")
+        .property("name", format!("spritetilesink-{}", stream_id))
+        .property("sync", false)
+        .build()?;
+
+    Ok(vec![queue, videorate, videoscale, videoconvert, capsfilter, appsink])
+}
+
+/// 합친 시트를 JPEG 으로 만드는 체인. 시트는 SpriteWriter 가 appsrc 로 밀어 넣는다.
+/// appsrc -> jpegenc -> appsink 순서로 반환한다.
+pub fn create_sprite_encoder(stream_id: u32, config: &SpriteConfig) -> Result<Vec<gst::Element>, BoolError> {
+    let caps = gst::Caps::builder("video/x-raw")
+        .field("format", "RGBx")
+        .field("width", (config.width * config.columns) as i32)
+        .field("height", (config.height * config.rows) as i32)
+        .field("framerate", gst::Fraction::new(0, 1))
+        .build();
+    let app_src = gst::ElementFactory::make("appsrc")
+        .property("name", format!("spritesrc-{}", stream_id))
+        .property("caps", &caps)
+        .property("format", gst::Format::Time)
+        .build()?;
+
+    let encoder = gst::ElementFactory::make("jpegenc")
+        .property("name", format!("spriteenc-{}", stream_id))
+        .property("quality", config.quality.min(100) as i32)
+        .build()?;
+
+    let appsink = gst::ElementFactory::make("appsink")
+        .property("name", format!("spritesink-{}", stream_id))
+        .property("sync", false)
+        .build()?;
+
+    Ok(vec![app_src, encoder, appsink])
+}
@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::SystemTime;
+use crate::config::RenditionConfig;
+use crate::transform_layer::playlist::live_playlist::LivePlaylist;
+use crate::utils::time::format_rfc3339;
+
+/// 오디오만 담은 DASH Representation 의 이름. 화질 이름으로는 쓸 수 없다.
+pub const AUDIO_REPRESENTATION: &str = "audio";
+
+/// 화질 영상 Representation 과 그 세그먼트 목록
+pub type VideoRepresentation = (RenditionConfig, Arc<LivePlaylist>);
+/// 오디오 비트레이트(kbps)와 세그먼트 목록
+pub type AudioRepresentation = (u32, Arc<LivePlaylist>);
+
+/// 재연결로 나뉜 구간 하나. 구간마다 Period 하나가 된다.
+#[derive(Default)]
+struct Period {
+    /// availabilityStartTime 부터 이 구간이 시작되기까지의 시간(초)
+    start: f64,
+    video: String,
+    audio: String,
+}
+
+/// DASH Representation 별 세그먼트 목록을 화질 영상과 오디오로 나눈다. 설정에 없는 화질은 건너뛴다.
+pub fn split_representations(
+    renditions: &[RenditionConfig],
+    playlists: Vec<(String, Arc<LivePlaylist>)>,
+) -> (Vec<VideoRepresentation>, Option<AudioRepresentation>) {
+    let mut video = Vec::new();
+    let mut audio = None;
+    for (name, playlist) in playlists {
+        if name == AUDIO_REPRESENTATION {
+            let bitrate = renditions.iter().map(|r| r.audio_bitrate).max().unwrap_or_default();
+            audio = Some((bitrate, playlist));
+        } else if let Some(rendition) = renditions.iter().find(|r| r.name == name) {
+            video.push((rendition.clone(), playlist));
+        }
+    }
+    (video, audio)
+}
+
+/// 영상과 오디오를 따로 담은 fMP4 세그먼트를 가리키는 MPEG-DASH 매니페스트를 만든다.
+/// 영상 AdaptationSet 에는 화질마다 Representation 하나를, 오디오 AdaptationSet 에는 오디오 Representation 하나를 둔다.
+/// 재연결되면 타임스탬프가 다시 0 부터 시작하므로 새 Period 를 연다. 실제 세그먼트 길이는 SegmentTimeline 으로 알려준다.
+/// 라이브 중에는 재연결 공백만큼 벽시계가 미디어 시간보다 앞서므로 Period 시작을 세그먼트가 만들어진 시각으로 정한다.
+/// 방송이 끝나 영상 목록이 모두 닫혔으면 녹화된 세그먼트 전체로 static 매니페스트를 만든다.
+pub fn render_manifest(
+    started_at: SystemTime,
+    segment_delay: u32,
+    video: &[VideoRepresentation],
+    audio: Option<AudioRepresentation>,
+) -> String {
+    let ended = !video.is_empty() && video.iter().all(|(_, playlist)| playlist.read(|p| p.is_ended()));
+    let mut periods: BTreeMap<u64, Period> = BTreeMap::new();
+    let mut buffer_depth: f64 = 0.0;
+    let mut duration: f64 = 0.0;
+
+    for (rendition, live_playlist) in video {
+        for (period, start, template) in timelines(live_playlist, started_at, ended, &mut buffer_depth, &mut duration) {
+            let period = periods.entry(period).or_insert_with(|| Period { start, ..Period::default() });
+            period.video.push_str(&format!(
+                "      <Representation id=\"{}\" bandwidth=\"{}\" width=\"{}\" height=\"{}\" frameRate=\"{}\" codecs=\"{}\">\n{}\
+                 \x20     </Representation>\n",
+                rendition.name,
+                rendition.video_bitrate * 1000,
+                rendition.width,
+                rendition.height,
+                rendition.fps,
+                rendition.video_codec(),
+                template
+            ));
+        }
+    }
+    if let Some((bitrate, live_playlist)) = audio {
+        for (period, start, template) in timelines(&live_playlist, started_at, ended, &mut buffer_depth, &mut duration) {
+            let period = periods.entry(period).or_insert_with(|| Period { start, ..Period::default() });
+            period.audio.push_str(&format!(
+                "      <Representation id=\"{}\" bandwidth=\"{}\" codecs=\"mp4a.40.2\">\n{}\
+                 \x20     </Representation>\n",
+                AUDIO_REPRESENTATION,
+                bitrate * 1000,
+                template
+            ));
+        }
+    }
+
+    let periods: String = periods.into_iter()
+        .map(|(id, period)| {
+            let mut adaptation_sets = String::new();
+            if !period.video.is_empty() {
+                adaptation_sets.push_str(&format!(
+                    "    <AdaptationSet id=\"0\" contentType=\"video\" mimeType=\"video/mp4\" segmentAlignment=\"true\" startWithSAP=\"1\">\n\
+                     {}\
+                     \x20   </AdaptationSet>\n",
+                    period.video
+                ));
+            }
+            if !period.audio.is_empty() {
+                adaptation_sets.push_str(&format!(
+                    "    <AdaptationSet id=\"1\" contentType=\"audio\" mimeType=\"audio/mp4\" segmentAlignment=\"true\" startWithSAP=\"1\">\n\
+                     {}\
+                     \x20   </AdaptationSet>\n",
+                    period.audio
+                ));
+            }
+            format!("  <Period id=\"{}\" start=\"PT{:.3}S\">\n{}  </Period>\n", id, period.start, adaptation_sets)
+        })
+        .collect();
+
+    let attributes = if ended {
+        format!("type=\"static\" mediaPresentationDuration=\"PT{:.3}S\" minBufferTime=\"PT{}S\"", duration, segment_delay)
+    } else {
+        format!(
+            "type=\"dynamic\" availabilityStartTime=\"{}\" publishTime=\"{}\" minimumUpdatePeriod=\"PT{}S\" minBufferTime=\"PT{}S\" \
+             timeShiftBufferDepth=\"PT{:.3}S\" suggestedPresentationDelay=\"PT{}S\"",
+            format_rfc3339(started_at),
+            format_rfc3339(SystemTime::now()),
+            segment_delay,
+            segment_delay,
+            buffer_depth,
+            segment_delay * 3
+        )
+    };
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <MPD xmlns=\"urn:mpeg:dash:schema:mpd:2011\" profiles=\"urn:mpeg:dash:profile:isoff-live:2011\" {}>\n\
+         {}\
+         </MPD>\n",
+        attributes,
+        periods
+    )
+}
+
+/// 라이브 윈도우의 세그먼트를 구간별 SegmentTemplate 으로 만든다. (구간 번호, 구간 시작, SegmentTemplate) 을 돌려준다.
+/// 끝난 방송이고 녹화했다면 녹화된 세그먼트 전체를 쓴다. t 는 구간 시작부터의 시간이다.
+/// 구간 시작은 라이브 중에는 벽시계로, 끝난 뒤에는 공백 없이 이어지도록 미디어 시간으로 정한다.
+/// duration 은 담은 세그먼트가 끝나는 시간(초)까지 늘린다.
+fn timelines(
+    live_playlist: &LivePlaylist,
+    started_at: SystemTime,
+    ended: bool,
+    buffer_depth: &mut f64,
+    duration: &mut f64,
+) -> Vec<(u64, f64, String)> {
+    live_playlist.read(|playlist| {
+        let window: f64 = playlist.segments().map(|s| s.duration).sum();
+        *buffer_depth = buffer_depth.max(window);
+        let segments = match playlist.archived_segments() {
+            Some(archived) if ended => archived,
+            _ => playlist.segments().collect(),
+        };
+
+        let mut timelines: Vec<(u64, f64, u64, String)> = Vec::new();
+        // 마지막으로 담은 세그먼트가 끝나는 시간. 다음 구간은 이보다 앞서 시작하지 않는다.
+        let mut end: f64 = 0.0;
+        for segment in segments {
+            let offset = segment.start - segment.period_start;
+            let entry = format!(
+                "            <S t=\"{}\" d=\"{}\"/>\n",
+                (offset * 1000.0).round() as u64,
+                (segment.duration * 1000.0).round() as u64
+            );
+            let start = match timelines.last_mut() {
+                Some((period, start, _, entries)) if *period == segment.period => {
+                    entries.push_str(&entry);
+                    *start
+                }
+                _ => {
+                    let start = if ended {
+                        segment.period_start
+                    } else {
+                        let produced = segment.program_date_time.duration_since(started_at).unwrap_or_default();
+                        (produced.as_secs_f64() - offset).max(end)
+                    };
+                    let start_number = segment_number(&segment.uri).unwrap_or(segment.sequence);
+                    timelines.push((segment.period, start, start_number, entry));
+                    start
+                }
+            };
+            end = start + offset + segment.duration;
+        }
+        *duration = duration.max(end);
+        timelines.into_iter()
+            .map(|(period, start, start_number, entries)| (period, start, format!(
+                "        <SegmentTemplate timescale=\"1000\" initialization=\"$RepresentationID$/init.mp4\" media=\"$RepresentationID$/segment_$Number%05d$.m4s\" startNumber=\"{}\">\n\
+                 \x20         <SegmentTimeline>\n\
+                 {}\
+                 \x20         </SegmentTimeline>\n\
+                 \x20       </SegmentTemplate>\n",
+                start_number,
+                entries
+            )))
+            .collect()
+    })
+}
+
+/// segment_00012.m4s 처럼 파일 이름에 붙은 번호. 저장에 실패한 세그먼트가 있으면 플레이리스트 번호와 다를 수 있다.
+fn segment_number(uri: &str) -> Option<u64> {
+    uri.strip_prefix("segment_")?.split('.').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::transform_layer::playlist::media_playlist::MediaPlaylist;
+
+    fn rendition() -> RenditionConfig {
+        RenditionConfig {
+            name: "720p".to_string(),
+            width: 1280,
+            height: 720,
+            video_bitrate: 2800,
+            audio_bitrate: 128,
+            fps: 30,
+            profile: "main".to_string(),
+            keyframe_interval: None,
+        }
+    }
+
+    fn at(secs: f64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs_f64(secs)
+    }
+
+    /// 세그먼트가 UNIX_EPOCH 부터 쉬지 않고 만들어진 플레이리스트
+    fn playlist(durations: &[f64]) -> Arc<LivePlaylist> {
+        let live_playlist = Arc::new(LivePlaylist::new(MediaPlaylist::new(2, 5)));
+        let mut produced = 0.0;
+        for (i, duration) in durations.iter().enumerate() {
+            produced += duration;
+            live_playlist.update(|p| p.push_segment_at(format!("segment_{:05}.m4s", i), *duration, at(produced)));
+        }
+        live_playlist
+    }
+
+    #[test]
+    fn renders_segment_timeline() {
+        let video = [(rendition(), playlist(&[2.0, 1.5]))];
+        let manifest = render_manifest(SystemTime::UNIX_EPOCH, 2, &video, Some((128, playlist(&[2.0, 1.5]))));
+        assert!(manifest.contains("type=\"dynamic\" availabilityStartTime=\"1970-01-01T00:00:00.000Z\""));
+        assert!(manifest.contains("timeShiftBufferDepth=\"PT3.500S\""));
+        assert_eq!(manifest.matches("<Period ").count(), 1);
+        assert!(manifest.contains(
+            "        <SegmentTemplate timescale=\"1000\" initialization=\"$RepresentationID$/init.mp4\" \
+             media=\"$RepresentationID$/segment_$Number%05d$.m4s\" startNumber=\"0\">\n\
+             \x20         <SegmentTimeline>\n\
+             \x20           <S t=\"0\" d=\"2000\"/>\n\
+             \x20           <S t=\"2000\" d=\"1500\"/>\n\
+             \x20         </SegmentTimeline>\n"
+        ));
+        assert!(manifest.contains("<Representation id=\"720p\" bandwidth=\"2800000\" width=\"1280\" height=\"720\""));
+        assert!(manifest.contains("<Representation id=\"audio\" bandwidth=\"128000\" codecs=\"mp4a.40.2\">"));
+    }
+
+    #[test]
+    fn opens_period_per_reconnect() {
+        let live_playlist = playlist(&[2.0, 2.0]);
+        live_playlist.update(|p| p.mark_discontinuity());
+        live_playlist.update(|p| p.push_segment_at("segment_00002.m4s".to_string(), 2.0, at(6.0)));
+        let manifest = render_manifest(SystemTime::UNIX_EPOCH, 2, &[(rendition(), live_playlist)], None);
+
+        let first = manifest.find("<Period id=\"0\" start=\"PT0.000S\">").unwrap();
+        let second = manifest.find("<Period id=\"1\" start=\"PT4.000S\">").unwrap();
+        assert!(first < second);
+        // 새 Period 의 타임라인은 구간 시작부터 다시 센다.
+        assert!(manifest[second..].contains("startNumber=\"2\">\n"));
+        assert!(manifest[second..].contains("<S t=\"0\" d=\"2000\"/>"));
+        assert!(!manifest.contains("contentType=\"audio\""));
+    }
+
+    #[test]
+    fn starts_period_at_wall_clock_after_gap() {
+        let live_playlist = playlist(&[2.0, 2.0]);
+        live_playlist.update(|p| p.mark_discontinuity());
+        // 10초 동안 끊겼다가 다시 송출했다.
+        live_playlist.update(|p| p.push_segment_at("segment_00002.m4s".to_string(), 2.0, at(16.0)));
+        let manifest = render_manifest(SystemTime::UNIX_EPOCH, 2, &[(rendition(), live_playlist.clone())], None);
+        assert!(manifest.contains("<Period id=\"1\" start=\"PT14.000S\">"));
+
+        // 끝난 뒤의 static 매니페스트는 공백 없이 잇는다.
+        live_playlist.update(|p| p.end());
+        let manifest = render_manifest(SystemTime::UNIX_EPOCH, 2, &[(rendition(), live_playlist)], None);
+        assert!(manifest.contains("<Period id=\"1\" start=\"PT4.000S\">"));
+        assert!(manifest.contains("mediaPresentationDuration=\"PT6.000S\""));
+    }
+
+    #[test]
+    fn renders_static_manifest_after_end() {
+        let live_playlist = playlist(&[2.0, 1.5]);
+        live_playlist.update(|p| p.end());
+        let manifest = render_manifest(SystemTime::UNIX_EPOCH, 2, &[(rendition(), live_playlist)], None);
+        assert!(manifest.contains("type=\"static\" mediaPresentationDuration=\"PT3.500S\" minBufferTime=\"PT2S\">"));
+        assert!(!manifest.contains("minimumUpdatePeriod"));
+    }
+
+    #[test]
+    fn renders_recorded_segments_after_end() {
+        let live_playlist = Arc::new(LivePlaylist::new(MediaPlaylist::new(2, 2).with_archive()));
+        for i in 0..4 {
+            live_playlist.update(|p| p.push_segment(format!("segment_{:05}.m4s", i), 2.0));
+        }
+        live_playlist.update(|p| p.end());
+        let manifest = render_manifest(SystemTime::UNIX_EPOCH, 2, &[(rendition(), live_playlist)], None);
+        assert!(manifest.contains("mediaPresentationDuration=\"PT8.000S\""));
+        assert!(manifest.contains("startNumber=\"0\">\n"));
+        assert_eq!(manifest.matches("<S ").count(), 4);
+    }
+}
@@ -0,0 +1,141 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use crate::transform_layer::playlist::media_playlist::MediaPlaylist;
+
+/// 파이프라인이 갱신하고 HTTP 서버가 읽는 메모리 상의 플레이리스트.
+/// 갱신될 때마다 watch 채널로 대기 중인 blocking playlist reload 요청을 깨운다.
+pub struct LivePlaylist {
+    playlist: Mutex<MediaPlaylist>,
+    updates: watch::Sender<u64>,
+    /// 다음 세그먼트 파일 번호. 플레이리스트는 저장이 끝난 뒤에 갱신되므로 파일 이름은 따로 센다.
+    /// 재연결된 파이프라인도 이어서 쓰도록 플레이리스트와 함께 둔다.
+    file_sequence: AtomicU64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BlockingReloadError {
+    /// 요청한 _HLS_msn 이 너무 먼 미래이다.
+    TooFarAhead,
+    /// 기다리는 동안 해당 세그먼트가 만들어지지 않았다.
+    Timeout,
+}
+
+impl LivePlaylist {
+    pub fn new(playlist: MediaPlaylist) -> Self {
+        Self {
+            playlist: Mutex::new(playlist),
+            updates: watch::Sender::new(0),
+            file_sequence: AtomicU64::new(0),
+        }
+    }
+
+    pub fn file_sequence(&self) -> u64 {
+        self.file_sequence.load(Ordering::SeqCst)
+    }
+
+    pub fn advance_file_sequence(&self) {
+        self.file_sequence.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// 플레이리스트를 수정하고 대기 중인 요청들에게 알린다.
+    pub fn update<R>(&self, f: impl FnOnce(&mut MediaPlaylist) -> R) -> R {
+        let result = f(&mut self.playlist.lock().unwrap());
+        self.updates.send_modify(|version| *version += 1);
+        result
+    }
+
+    pub fn read<R>(&self, f: impl FnOnce(&MediaPlaylist) -> R) -> R {
+        f(&self.playlist.lock().unwrap())
+    }
+
+    pub fn render(&self) -> String {
+        self.playlist.lock().unwrap().render()
+    }
+
+    /// msn 번 세그먼트(part 가 있으면 그 부분 세그먼트)가 플레이리스트에 올라왔는지 확인한다.
+    fn contains(&self, msn: u64, part: Option<u64>) -> Result<bool, BlockingReloadError> {
+        let playlist = self.playlist.lock().unwrap();
+        let next = playlist.next_sequence();
+        if playlist.is_ended() || msn < next {
+            return Ok(true);
+        }
+        // LL-HLS 명세: 마지막 세그먼트보다 2개 넘게 앞선 요청은 400 으로 거절한다.
+        // next 는 아직 올라오지 않은 세그먼트이므로 마지막 세그먼트는 next - 1 이다.
+        if msn > next + 1 {
+            return Err(BlockingReloadError::TooFarAhead);
+        }
+        Ok(msn == next && part.is_some_and(|part| part < playlist.pending_parts() as u64))
+    }
+
+    /// _HLS_msn / _HLS_part 가 가리키는 위치가 준비될 때까지 기다린다.
+    pub async fn wait_for(&self, msn: u64, part: Option<u64>, timeout: Duration) -> Result<(), BlockingReloadError> {
+        let mut updates = self.updates.subscribe();
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if self.contains(msn, part)? {
+                return Ok(());
+            }
+            match tokio::time::timeout_at(deadline, updates.changed()).await {
+                Ok(Ok(())) => continue,
+                _ => return Err(BlockingReloadError::Timeout),
+            }
+        }
+    }
+
+    pub fn is_preload_hint(&self, uri: &str) -> bool {
+        self.read(|p| p.preload_hint() == Some(uri))
+    }
+
+    /// 아직 쓰이지 않은 부분 세그먼트(preload hint)를 요청받았을 때 갱신을 기다린다.
+    pub async fn wait_for_update(&self, timeout: Duration) -> bool {
+        let mut updates = self.updates.subscribe();
+        matches!(tokio::time::timeout(timeout, updates.changed()).await, Ok(Ok(())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn live_playlist(segments: u64) -> LivePlaylist {
+        let live_playlist = LivePlaylist::new(MediaPlaylist::new(2, 5).with_low_latency(0.5));
+        for i in 0..segments {
+            live_playlist.update(|p| p.push_segment(format!("segment_{:05}.m4s", i), 1.0));
+        }
+        live_playlist
+    }
+
+    #[test]
+    fn renders_current_playlist() {
+        let live_playlist = live_playlist(1);
+        live_playlist.update(|p| p.set_preload_hint(Some("part_00001.0.m4s".to_string())));
+        let rendered = live_playlist.render();
+        assert!(rendered.ends_with("#EXTINF:1.000,\nsegment_00000.m4s\n#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"part_00001.0.m4s\"\n"));
+        assert!(live_playlist.is_preload_hint("part_00001.0.m4s"));
+        assert!(!live_playlist.is_preload_hint("part_00000.0.m4s"));
+    }
+
+    #[tokio::test]
+    async fn rejects_msn_too_far_ahead() {
+        let live_playlist = live_playlist(2);
+        let timeout = Duration::from_millis(10);
+        assert_eq!(live_playlist.wait_for(1, None, timeout).await, Ok(()));
+        assert_eq!(live_playlist.wait_for(3, None, timeout).await, Err(BlockingReloadError::Timeout));
+        assert_eq!(live_playlist.wait_for(4, None, timeout).await, Err(BlockingReloadError::TooFarAhead));
+    }
+
+    #[tokio::test]
+    async fn wakes_when_part_is_pushed() {
+        let live_playlist = Arc::new(live_playlist(1));
+        let waiter = {
+            let live_playlist = live_playlist.clone();
+            tokio::spawn(async move { live_playlist.wait_for(1, Some(0), Duration::from_secs(5)).await })
+        };
+        tokio::task::yield_now().await;
+        live_playlist.update(|p| p.push_part("part_00001.0.m4s".to_string(), 0.5, true));
+        assert_eq!(waiter.await.unwrap(), Ok(()));
+    }
+}
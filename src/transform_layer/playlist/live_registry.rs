@@ -0,0 +1,239 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use bytes::Bytes;
+use crate::transform_layer::playlist::live_playlist::LivePlaylist;
+use crate::transform_layer::playlist::sprite_track::SpriteTrack;
+
+/// 현재 송출 중인 채널들의 출력 위치와 메모리 플레이리스트를 HTTP 서버와 공유한다.
+/// 채널 키는 인증된 경로("닉네임/시작시각")의 닉네임 부분이다.
+#[derive(Default)]
+pub struct LiveRegistry {
+    channels: Mutex<HashMap<String, LiveChannel>>,
+    /// 채널별 마지막 출력 위치. 방송이 끝난 뒤에도 남아 끝난 플레이리스트와 미리보기를 찾는 데 쓴다.
+    last_outputs: Mutex<LastOutputs>,
+}
+
+/// 기억할 끝난 채널 수. 넘치면 가장 오래전에 송출한 채널부터 잊는다.
+const MAX_LAST_OUTPUTS: usize = 10_000;
+
+#[derive(Default)]
+struct LastOutputs {
+    keys: HashMap<String, String>,
+    /// 오래전에 송출한 채널부터
+    order: VecDeque<String>,
+}
+
+impl LastOutputs {
+    fn insert(&mut self, channel: &str, output_key: String) {
+        if self.keys.insert(channel.to_string(), output_key).is_some() {
+            self.order.retain(|c| c != channel);
+        }
+        self.order.push_back(channel.to_string());
+        while self.order.len() > MAX_LAST_OUTPUTS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+    }
+
+    fn remove(&mut self, output_key: &str) {
+        self.keys.retain(|_, key| key != output_key);
+        let keys = &self.keys;
+        self.order.retain(|c| keys.contains_key(c));
+    }
+}
+
+struct LiveChannel {
+    /// 저장소 key 접두사 ("닉네임/시작시각")
+    output_key: String,
+    started_at: SystemTime,
+    playlists: HashMap<String, Arc<LivePlaylist>>,
+    /// [dash] 가 켜져 있으면 DASH Representation 별 세그먼트 목록 (화질 이름과 "audio")
+    dash_playlists: HashMap<String, Arc<LivePlaylist>>,
+    /// 가장 최근에 만든 미리보기 JPEG
+    thumbnail: Option<Bytes>,
+    /// [hls.sprites] 가 켜져 있으면 탐색 미리보기 트랙
+    sprite_track: Option<Arc<Mutex<SpriteTrack>>>,
+    /// PlaylistReady 를 이미 알렸다.
+    playlist_ready: bool,
+}
+
+impl LiveRegistry {
+    pub fn register_channel(&self, channel: &str, output_key: String) {
+        self.last_outputs.lock().unwrap().insert(channel, output_key.clone());
+        self.channels.lock().unwrap().insert(channel.to_string(), LiveChannel {
+            output_key,
+            started_at: SystemTime::now(),
+            playlists: HashMap::new(),
+            dash_playlists: HashMap::new(),
+            thumbnail: None,
+            sprite_track: None,
+            playlist_ready: false,
+        });
+    }
+
+    pub fn register_playlist(&self, channel: &str, rendition: &str, playlist: Arc<LivePlaylist>) {
+        if let Some(live_channel) = self.channels.lock().unwrap().get_mut(channel) {
+            live_channel.playlists.insert(rendition.to_string(), playlist);
+        }
+    }
+
+    pub fn register_dash_playlist(&self, channel: &str, representation: &str, playlist: Arc<LivePlaylist>) {
+        if let Some(live_channel) = self.channels.lock().unwrap().get_mut(channel) {
+            live_channel.dash_playlists.insert(representation.to_string(), playlist);
+        }
+    }
+
+    pub fn dash_playlist(&self, channel: &str, representation: &str) -> Option<Arc<LivePlaylist>> {
+        self.channels.lock().unwrap()
+            .get(channel)
+            .and_then(|c| c.dash_playlists.get(representation).cloned())
+    }
+
+    /// 채널의 모든 DASH Representation 별 세그먼트 목록 (이름 순)
+    pub fn dash_playlists(&self, channel: &str) -> Vec<(String, Arc<LivePlaylist>)> {
+        let channels = self.channels.lock().unwrap();
+        let mut playlists: Vec<_> = channels
+            .get(channel)
+            .map(|c| c.dash_playlists.iter().map(|(name, p)| (name.clone(), p.clone())).collect())
+            .unwrap_or_default();
+        playlists.sort_by(|a, b| a.0.cmp(&b.0));
+        playlists
+    }
+
+    pub fn register_sprite_track(&self, channel: &str, track: Arc<Mutex<SpriteTrack>>) {
+        if let Some(live_channel) = self.channels.lock().unwrap().get_mut(channel) {
+            live_channel.sprite_track = Some(track);
+        }
+    }
+
+    pub fn sprite_track(&self, channel: &str) -> Option<Arc<Mutex<SpriteTrack>>> {
+        self.channels.lock().unwrap()
+            .get(channel)
+            .and_then(|c| c.sprite_track.clone())
+    }
+
+    pub fn remove_channel(&self, channel: &str) {
+        self.channels.lock().unwrap().remove(channel);
+    }
+
+    /// 같은 채널에 다른 송출이 이미 등록되었다면 그대로 둔다.
+    pub fn remove_output(&self, channel: &str, output_key: &str) {
+        let mut channels = self.channels.lock().unwrap();
+        if channels.get(channel).is_some_and(|c| c.output_key == output_key) {
+            channels.remove(channel);
+        }
+    }
+
+    pub fn output_key(&self, channel: &str) -> Option<String> {
+        self.channels.lock().unwrap()
+            .get(channel)
+            .map(|c| c.output_key.clone())
+    }
+
+    /// 송출 중이면 현재 출력 위치를, 끝났으면 이 채널의 마지막 출력 위치를 돌려준다.
+    pub fn last_output_key(&self, channel: &str) -> Option<String> {
+        self.output_key(channel)
+            .or_else(|| self.last_outputs.lock().unwrap().keys.get(channel).cloned())
+    }
+
+    /// 지워진 출력 위치를 더 이상 끝난 방송으로 내주지 않는다.
+    pub fn forget_output(&self, output_key: &str) {
+        self.last_outputs.lock().unwrap().remove(output_key);
+    }
+
+    /// 송출 중이거나 재연결을 기다리는 모든 채널의 출력 위치
+    pub fn output_keys(&self) -> HashSet<String> {
+        self.channels.lock().unwrap()
+            .values()
+            .map(|c| c.output_key.clone())
+            .collect()
+    }
+
+    pub fn playlist(&self, channel: &str, rendition: &str) -> Option<Arc<LivePlaylist>> {
+        self.channels.lock().unwrap()
+            .get(channel)
+            .and_then(|c| c.playlists.get(rendition).cloned())
+    }
+
+    /// 이미 내려간 파이프라인이 새 송출의 미리보기를 덮어쓰지 않도록 출력 위치가 같을 때만 바꾼다.
+    pub fn set_thumbnail(&self, channel: &str, output_key: &str, image: Bytes) {
+        if let Some(live_channel) = self.channels.lock().unwrap().get_mut(channel)
+            && live_channel.output_key == output_key
+        {
+            live_channel.thumbnail = Some(image);
+        }
+    }
+
+    /// 이 송출에서 처음 호출했을 때만 true 를 돌려준다. 출력 위치가 다르면 false 이다.
+    pub fn mark_playlist_ready(&self, channel: &str, output_key: &str) -> bool {
+        match self.channels.lock().unwrap().get_mut(channel) {
+            Some(live_channel) if live_channel.output_key == output_key => !std::mem::replace(&mut live_channel.playlist_ready, true),
+            _ => false,
+        }
+    }
+
+    pub fn thumbnail(&self, channel: &str) -> Option<Bytes> {
+        self.channels.lock().unwrap()
+            .get(channel)
+            .and_then(|c| c.thumbnail.clone())
+    }
+
+    pub fn started_at(&self, channel: &str) -> Option<SystemTime> {
+        self.channels.lock().unwrap()
+            .get(channel)
+            .map(|c| c.started_at)
+    }
+
+    /// 채널의 모든 화질별 플레이리스트 (화질 이름 순)
+    pub fn playlists(&self, channel: &str) -> Vec<(String, Arc<LivePlaylist>)> {
+        let channels = self.channels.lock().unwrap();
+        let mut playlists: Vec<_> = channels
+            .get(channel)
+            .map(|c| c.playlists.iter().map(|(name, p)| (name.clone(), p.clone())).collect())
+            .unwrap_or_default();
+        playlists.sort_by(|a, b| a.0.cmp(&b.0));
+        playlists
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forgets_removed_output() {
+        let live_registry = LiveRegistry::default();
+        live_registry.register_channel("pang", "pang/1".to_string());
+        live_registry.remove_channel("pang");
+        assert_eq!(live_registry.last_output_key("pang").as_deref(), Some("pang/1"));
+        live_registry.forget_output("pang/1");
+        assert_eq!(live_registry.last_output_key("pang"), None);
+    }
+
+    #[test]
+    fn marks_playlist_ready_once_per_output() {
+        let live_registry = LiveRegistry::default();
+        live_registry.register_channel("pang", "pang/1".to_string());
+        assert!(live_registry.mark_playlist_ready("pang", "pang/1"));
+        assert!(!live_registry.mark_playlist_ready("pang", "pang/1"));
+        live_registry.register_channel("pang", "pang/2".to_string());
+        assert!(!live_registry.mark_playlist_ready("pang", "pang/1"));
+        assert!(live_registry.mark_playlist_ready("pang", "pang/2"));
+    }
+
+    #[test]
+    fn bounds_last_outputs() {
+        let mut last_outputs = LastOutputs::default();
+        for i in 0..MAX_LAST_OUTPUTS + 2 {
+            last_outputs.insert(&format!("channel{}", i), format!("channel{}/1", i));
+        }
+        last_outputs.insert("channel2", "channel2/2".to_string());
+        last_outputs.insert(&format!("channel{}", MAX_LAST_OUTPUTS + 2), "new/1".to_string());
+        assert_eq!(last_outputs.keys.len(), MAX_LAST_OUTPUTS);
+        assert!(!last_outputs.keys.contains_key("channel1"));
+        assert!(!last_outputs.keys.contains_key("channel3"));
+        assert_eq!(last_outputs.keys.get("channel2").map(String::as_str), Some("channel2/2"));
+    }
+}
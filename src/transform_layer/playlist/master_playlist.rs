@@ -0,0 +1,33 @@
+use crate::config::RenditionConfig;
+
+/// 화질별 미디어 플레이리스트("화질/playlist_file")를 묶는 마스터 플레이리스트.
+/// thumbnails 가 있으면 탐색 미리보기 WebVTT 의 위치를 EXT-X-SESSION-DATA 로 알린다.
+pub fn render_master_playlist<'a>(
+    renditions: impl IntoIterator<Item = &'a RenditionConfig>,
+    playlist_file: &str,
+    thumbnails: Option<&str>,
+) -> String {
+    let mut master_playlist = String::from("#EXTM3U\n#EXT-X-VERSION:3\n");
+    for rendition in renditions {
+        master_playlist.push_str(&format!(
+            "#EXT-X-STREAM-INF:BANDWIDTH={},RESOLUTION={}x{},FRAME-RATE={},CODECS=\"{}\"\n\
+             {}/{}\n",
+            rendition.bandwidth(),
+            rendition.width,
+            rendition.height,
+            rendition.fps,
+            rendition.codecs(),
+            rendition.name,
+            playlist_file
+        ));
+    }
+    if let Some(thumbnails) = thumbnails {
+        master_playlist.push_str(&format!(
+            "#EXT-X-SESSION-DATA:DATA-ID=\"{}\",VALUE=\"{}\"\n",
+            THUMBNAILS_DATA_ID, thumbnails
+        ));
+    }
+    master_playlist
+}
+
+pub const THUMBNAILS_DATA_ID: &str = "com.pang-streaming.thumbnails";
@@ -0,0 +1,485 @@
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime};
+use crate::utils::time::format_rfc3339;
+
+/// LL-HLS 부분 세그먼트 (EXT-X-PART)
+#[derive(Debug, Clone)]
+pub struct Part {
+    pub uri: String,
+    pub duration: f64,
+    pub independent: bool,
+}
+
+/// 미디어 플레이리스트에 올라가는 세그먼트 하나
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub sequence: u64,
+    pub uri: String,
+    /// 스트림 시작부터 이 세그먼트가 시작되기까지의 시간(초)
+    pub start: f64,
+    pub duration: f64,
+    pub parts: Vec<Part>,
+    /// 송출자가 다시 연결되어 이 세그먼트부터 타임스탬프와 인코딩이 이어지지 않는다.
+    pub discontinuity: bool,
+    /// 재연결로 나뉜 구간 번호. 첫 파이프라인이 0 이다.
+    pub period: u64,
+    /// 이 구간이 시작된 시간(초). 구간 안의 미디어 타임스탬프는 여기서부터 0 으로 다시 센다.
+    pub period_start: f64,
+    /// 이 세그먼트가 시작된 벽시계 시각 (EXT-X-PROGRAM-DATE-TIME)
+    pub program_date_time: SystemTime,
+}
+
+/// 라이브 윈도우 밖으로 밀려났지만 되감기 시간 안에 있어 남겨 둔 세그먼트들
+struct Dvr {
+    /// 되감을 수 있는 시간(초)
+    window: f64,
+    segments: VecDeque<Segment>,
+    /// DVR 윈도우 밖으로 밀려난 EXT-X-DISCONTINUITY 개수
+    discontinuity_sequence: u64,
+}
+
+/// hlssink 가 디스크에 쓰는 것과 같은 형태의 미디어 플레이리스트를 메모리에서 관리한다.
+pub struct MediaPlaylist {
+    /// 플레이리스트를 다시 읽는 사이에 바뀌면 안 되므로 더 긴 세그먼트가 들어올 때만 늘리고 줄이지 않는다.
+    target_duration: u32,
+    window: usize,
+    next_sequence: u64,
+    /// 윈도우 밖으로 밀려난 EXT-X-DISCONTINUITY 개수
+    discontinuity_sequence: u64,
+    /// 다음에 추가되는 세그먼트 앞에 EXT-X-DISCONTINUITY 를 붙인다.
+    pending_discontinuity: bool,
+    period: u64,
+    period_start: f64,
+    total_duration: f64,
+    init_uri: Option<String>,
+    segments: VecDeque<Segment>,
+    part_target: Option<f64>,
+    pending_parts: Vec<Part>,
+    preload_hint: Option<String>,
+    ended: bool,
+    /// 녹화 중이면 윈도우 밖으로 밀려난 세그먼트를 VOD 플레이리스트용으로 남긴다.
+    archive: Option<Vec<Segment>>,
+    dvr: Option<Dvr>,
+}
+
+/// 부분 세그먼트를 노출할 최근 세그먼트 개수
+const PART_SEGMENTS: usize = 3;
+
+impl MediaPlaylist {
+    /// window 가 0 이면 세그먼트를 지우지 않는다.
+    pub fn new(target_duration: u32, window: usize) -> Self {
+        Self {
+            target_duration,
+            window,
+            next_sequence: 0,
+            discontinuity_sequence: 0,
+            pending_discontinuity: false,
+            period: 0,
+            period_start: 0.0,
+            total_duration: 0.0,
+            init_uri: None,
+            segments: VecDeque::new(),
+            part_target: None,
+            pending_parts: Vec::new(),
+            preload_hint: None,
+            ended: false,
+            archive: None,
+            dvr: None,
+        }
+    }
+
+    /// 라이브 윈도우 밖으로 밀려난 세그먼트를 window(초) 동안 남기고 render_dvr 로 보여준다.
+    pub fn with_dvr(mut self, window: f64) -> Self {
+        self.dvr = Some(Dvr { window, segments: VecDeque::new(), discontinuity_sequence: 0 });
+        self
+    }
+
+    /// 밀려난 세그먼트 파일을 지우지 않고 render_vod 로 전체 녹화본을 만들 수 있게 한다.
+    pub fn with_archive(mut self) -> Self {
+        self.archive = Some(Vec::new());
+        self
+    }
+
+    /// 부분 세그먼트 길이(초)를 지정하면 LL-HLS 태그를 함께 출력한다.
+    pub fn with_low_latency(mut self, part_target: f64) -> Self {
+        self.part_target = Some(part_target);
+        self
+    }
+
+    pub fn set_init_uri(&mut self, uri: String) {
+        self.init_uri = Some(uri);
+    }
+
+    /// 지금까지 추가된 세그먼트 길이의 합(초)
+    pub fn total_duration(&self) -> f64 {
+        self.total_duration
+    }
+
+    pub fn next_sequence(&self) -> u64 {
+        self.next_sequence
+    }
+
+    /// 진행 중인 세그먼트에 이미 올라간 부분 세그먼트 개수
+    pub fn pending_parts(&self) -> usize {
+        self.pending_parts.len()
+    }
+
+    pub fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = &Segment> {
+        self.segments.iter()
+    }
+
+    pub fn push_part(&mut self, uri: String, duration: f64, independent: bool) {
+        self.pending_parts.push(Part { uri, duration, independent });
+    }
+
+    pub fn set_preload_hint(&mut self, uri: Option<String>) {
+        self.preload_hint = uri;
+    }
+
+    /// EXT-X-PRELOAD-HINT 로 알려준 다음 부분 세그먼트
+    pub fn preload_hint(&self) -> Option<&str> {
+        self.preload_hint.as_deref()
+    }
+
+    /// 재연결된 송출을 이어 쓰기 전에 호출한다. 끊긴 파이프라인이 남긴 부분 세그먼트는 버린다.
+    pub fn mark_discontinuity(&mut self) {
+        self.pending_parts.clear();
+        self.preload_hint = None;
+        self.pending_discontinuity = self.next_sequence > 0;
+    }
+
+    /// 세그먼트를 추가하고, 윈도우 밖으로 밀려나 저장소에서 지워도 되는 파일 이름들을 돌려준다.
+    /// 녹화 중이면 밀려난 세그먼트는 남기고 부분 세그먼트 파일만 돌려준다.
+    /// DVR 이 켜져 있으면 세그먼트는 DVR 윈도우까지 벗어난 뒤에 밀려난 것으로 본다.
+    /// 진행 중이던 부분 세그먼트들은 이 세그먼트에 속하게 된다.
+    pub fn push_segment(&mut self, uri: String, duration: f64) -> Vec<String> {
+        self.push_segment_at(uri, duration, SystemTime::now())
+    }
+
+    /// push_segment 와 같고, produced_at 은 세그먼트가 다 만들어진 벽시계 시각이다.
+    pub fn push_segment_at(&mut self, uri: String, duration: f64, produced_at: SystemTime) -> Vec<String> {
+        let discontinuity = std::mem::take(&mut self.pending_discontinuity);
+        if discontinuity {
+            self.period += 1;
+            self.period_start = self.total_duration;
+        }
+        self.segments.push_back(Segment {
+            sequence: self.next_sequence,
+            uri,
+            start: self.total_duration,
+            duration,
+            parts: std::mem::take(&mut self.pending_parts),
+            discontinuity,
+            period: self.period,
+            period_start: self.period_start,
+            program_date_time: produced_at
+                .checked_sub(Duration::from_secs_f64(duration))
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+        self.next_sequence += 1;
+        self.total_duration += duration;
+        self.target_duration = self.target_duration.max(duration.ceil() as u32);
+
+        let mut expired = Vec::new();
+        let mut evicted = Vec::new();
+        while self.window > 0 && self.segments.len() > self.window {
+            if let Some(mut segment) = self.segments.pop_front() {
+                if segment.discontinuity {
+                    self.discontinuity_sequence += 1;
+                }
+                expired.extend(segment.parts.drain(..).map(|part| part.uri));
+                evicted.push(segment);
+            }
+        }
+        if let Some(dvr) = &mut self.dvr {
+            dvr.segments.extend(evicted);
+            // 가장 오래된 세그먼트의 시작부터 라이브 끝까지가 window 를 넘으면 버린다.
+            evicted = Vec::new();
+            while dvr.segments.front().is_some_and(|s| self.total_duration - s.start > dvr.window) {
+                if let Some(segment) = dvr.segments.pop_front() {
+                    if segment.discontinuity {
+                        dvr.discontinuity_sequence += 1;
+                    }
+                    evicted.push(segment);
+                }
+            }
+        }
+        for segment in evicted {
+            match &mut self.archive {
+                Some(archive) => archive.push(segment),
+                None => expired.push(segment.uri),
+            }
+        }
+        expired
+    }
+
+    pub fn end(&mut self) {
+        self.preload_hint = None;
+        self.ended = true;
+    }
+
+    pub fn render(&self) -> String {
+        let version = if self.part_target.is_some() {
+            9
+        } else if self.init_uri.is_some() {
+            7
+        } else {
+            3
+        };
+        let media_sequence = self.segments.front()
+            .map(|s| s.sequence)
+            .unwrap_or(self.next_sequence);
+
+        let mut playlist = format!(
+            "#EXTM3U\n\
+             #EXT-X-VERSION:{}\n\
+             #EXT-X-TARGETDURATION:{}\n",
+            version, self.target_duration
+        );
+        if let Some(part_target) = self.part_target {
+            playlist.push_str(&format!(
+                "#EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK={:.3}\n\
+                 #EXT-X-PART-INF:PART-TARGET={:.3}\n",
+                part_target * 3.0, part_target
+            ));
+        }
+        playlist.push_str(&format!("#EXT-X-MEDIA-SEQUENCE:{}\n", media_sequence));
+        if self.discontinuity_sequence > 0 {
+            playlist.push_str(&format!("#EXT-X-DISCONTINUITY-SEQUENCE:{}\n", self.discontinuity_sequence));
+        }
+        if let Some(init_uri) = &self.init_uri {
+            playlist.push_str(&format!("#EXT-X-MAP:URI=\"{}\"\n", init_uri));
+        }
+
+        let parts_from = self.segments.len().saturating_sub(PART_SEGMENTS);
+        for (index, segment) in self.segments.iter().enumerate() {
+            if segment.discontinuity {
+                playlist.push_str("#EXT-X-DISCONTINUITY\n");
+            }
+            if index >= parts_from {
+                for part in &segment.parts {
+                    playlist.push_str(&render_part(part));
+                }
+            }
+            playlist.push_str(&format!("#EXTINF:{:.3},\n{}\n", segment.duration, segment.uri));
+        }
+        if self.pending_discontinuity && !self.pending_parts.is_empty() {
+            playlist.push_str("#EXT-X-DISCONTINUITY\n");
+        }
+        for part in &self.pending_parts {
+            playlist.push_str(&render_part(part));
+        }
+        if let Some(hint) = &self.preload_hint {
+            playlist.push_str(&format!("#EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"{}\"\n", hint));
+        }
+        if self.ended {
+            playlist.push_str("#EXT-X-ENDLIST\n");
+        }
+        playlist
+    }
+
+    /// 녹화 중이면 처음부터 지금까지의 모든 세그먼트를 돌려준다.
+    pub fn archived_segments(&self) -> Option<Vec<&Segment>> {
+        let archive = self.archive.as_ref()?;
+        let dvr_segments = self.dvr.iter().flat_map(|dvr| &dvr.segments);
+        Some(archive.iter().chain(dvr_segments).chain(&self.segments).collect())
+    }
+
+    /// 녹화 중이면 처음부터 지금까지의 모든 세그먼트로 VOD 플레이리스트를 만든다.
+    pub fn render_vod(&self) -> Option<String> {
+        let segments = self.archived_segments()?;
+        let version = if self.init_uri.is_some() { 7 } else { 3 };
+
+        let mut playlist = format!(
+            "#EXTM3U\n\
+             #EXT-X-VERSION:{}\n\
+             #EXT-X-TARGETDURATION:{}\n\
+             #EXT-X-PLAYLIST-TYPE:VOD\n\
+             #EXT-X-MEDIA-SEQUENCE:{}\n",
+            version,
+            self.target_duration,
+            segments.first().map_or(0, |s| s.sequence)
+        );
+        if let Some(init_uri) = &self.init_uri {
+            playlist.push_str(&format!("#EXT-X-MAP:URI=\"{}\"\n", init_uri));
+        }
+        for segment in segments {
+            if segment.discontinuity {
+                playlist.push_str("#EXT-X-DISCONTINUITY\n");
+            }
+            playlist.push_str(&format!("#EXTINF:{:.3},\n{}\n", segment.duration, segment.uri));
+        }
+        playlist.push_str("#EXT-X-ENDLIST\n");
+        Some(playlist)
+    }
+
+    /// DVR 이 켜져 있으면 되감기 윈도우 전체를 세그먼트마다 EXT-X-PROGRAM-DATE-TIME 을 붙여 보여준다.
+    /// 윈도우 앞쪽이 잘려 나가므로 EXT-X-PLAYLIST-TYPE 은 붙이지 않는다.
+    pub fn render_dvr(&self) -> Option<String> {
+        let dvr = self.dvr.as_ref()?;
+        let segments: Vec<&Segment> = dvr.segments.iter().chain(&self.segments).collect();
+        let version = if self.init_uri.is_some() { 7 } else { 3 };
+        let media_sequence = segments.first().map_or(self.next_sequence, |s| s.sequence);
+
+        let mut playlist = format!(
+            "#EXTM3U\n\
+             #EXT-X-VERSION:{}\n\
+             #EXT-X-TARGETDURATION:{}\n",
+            version, self.target_duration
+        );
+        playlist.push_str(&format!("#EXT-X-MEDIA-SEQUENCE:{}\n", media_sequence));
+        if dvr.discontinuity_sequence > 0 {
+            playlist.push_str(&format!("#EXT-X-DISCONTINUITY-SEQUENCE:{}\n", dvr.discontinuity_sequence));
+        }
+        if let Some(init_uri) = &self.init_uri {
+            playlist.push_str(&format!("#EXT-X-MAP:URI=\"{}\"\n", init_uri));
+        }
+        for segment in segments {
+            if segment.discontinuity {
+                playlist.push_str("#EXT-X-DISCONTINUITY\n");
+            }
+            playlist.push_str(&format!(
+                "#EXT-X-PROGRAM-DATE-TIME:{}\n#EXTINF:{:.3},\n{}\n",
+                format_rfc3339(segment.program_date_time), segment.duration, segment.uri
+            ));
+        }
+        if self.ended {
+            playlist.push_str("#EXT-X-ENDLIST\n");
+        }
+        Some(playlist)
+    }
+}
+
+fn render_part(part: &Part) -> String {
+    let independent = if part.independent { ",INDEPENDENT=YES" } else { "" };
+    format!("#EXT-X-PART:DURATION={:.3},URI=\"{}\"{}\n", part.duration, part.uri, independent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_sliding_window() {
+        let mut playlist = MediaPlaylist::new(2, 3);
+        let mut expired = Vec::new();
+        for i in 0..5 {
+            expired.extend(playlist.push_segment(format!("segment_{:05}.ts", i), 2.0));
+        }
+        assert_eq!(expired, ["segment_00000.ts", "segment_00001.ts"]);
+        assert_eq!(
+            playlist.render(),
+            "#EXTM3U\n\
+             #EXT-X-VERSION:3\n\
+             #EXT-X-TARGETDURATION:2\n\
+             #EXT-X-MEDIA-SEQUENCE:2\n\
+             #EXTINF:2.000,\nsegment_00002.ts\n\
+             #EXTINF:2.000,\nsegment_00003.ts\n\
+             #EXTINF:2.000,\nsegment_00004.ts\n"
+        );
+    }
+
+    #[test]
+    fn keeps_target_duration_after_long_segment_leaves_window() {
+        let mut playlist = MediaPlaylist::new(2, 2);
+        playlist.push_segment("segment_00000.ts".to_string(), 2.0);
+        assert!(playlist.render().contains("#EXT-X-TARGETDURATION:2\n"));
+        playlist.push_segment("segment_00001.ts".to_string(), 4.2);
+        assert!(playlist.render().contains("#EXT-X-TARGETDURATION:5\n"));
+        playlist.push_segment("segment_00002.ts".to_string(), 2.0);
+        playlist.push_segment("segment_00003.ts".to_string(), 2.0);
+        let rendered = playlist.render();
+        assert!(!rendered.contains("segment_00001.ts"));
+        assert!(rendered.contains("#EXT-X-TARGETDURATION:5\n"));
+    }
+
+    #[test]
+    fn renders_discontinuity_and_endlist() {
+        let mut playlist = MediaPlaylist::new(2, 2);
+        playlist.push_segment("segment_00000.ts".to_string(), 2.5);
+        playlist.mark_discontinuity();
+        playlist.push_segment("segment_00001.ts".to_string(), 2.0);
+        playlist.push_segment("segment_00002.ts".to_string(), 2.0);
+        playlist.end();
+        assert_eq!(
+            playlist.render(),
+            "#EXTM3U\n\
+             #EXT-X-VERSION:3\n\
+             #EXT-X-TARGETDURATION:3\n\
+             #EXT-X-MEDIA-SEQUENCE:1\n\
+             #EXT-X-DISCONTINUITY\n\
+             #EXTINF:2.000,\nsegment_00001.ts\n\
+             #EXTINF:2.000,\nsegment_00002.ts\n\
+             #EXT-X-ENDLIST\n"
+        );
+
+        playlist.push_segment("segment_00003.ts".to_string(), 2.0);
+        assert!(playlist.render().contains("#EXT-X-DISCONTINUITY-SEQUENCE:1\n"));
+    }
+
+    #[test]
+    fn renders_low_latency_parts() {
+        let mut playlist = MediaPlaylist::new(2, 5).with_low_latency(0.5);
+        playlist.set_init_uri("init.mp4".to_string());
+        playlist.push_part("part_00000.0.m4s".to_string(), 0.5, true);
+        playlist.push_part("part_00000.1.m4s".to_string(), 0.5, false);
+        playlist.push_segment("segment_00000.m4s".to_string(), 1.0);
+        playlist.push_part("part_00001.0.m4s".to_string(), 0.5, true);
+        playlist.set_preload_hint(Some("part_00001.1.m4s".to_string()));
+        assert_eq!(
+            playlist.render(),
+            "#EXTM3U\n\
+             #EXT-X-VERSION:9\n\
+             #EXT-X-TARGETDURATION:2\n\
+             #EXT-X-SERVER-CONTROL:CAN-BLOCK-RELOAD=YES,PART-HOLD-BACK=1.500\n\
+             #EXT-X-PART-INF:PART-TARGET=0.500\n\
+             #EXT-X-MEDIA-SEQUENCE:0\n\
+             #EXT-X-MAP:URI=\"init.mp4\"\n\
+             #EXT-X-PART:DURATION=0.500,URI=\"part_00000.0.m4s\",INDEPENDENT=YES\n\
+             #EXT-X-PART:DURATION=0.500,URI=\"part_00000.1.m4s\"\n\
+             #EXTINF:1.000,\nsegment_00000.m4s\n\
+             #EXT-X-PART:DURATION=0.500,URI=\"part_00001.0.m4s\",INDEPENDENT=YES\n\
+             #EXT-X-PRELOAD-HINT:TYPE=PART,URI=\"part_00001.1.m4s\"\n"
+        );
+    }
+
+    #[test]
+    fn renders_vod_from_archive() {
+        let mut playlist = MediaPlaylist::new(2, 1).with_archive();
+        for i in 0..3 {
+            assert!(playlist.push_segment(format!("segment_{:05}.ts", i), 2.0).is_empty());
+        }
+        assert_eq!(
+            playlist.render_vod().unwrap(),
+            "#EXTM3U\n\
+             #EXT-X-VERSION:3\n\
+             #EXT-X-TARGETDURATION:2\n\
+             #EXT-X-PLAYLIST-TYPE:VOD\n\
+             #EXT-X-MEDIA-SEQUENCE:0\n\
+             #EXTINF:2.000,\nsegment_00000.ts\n\
+             #EXTINF:2.000,\nsegment_00001.ts\n\
+             #EXTINF:2.000,\nsegment_00002.ts\n\
+             #EXT-X-ENDLIST\n"
+        );
+    }
+
+    #[test]
+    fn keeps_dvr_window_without_playlist_type() {
+        let mut playlist = MediaPlaylist::new(2, 1).with_dvr(6.0);
+        let mut expired = Vec::new();
+        for i in 0..4 {
+            expired.extend(playlist.push_segment(format!("segment_{:05}.ts", i), 2.0));
+        }
+        assert_eq!(expired, ["segment_00000.ts"]);
+        let dvr = playlist.render_dvr().unwrap();
+        assert!(!dvr.contains("#EXT-X-PLAYLIST-TYPE"));
+        assert!(dvr.contains("#EXT-X-MEDIA-SEQUENCE:1\n"));
+        let uris: Vec<&str> = dvr.lines().filter(|line| !line.starts_with('#')).collect();
+        assert_eq!(uris, ["segment_00001.ts", "segment_00002.ts", "segment_00003.ts"]);
+        assert_eq!(dvr.matches("#EXT-X-PROGRAM-DATE-TIME:").count(), 3);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod media_playlist;
+pub mod live_playlist;
+pub mod live_registry;
+pub mod dash_manifest;
+pub mod master_playlist;
+pub mod sprite_track;
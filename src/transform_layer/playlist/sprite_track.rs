@@ -0,0 +1,145 @@
+use crate::config::SpriteConfig;
+
+/// 시트 안의 타일 하나가 맡는 시간 구간
+#[derive(Debug, Clone)]
+struct SpriteCue {
+    /// 스트림 시작부터의 시간(초). VOD 플레이리스트의 시간축과 같다.
+    start: f64,
+    end: f64,
+    sheet: u32,
+    x: u32,
+    y: u32,
+}
+
+/// 스프라이트 시트의 타일 배치와 WebVTT 썸네일 트랙.
+/// 재연결된 송출도 같은 트랙에 이어 쓰도록 채널마다 LiveRegistry 에 등록한다.
+pub struct SpriteTrack {
+    tile_width: u32,
+    tile_height: u32,
+    columns: u32,
+    rows: u32,
+    cues: Vec<SpriteCue>,
+    /// 지금 채우고 있는 시트 번호와 그 안의 타일 개수
+    sheet: u32,
+    tiles: u32,
+    /// 앞에서부터 이만큼의 타일은 시트 파일에 기록되었다.
+    written: usize,
+}
+
+impl SpriteTrack {
+    pub fn new(config: &SpriteConfig) -> Self {
+        Self {
+            tile_width: config.width,
+            tile_height: config.height,
+            columns: config.columns.max(1),
+            rows: config.rows.max(1),
+            cues: Vec::new(),
+            sheet: 0,
+            tiles: 0,
+            written: 0,
+        }
+    }
+
+    pub fn sheet_file(sheet: u32) -> String {
+        format!("sprite_{:05}.jpg", sheet)
+    }
+
+    /// 이전 파이프라인이 채우던 시트의 픽셀은 남아 있지 않으므로 새 파이프라인은 다음 시트부터 쓴다.
+    /// 시트에 기록되지 못한 타일도 버린다.
+    pub fn resume(&mut self) {
+        self.cues.truncate(self.written);
+        self.start_new_sheet();
+    }
+
+    fn start_new_sheet(&mut self) {
+        if self.tiles > 0 {
+            self.sheet += 1;
+            self.tiles = 0;
+        }
+    }
+
+    /// 타일을 배정하고 (시트 번호, x, y, 지금까지의 타일 개수) 를 돌려준다.
+    pub fn add_tile(&mut self, start: f64, duration: f64) -> (u32, u32, u32, usize) {
+        if self.tiles == self.columns * self.rows {
+            self.start_new_sheet();
+        }
+        let x = (self.tiles % self.columns) * self.tile_width;
+        let y = (self.tiles / self.columns) * self.tile_height;
+        self.tiles += 1;
+        self.cues.push(SpriteCue { start, end: start + duration, sheet: self.sheet, x, y });
+        (self.sheet, x, y, self.cues.len())
+    }
+
+    pub fn mark_written(&mut self, tiles: usize) {
+        self.written = self.written.max(tiles);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.written == 0
+    }
+
+    /// 시트 파일에 기록된 타일만 담는다. 경로는 thumbnails.vtt 와 같은 디렉터리 기준이다.
+    pub fn render_vtt(&self) -> String {
+        let mut vtt = String::from("WEBVTT\n");
+        for cue in &self.cues[..self.written] {
+            vtt.push_str(&format!(
+                "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+                format_timestamp(cue.start),
+                format_timestamp(cue.end),
+                Self::sheet_file(cue.sheet),
+                cue.x,
+                cue.y,
+                self.tile_width,
+                self.tile_height
+            ));
+        }
+        vtt
+    }
+}
+
+/// WebVTT 타임스탬프 ("00:00:05.000")
+fn format_timestamp(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        (millis / 60_000) % 60,
+        (millis / 1000) % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_written_tiles_across_resume() {
+        let config = SpriteConfig { columns: 2, rows: 1, ..SpriteConfig::default() };
+        let mut track = SpriteTrack::new(&config);
+        assert_eq!(track.add_tile(0.0, 5.0), (0, 0, 0, 1));
+        assert_eq!(track.add_tile(5.0, 5.0), (0, 160, 0, 2));
+        track.mark_written(2);
+        // 시트 1 은 기록되기 전에 송출이 끊겼다.
+        assert_eq!(track.add_tile(10.0, 5.0), (1, 0, 0, 3));
+        assert!(!track.is_empty());
+
+        track.resume();
+        assert_eq!(track.add_tile(15.0, 5.0), (2, 0, 0, 3));
+        assert_eq!(track.render_vtt().matches("-->").count(), 2);
+        track.mark_written(3);
+        assert_eq!(
+            track.render_vtt(),
+            "WEBVTT\n\
+             \n00:00:00.000 --> 00:00:05.000\nsprite_00000.jpg#xywh=0,0,160,90\n\
+             \n00:00:05.000 --> 00:00:10.000\nsprite_00000.jpg#xywh=160,0,160,90\n\
+             \n00:00:15.000 --> 00:00:20.000\nsprite_00002.jpg#xywh=0,0,160,90\n"
+        );
+    }
+
+    #[test]
+    fn formats_vtt_timestamp() {
+        assert_eq!(format_timestamp(3725.5), "01:02:05.500");
+        assert_eq!(format_timestamp(-1.0), "00:00:00.000");
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::{Arc, Mutex};
+use gstreamer_app::{gst, AppSink, AppSinkCallbacks};
+use crate::transform_layer::sinks::segment_output::SegmentOutput;
+
+/// isofmp4mux 가 내보내는 init 세그먼트와 프래그먼트를 받아
+/// init.mp4, segment_%05d.m4s, playlist.m3u8 로 기록한다.
+///
+/// low latency 모드에서는 isofmp4mux 의 chunk 하나가 부분 세그먼트(segment_%05d.%d.m4s)가 되고,
+/// 다음 프래그먼트가 시작될 때 모인 chunk 들을 합쳐 전체 세그먼트를 기록한다.
+pub struct Fmp4Writer {
+    output: SegmentOutput,
+    segment_delay: u32,
+    low_latency: bool,
+    pending: Option<PendingSegment>,
+}
+
+struct PendingSegment {
+    data: Vec<u8>,
+    duration: f64,
+    parts: usize,
+}
+
+impl Fmp4Writer {
+    pub fn new(output: SegmentOutput, segment_delay: u32, low_latency: bool) -> Self {
+        Self {
+            output,
+            segment_delay,
+            low_latency,
+            pending: None,
+        }
+    }
+
+    pub fn attach(self, app_sink: &AppSink) {
+        let writer = Arc::new(Mutex::new(self));
+        let eos_writer = writer.clone();
+
+        app_sink.set_callbacks(
+            AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    writer.lock().unwrap().write_sample(&sample)
+                })
+                .eos(move |_| {
+                    eos_writer.lock().unwrap().finish();
+                })
+                .build(),
+        );
+    }
+
+    fn write_sample(&mut self, sample: &gst::Sample) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let buffers: Vec<&gst::BufferRef> = match sample.buffer_list() {
+            Some(list) => list.iter().collect(),
+            None => sample.buffer().into_iter().collect(),
+        };
+
+        let mut header = Vec::new();
+        let mut fragment = Vec::new();
+        let mut fragment_start = false;
+        let mut start: Option<gst::ClockTime> = None;
+        let mut end: Option<gst::ClockTime> = None;
+
+        for buffer in buffers {
+            let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+            if buffer.flags().contains(gst::BufferFlags::HEADER) {
+                header.extend_from_slice(&map);
+                continue;
+            }
+
+            // isofmp4mux 는 프래그먼트의 첫 chunk 에만 DELTA_UNIT 을 붙이지 않는다.
+            if fragment.is_empty() {
+                fragment_start = !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT);
+            }
+            fragment.extend_from_slice(&map);
+            if let Some(pts) = buffer.pts() {
+                start = Some(start.map_or(pts, |s| s.min(pts)));
+                let buffer_end = pts + buffer.duration().unwrap_or(gst::ClockTime::ZERO);
+                end = Some(end.map_or(buffer_end, |e| e.max(buffer_end)));
+            }
+        }
+
+        if !header.is_empty() {
+            self.output.put_file("init.mp4", header, |p| p.set_init_uri("init.mp4".to_string()));
+        }
+
+        if !fragment.is_empty() {
+            let duration = match (start, end) {
+                (Some(start), Some(end)) if end > start => (end - start).mseconds() as f64 / 1000.0,
+                _ => self.segment_delay as f64,
+            };
+
+            if self.low_latency {
+                self.write_part(fragment, duration, fragment_start);
+            } else {
+                let file_name = format!("segment_{:05}.m4s", self.output.next_sequence());
+                self.output.push_segment(file_name, fragment, duration);
+            }
+            self.output.publish_playlist();
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn write_part(&mut self, data: Vec<u8>, duration: f64, independent: bool) {
+        if independent && let Some(pending) = self.pending.take() {
+            let file_name = format!("segment_{:05}.m4s", self.output.next_sequence());
+            self.output.push_segment(file_name, pending.data, pending.duration);
+        }
+
+        let sequence = self.output.next_sequence();
+        let pending = self.pending.get_or_insert(PendingSegment { data: Vec::new(), duration: 0.0, parts: 0 });
+        let part_name = format!("segment_{:05}.{}.m4s", sequence, pending.parts);
+        pending.data.extend_from_slice(&data);
+        pending.duration += duration;
+        pending.parts += 1;
+
+        // 세그먼트 길이를 다 채웠다면 다음 부분 세그먼트는 새 세그먼트의 첫 조각이 된다.
+        let next_part_name = if pending.duration + duration > self.segment_delay as f64 {
+            format!("segment_{:05}.0.m4s", sequence + 1)
+        } else {
+            format!("segment_{:05}.{}.m4s", sequence, pending.parts)
+        };
+
+        let file_name = part_name.clone();
+        self.output.put_file(&file_name, data, move |p| {
+            p.push_part(part_name, duration, independent);
+            p.set_preload_hint(Some(next_part_name));
+        });
+    }
+
+    fn finish(&mut self) {
+        if let Some(pending) = self.pending.take() {
+            let file_name = format!("segment_{:05}.m4s", self.output.next_sequence());
+            self.output.push_segment(file_name, pending.data, pending.duration);
+        }
+        self.output.finish();
+    }
+}
@@ -0,0 +1,5 @@
+pub mod segment_output;
+pub mod fmp4_writer;
+pub mod ts_writer;
+pub mod thumbnail_writer;
+pub mod sprite_writer;
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::metrics_layer::server_metrics::SEGMENTS_WRITTEN;
+use crate::storage_layer::store_queue::StoreQueue;
+use crate::transform_layer::playlist::live_playlist::LivePlaylist;
+use crate::transform_layer::playlist::media_playlist::MediaPlaylist;
+
+/// 화질 하나의 출력. 세그먼트를 저장소에 올리고 메모리 플레이리스트를 갱신한다.
+/// 메모리 플레이리스트는 HTTP 로 바로 보이므로 저장 큐에서 파일이 저장된 뒤에 갱신한다.
+/// key_prefix 는 "닉네임/시작시각/화질" 형태이다.
+pub struct SegmentOutput {
+    key_prefix: String,
+    store: StoreQueue,
+    playlist: Arc<LivePlaylist>,
+    on_first_playlist: Option<Arc<dyn Fn() + Send + Sync>>,
+    keep_open: Arc<AtomicBool>,
+}
+
+impl SegmentOutput {
+    pub fn new(key_prefix: String, store: StoreQueue, playlist: Arc<LivePlaylist>) -> Self {
+        Self { key_prefix, store, playlist, on_first_playlist: None, keep_open: Arc::default() }
+    }
+
+    /// 플레이리스트를 처음 기록할 때 한 번 호출된다.
+    pub fn with_first_playlist_hook(mut self, hook: Arc<dyn Fn() + Send + Sync>) -> Self {
+        self.on_first_playlist = Some(hook);
+        self
+    }
+
+    /// 파이프라인이 끝날 때 이 값이 켜져 있으면 재연결을 기다리는 중이므로 ENDLIST 를 붙이지 않는다.
+    pub fn with_keep_open(mut self, keep_open: Arc<AtomicBool>) -> Self {
+        self.keep_open = keep_open;
+        self
+    }
+
+    /// 다음 세그먼트의 파일 번호
+    pub fn next_sequence(&self) -> u64 {
+        self.playlist.file_sequence()
+    }
+
+    fn key(&self, file_name: &str) -> String {
+        format!("{}/{}", self.key_prefix, file_name)
+    }
+
+    /// 파일을 올리고, 저장에 성공하면 update 로 플레이리스트를 고친다.
+    pub fn put_file(&self, file_name: &str, data: Vec<u8>, update: impl FnOnce(&mut MediaPlaylist) + Send + 'static) {
+        let playlist = self.playlist.clone();
+        self.store.put_segment_then(self.key(file_name), data, move || {
+            playlist.update(update);
+            Vec::new()
+        });
+    }
+
+    /// 세그먼트를 올리고 저장되면 플레이리스트에 추가한다. 라이브 윈도우 밖으로 밀려난 파일은 녹화 중이 아니면 지운다.
+    pub fn push_segment(&self, file_name: String, data: Vec<u8>, duration: f64) {
+        self.playlist.advance_file_sequence();
+
+        let key = self.key(&file_name);
+        let key_prefix = self.key_prefix.clone();
+        let playlist = self.playlist.clone();
+        self.store.put_segment_then(key, data, move || {
+            SEGMENTS_WRITTEN.inc(&[key_prefix.rsplit('/').next().unwrap_or_default()]);
+            playlist.update(|p| p.push_segment(file_name, duration))
+                .into_iter()
+                .map(|file_name| format!("{}/{}", key_prefix, file_name))
+                .collect()
+        });
+    }
+
+    /// 메모리 플레이리스트를 저장소에도 기록한다. 앞서 올린 세그먼트가 반영된 뒤에 렌더링된다.
+    pub fn publish_playlist(&mut self) {
+        let playlist = self.playlist.clone();
+        self.store.put_rendered_playlist(self.key("playlist.m3u8"), move || playlist.render());
+        if let Some(hook) = self.on_first_playlist.take() {
+            self.store.run(move || hook());
+        }
+    }
+
+    pub fn finish(&mut self) {
+        if !self.keep_open.load(Ordering::SeqCst) {
+            let playlist = self.playlist.clone();
+            self.store.run(move || playlist.update(|p| p.end()));
+        }
+        self.publish_playlist();
+    }
+}
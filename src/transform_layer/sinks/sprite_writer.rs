@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use gstreamer_app::{gst, AppSink, AppSinkCallbacks, AppSrc};
+use crate::config::SpriteConfig;
+use crate::storage_layer::store_queue::StoreQueue;
+use crate::transform_layer::playlist::sprite_track::SpriteTrack;
+
+/// 타일을 시트에 붙여 jpegenc 로 보낸다.
+/// 시트가 다 차기 전에 파이프라인이 내려가도 빠지는 구간이 없도록 타일이 붙을 때마다 시트를 다시 인코딩한다.
+pub struct SpriteWriter {
+    track: Arc<Mutex<SpriteTrack>>,
+    tile_width: usize,
+    tile_height: usize,
+    sheet_width: usize,
+    /// 타일 하나가 맡는 시간(초)
+    interval: f64,
+    /// 이 파이프라인의 첫 타일이 스트림 시작부터 떨어진 시간(초)
+    time_base: f64,
+    first_pts: Option<gst::ClockTime>,
+    /// 지금 채우고 있는 시트 번호와 RGBx 픽셀
+    sheet: Option<u32>,
+    pixels: Vec<u8>,
+    encoder: AppSrc,
+    /// 인코더에 보낸 (시트 번호, 타일 개수). jpegenc 는 받은 순서대로 내보낸다.
+    pending: Arc<Mutex<VecDeque<(u32, usize)>>>,
+}
+
+/// 인코딩된 시트를 "출력 key/sprite_00000.jpg" 에 쓰고 thumbnails.vtt 를 갱신한다.
+struct SheetOutput {
+    output_key: String,
+    track: Arc<Mutex<SpriteTrack>>,
+    store: StoreQueue,
+    pending: Arc<Mutex<VecDeque<(u32, usize)>>>,
+}
+
+impl SpriteWriter {
+    pub fn new(track: Arc<Mutex<SpriteTrack>>, config: &SpriteConfig, time_base: f64, encoder: AppSrc) -> Self {
+        track.lock().unwrap().resume();
+        let sheet_width = (config.width * config.columns) as usize;
+        let sheet_height = (config.height * config.rows) as usize;
+        Self {
+            track,
+            tile_width: config.width as usize,
+            tile_height: config.height as usize,
+            sheet_width,
+            interval: config.interval as f64 / 1000.0,
+            time_base,
+            first_pts: None,
+            sheet: None,
+            pixels: vec![0; sheet_width * sheet_height * 4],
+            encoder,
+            pending: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    pub fn attach(self, tile_sink: &AppSink, sheet_sink: &AppSink, output_key: &str, store: StoreQueue) {
+        let sheet_output = SheetOutput {
+            output_key: output_key.to_string(),
+            track: self.track.clone(),
+            store,
+            pending: self.pending.clone(),
+        };
+        sheet_sink.set_callbacks(
+            AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    sheet_output.write_sheet(&sample)
+                })
+                .build(),
+        );
+
+        let encoder = self.encoder.clone();
+        let writer = Mutex::new(self);
+        tile_sink.set_callbacks(
+            AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    writer.lock().unwrap().add_tile(&sample)
+                })
+                .eos(move |_| {
+                    let _ = encoder.end_of_stream();
+                })
+                .build(),
+        );
+    }
+
+    fn add_tile(&mut self, sample: &gst::Sample) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+        let Some(pts) = buffer.pts() else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+        let first_pts = *self.first_pts.get_or_insert(pts);
+        let start = self.time_base + pts.saturating_sub(first_pts).mseconds() as f64 / 1000.0;
+
+        let (sheet, x, y, tiles) = self.track.lock().unwrap().add_tile(start, self.interval);
+        if self.sheet != Some(sheet) {
+            self.sheet = Some(sheet);
+            self.pixels.fill(0);
+        }
+
+        // RGBx 는 한 줄이 항상 width * 4 바이트이다.
+        let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+        let row_len = self.tile_width * 4;
+        for (row, src) in map.chunks_exact(row_len).take(self.tile_height).enumerate() {
+            let offset = ((y as usize + row) * self.sheet_width + x as usize) * 4;
+            self.pixels[offset..offset + row_len].copy_from_slice(src);
+        }
+
+        self.pending.lock().unwrap().push_back((sheet, tiles));
+        if self.encoder.push_buffer(gst::Buffer::from_slice(self.pixels.clone())).is_err() {
+            self.pending.lock().unwrap().pop_back();
+        }
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
+
+impl SheetOutput {
+    fn write_sheet(&self, sample: &gst::Sample) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let Some((sheet, tiles)) = self.pending.lock().unwrap().pop_front() else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+        let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+        let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+        self.store.put_segment(format!("{}/{}", self.output_key, SpriteTrack::sheet_file(sheet)), map.to_vec());
+
+        let vtt = {
+            let mut track = self.track.lock().unwrap();
+            track.mark_written(tiles);
+            track.render_vtt()
+        };
+        self.store.put_playlist(format!("{}/thumbnails.vtt", self.output_key), vtt);
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
@@ -0,0 +1,46 @@
+use std::sync::Arc;
+use bytes::Bytes;
+use gstreamer_app::{gst, AppSink, AppSinkCallbacks};
+use crate::storage_layer::store_queue::StoreQueue;
+use crate::transform_layer::playlist::live_registry::LiveRegistry;
+
+/// jpegenc 가 만든 미리보기를 메모리에 올려 두고 저장소의 "출력 key/thumbnail.jpg" 에도 기록한다.
+/// 방송이 끝난 뒤에는 저장소에 남은 마지막 미리보기를 보여준다.
+pub struct ThumbnailWriter {
+    channel: String,
+    output_key: String,
+    live_registry: Arc<LiveRegistry>,
+    store: StoreQueue,
+}
+
+impl ThumbnailWriter {
+    pub fn new(channel: &str, output_key: &str, live_registry: Arc<LiveRegistry>, store: StoreQueue) -> Self {
+        Self {
+            channel: channel.to_string(),
+            output_key: output_key.to_string(),
+            live_registry,
+            store,
+        }
+    }
+
+    pub fn attach(self, app_sink: &AppSink) {
+        app_sink.set_callbacks(
+            AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    self.write_sample(&sample)
+                })
+                .build(),
+        );
+    }
+
+    fn write_sample(&self, sample: &gst::Sample) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+        let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+        let image = Bytes::copy_from_slice(&map);
+
+        self.live_registry.set_thumbnail(&self.channel, &self.output_key, image.clone());
+        self.store.put_segment(format!("{}/thumbnail.jpg", self.output_key), image.to_vec());
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
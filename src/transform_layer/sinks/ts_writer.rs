@@ -0,0 +1,90 @@
+use std::sync::{Arc, Mutex};
+use gstreamer_app::{gst, AppSink, AppSinkCallbacks};
+use crate::transform_layer::sinks::segment_output::SegmentOutput;
+
+/// mpegtsmux 의 출력을 키프레임 경계에서 잘라 segment_%05d.ts 로 기록한다.
+/// 세그먼트 길이가 segment_delay 를 넘긴 뒤 처음 오는 키프레임에서 새 세그먼트를 시작한다.
+pub struct TsWriter {
+    output: SegmentOutput,
+    segment_delay: u32,
+    data: Vec<u8>,
+    start: Option<gst::ClockTime>,
+    end: Option<gst::ClockTime>,
+}
+
+impl TsWriter {
+    pub fn new(output: SegmentOutput, segment_delay: u32) -> Self {
+        Self {
+            output,
+            segment_delay,
+            data: Vec::new(),
+            start: None,
+            end: None,
+        }
+    }
+
+    pub fn attach(self, app_sink: &AppSink) {
+        let writer = Arc::new(Mutex::new(self));
+        let eos_writer = writer.clone();
+
+        app_sink.set_callbacks(
+            AppSinkCallbacks::builder()
+                .new_sample(move |sink| {
+                    let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
+                    writer.lock().unwrap().write_sample(&sample)
+                })
+                .eos(move |_| {
+                    eos_writer.lock().unwrap().finish();
+                })
+                .build(),
+        );
+    }
+
+    fn write_sample(&mut self, sample: &gst::Sample) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let buffers: Vec<&gst::BufferRef> = match sample.buffer_list() {
+            Some(list) => list.iter().collect(),
+            None => sample.buffer().into_iter().collect(),
+        };
+
+        for buffer in buffers {
+            let keyframe = !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT);
+            if keyframe && self.duration() >= self.segment_delay as f64 {
+                self.flush_segment();
+            }
+
+            let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+            self.data.extend_from_slice(&map);
+            if let Some(timestamp) = buffer.pts().or(buffer.dts()) {
+                self.start = Some(self.start.map_or(timestamp, |s| s.min(timestamp)));
+                let buffer_end = timestamp + buffer.duration().unwrap_or(gst::ClockTime::ZERO);
+                self.end = Some(self.end.map_or(buffer_end, |e| e.max(buffer_end)));
+            }
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn duration(&self) -> f64 {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) if end > start => (end - start).mseconds() as f64 / 1000.0,
+            _ => 0.0,
+        }
+    }
+
+    fn flush_segment(&mut self) {
+        if self.data.is_empty() {
+            return;
+        }
+        let duration = self.duration();
+        let file_name = format!("segment_{:05}.ts", self.output.next_sequence());
+        self.output.push_segment(file_name, std::mem::take(&mut self.data), duration);
+        self.output.publish_playlist();
+        self.start = None;
+        self.end = None;
+    }
+
+    fn finish(&mut self) {
+        self.flush_segment();
+        self.output.finish();
+    }
+}
@@ -0,0 +1,26 @@
+use std::sync::{Arc, OnceLock};
+use tokio_util::sync::CancellationToken;
+
+/// 변환이 서버 쪽 사유(파이프라인 오류, 관리자 강제 종료)로 중단됐음을 RTMP 세션에 알린다.
+#[derive(Clone, Default)]
+pub struct StopSignal {
+    reason: Arc<OnceLock<String>>,
+    /// 멈추면 취소된다. 세션은 다음 데이터를 기다리지 않고 이 토큰으로 연결을 닫는다.
+    closed: CancellationToken,
+}
+
+impl StopSignal {
+    pub fn new(closed: CancellationToken) -> Self {
+        Self { reason: Arc::default(), closed }
+    }
+
+    /// 처음 기록된 사유만 남는다.
+    pub fn stop(&self, reason: String) {
+        let _ = self.reason.set(reason);
+        self.closed.cancel();
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        self.reason.get().map(String::as_str)
+    }
+}
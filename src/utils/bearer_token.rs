@@ -0,0 +1,13 @@
+use axum::http::{HeaderMap, header};
+
+/// "Authorization: Bearer <token>" 헤더가 token 과 같은지 본다. 길이 외에는 비교 시간이 드러나지 않는다.
+pub fn has_bearer_token(headers: &HeaderMap, token: &str) -> bool {
+    headers.get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|actual| constant_time_eq(actual.as_bytes(), token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
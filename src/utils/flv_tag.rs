@@ -0,0 +1,22 @@
+/// FLV 영상 태그의 첫 바이트 상위 4비트가 1 이면 키프레임이다.
+pub fn is_keyframe(data: &[u8]) -> bool {
+    data.first().is_some_and(|flags| flags >> 4 == 1)
+}
+
+pub fn is_avc_sequence_header(data: &[u8]) -> bool {
+    matches!(data, [flags, 0, ..] if flags & 0x0f == 7)
+}
+
+pub fn is_aac_sequence_header(data: &[u8]) -> bool {
+    matches!(data, [flags, 0, ..] if flags >> 4 == 10)
+}
+
+/// 메타데이터나 코덱 설정처럼 재생을 시작하기 전에 받아야 하는 태그
+pub fn is_header_tag(tag_type: u8, data: &[u8]) -> bool {
+    match tag_type {
+        18 => true,
+        9 => is_avc_sequence_header(data),
+        8 => is_aac_sequence_header(data),
+        _ => false,
+    }
+}
@@ -7,7 +7,7 @@ impl<T, E: std::fmt::Display> LogError<T> for Result<T, E> {
         match self {
             Ok(val) => Some(val),
             Err(e) => {
-                eprintln!("{}: {}", error_text, e);
+                tracing::error!("{}: {}", error_text, e);
                 None
             }
         }
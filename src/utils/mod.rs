@@ -1 +1,7 @@
-pub mod log_error;
\ No newline at end of file
+pub mod bearer_token;
+pub mod flv_tag;
+pub mod hex;
+pub mod log_error;
+pub mod rtmp_command;
+pub mod shutdown_signal;
+pub mod time;
@@ -0,0 +1,90 @@
+use bytes::Bytes;
+use scuffle_amf0::{Amf0Decoder, Amf0Encoder, Amf0Error, Amf0Value};
+
+/// scuffle-rtmp 의 Command 는 서버 쪽 응답만 쓸 수 있어서 AMF0 명령을 직접 읽고 쓴다.
+pub struct RtmpCommand {
+    pub name: String,
+    pub transaction: f64,
+    /// 명령 객체부터 나머지 인자까지
+    pub values: Vec<Amf0Value<'static>>,
+}
+
+impl RtmpCommand {
+    /// 이름이 없는 메시지면 None 을 돌려준다.
+    pub fn decode(payload: &[u8]) -> Result<Option<Self>, Amf0Error> {
+        let mut values = Amf0Decoder::from_slice(payload)
+            .decode_all()?
+            .into_iter()
+            .map(Amf0Value::into_owned);
+        let name = match values.next() {
+            Some(Amf0Value::String(name)) => name.as_str().to_string(),
+            _ => return Ok(None),
+        };
+        let transaction = match values.next() {
+            Some(Amf0Value::Number(transaction)) => transaction,
+            _ => 0.0,
+        };
+        Ok(Some(Self { name, transaction, values: values.collect() }))
+    }
+
+    pub fn encode(name: &str, transaction: f64, object: Amf0Value<'_>, args: &[Amf0Value<'_>]) -> Result<Bytes, Amf0Error> {
+        let mut payload = Vec::new();
+        let mut encoder = Amf0Encoder::new(&mut payload);
+        encoder.encode_string(name)?;
+        encoder.encode_number(transaction)?;
+        object.encode(&mut encoder)?;
+        for arg in args {
+            arg.encode(&mut encoder)?;
+        }
+        Ok(Bytes::from(payload))
+    }
+
+    /// 명령 객체 다음 n 번째 인자가 문자열이면 돌려준다.
+    pub fn string_arg(&self, n: usize) -> Option<&str> {
+        match self.values.get(n + 1) {
+            Some(Amf0Value::String(value)) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    /// onStatus, _error 의 info 객체에서 필드 하나를 꺼낸다.
+    pub fn status_field(&self, field: &str) -> Option<String> {
+        self.values.iter().find_map(|value| match value {
+            Amf0Value::Object(object) => object.iter()
+                .find(|(key, _)| key.as_str() == field)
+                .and_then(|(_, value)| match value {
+                    Amf0Value::String(value) => Some(value.as_str().to_string()),
+                    _ => None,
+                }),
+            _ => None,
+        })
+    }
+
+    pub fn status_text(&self) -> String {
+        match (self.status_field("code"), self.status_field("description")) {
+            (Some(code), Some(description)) => format!("{} ({})", code, description),
+            (Some(code), None) => code,
+            (None, Some(description)) => description,
+            (None, None) => "no reason given".to_string(),
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.name == "_error" || self.status_field("level").as_deref() == Some("error")
+    }
+}
+
+/// onStatus 명령. 상태 객체는 level, code, description 만 담는다.
+pub fn encode_on_status(level: &str, code: &str, description: &str) -> Result<Bytes, Amf0Error> {
+    let info = [
+        ("level".into(), Amf0Value::String(level.into())),
+        ("code".into(), Amf0Value::String(code.into())),
+        ("description".into(), Amf0Value::String(description.into())),
+    ].into_iter().collect();
+    RtmpCommand::encode("onStatus", 0.0, Amf0Value::Null, &[Amf0Value::Object(info)])
+}
+
+/// SetChunkSize 메시지 본문의 chunk 크기. 최상위 비트는 항상 0 이다.
+pub fn read_chunk_size(payload: &[u8]) -> Option<usize> {
+    payload.get(..4).map(|b| (u32::from_be_bytes([b[0], b[1], b[2], b[3]]) & 0x7fff_ffff) as usize)
+}
@@ -0,0 +1,10 @@
+use tokio::signal::unix::{signal, SignalKind};
+
+/// SIGTERM 이나 Ctrl+C(SIGINT) 를 받으면 끝난다.
+pub async fn shutdown_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+    tokio::select! {
+        _ = terminate.recv() => {}
+        _ = tokio::signal::ctrl_c() => {}
+    }
+}
@@ -0,0 +1,43 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// SystemTime 을 "2025-01-01T00:00:00.000Z" 형태의 UTC 문자열로 바꾼다.
+pub fn format_rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
+    let secs = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// 1970-01-01 부터의 일 수를 (년, 월, 일) 로 바꾼다. (Howard Hinnant 의 civil_from_days)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// AWS SigV4 에 쓰이는 ("20250101T000000Z", "20250101") 형태의 날짜 문자열
+pub fn format_amz_date(time: SystemTime) -> (String, String) {
+    let secs = time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO).as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let rem = secs % 86_400;
+    let date = format!("{:04}{:02}{:02}", year, month, day);
+    let amz_date = format!("{}T{:02}{:02}{:02}Z", date, rem / 3600, (rem % 3600) / 60, rem % 60);
+    (amz_date, date)
+}
@@ -0,0 +1,7 @@
+/*
+ 웹훅 레이어 (webhook_layer)
+ 방송 시작/종료, 파이프라인 오류, 첫 플레이리스트 기록 같은 스트림 생명주기 이벤트를
+ 설정된 URL 로 JSON 으로 보낸다. 전송은 별도 작업에서 처리되므로 RTMP 처리를 막지 않는다.
+ */
+pub mod stream_event;
+pub mod webhook_sender;
@@ -0,0 +1,63 @@
+use std::time::SystemTime;
+use serde::Serialize;
+use crate::config;
+use crate::utils::time::format_rfc3339;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum EventKind {
+    #[serde(rename = "stream.publish")]
+    Publish,
+    #[serde(rename = "stream.unpublish")]
+    Unpublish,
+    #[serde(rename = "stream.error")]
+    Error,
+    #[serde(rename = "stream.playlist_ready")]
+    PlaylistReady,
+}
+
+/// 웹훅으로 보내는 JSON 본문
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamEvent {
+    pub event: EventKind,
+    /// 인증된 스트림 경로 ("닉네임/시작시각")
+    pub stream_id: String,
+    pub nickname: String,
+    pub started_at: String,
+    /// 저장소 안에서 이 방송의 세그먼트가 저장되는 key 접두사
+    pub output_path: String,
+    pub playlist_url: String,
+    /// 이벤트가 발생한 시각 (RFC 3339)
+    pub timestamp: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// 녹화본이 있으면 저장소 안의 VOD 플레이리스트 key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive_path: Option<String>,
+}
+
+impl StreamEvent {
+    pub fn new(event: EventKind, stream_id: &str) -> Self {
+        let (nickname, started_at) = stream_id.split_once('/').unwrap_or((stream_id, ""));
+        Self {
+            event,
+            stream_id: stream_id.to_string(),
+            nickname: nickname.to_string(),
+            started_at: started_at.to_string(),
+            output_path: stream_id.to_string(),
+            playlist_url: format!("{}/{}/master.m3u8", config::get_config().server.host, nickname),
+            timestamp: format_rfc3339(SystemTime::now()),
+            reason: None,
+            archive_path: None,
+        }
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    pub fn with_archive(mut self, archive_path: Option<String>) -> Self {
+        self.archive_path = archive_path;
+        self
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+use bytes::Bytes;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use crate::config::WebhookConfig;
+use crate::utils::hex::encode_hex;
+use crate::webhook_layer::stream_event::StreamEvent;
+
+/// 이벤트를 수신 URL 마다 제한된 크기의 큐에 넣고 URL 별 작업에서 전송한다.
+/// 큐가 가득 차면 그 URL 로 보낼 이벤트를 버리므로 느린 수신 서버가 다른 수신 서버나 RTMP 처리, 스트리밍 스레드를 막지 않는다.
+#[derive(Clone)]
+pub struct WebhookSender {
+    /// 수신 URL 별 큐. 직렬화한 본문을 넣는다.
+    senders: Vec<(String, mpsc::Sender<Bytes>)>,
+}
+
+impl WebhookSender {
+    /// urls 가 비어 있으면 아무것도 보내지 않는다. tokio 런타임 안에서 호출해야 한다.
+    pub fn spawn(config: &WebhookConfig, client: Client) -> Self {
+        let senders = config.urls.iter()
+            .map(|url| {
+                let (sender, mut receiver) = mpsc::channel::<Bytes>(config.queue_size.max(1));
+                let client = client.clone();
+                let config = config.clone();
+                let url = url.clone();
+                tokio::spawn({
+                    let url = url.clone();
+                    async move {
+                        while let Some(body) = receiver.recv().await {
+                            deliver(&client, &config, &url, &body).await;
+                        }
+                    }
+                });
+                (url, sender)
+            })
+            .collect();
+        Self { senders }
+    }
+
+    /// 스트리밍 스레드에서도 호출할 수 있다.
+    pub fn send(&self, event: StreamEvent) {
+        if self.senders.is_empty() {
+            return;
+        }
+        match serde_json::to_vec(&event) {
+            Ok(body) => self.enqueue(Bytes::from(body)),
+            Err(e) => tracing::error!(error = %e, "Failed to serialize webhook event"),
+        }
+    }
+
+    fn enqueue(&self, body: Bytes) {
+        for (url, sender) in &self.senders {
+            if let Err(e) = sender.try_send(body.clone()) {
+                tracing::warn!(url, error = %e, "Dropping webhook event");
+            }
+        }
+    }
+}
+
+/// 실패하면 retry_backoff 부터 두 배씩 늘려가며 retries 만큼 다시 보낸다.
+async fn deliver(client: &Client, config: &WebhookConfig, url: &str, body: &Bytes) {
+    for attempt in 0..=config.retries {
+        let mut request = client
+            .post(url)
+            .timeout(Duration::from_millis(config.timeout))
+            .header("content-type", "application/json")
+            .body(body.clone());
+        if let Some(secret) = &config.secret {
+            request = request.header(&config.signature_header, signature(secret, body));
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => tracing::warn!(url, status = %response.status(), attempt = attempt + 1, "Webhook rejected"),
+            Err(e) => tracing::warn!(url, error = %e, attempt = attempt + 1, "Webhook failed"),
+        }
+        if attempt < config.retries {
+            tokio::time::sleep(backoff(config, attempt)).await;
+        }
+    }
+}
+
+/// attempt 번째(0 부터) 실패 뒤에 기다릴 시간
+fn backoff(config: &WebhookConfig, attempt: u32) -> Duration {
+    Duration::from_millis(config.retry_backoff.saturating_mul(1 << attempt.min(16)))
+}
+
+/// signature_header 에 넣을 "sha256=<hex>"
+fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", encode_hex(&mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_body_with_hmac_sha256() {
+        assert_eq!(
+            signature("secret", br#"{"a":1}"#),
+            "sha256=aa9e2e3575f5d7098b6caccd790888c36d5fdb63342a73bada2d6a51747a8494"
+        );
+    }
+
+    #[test]
+    fn doubles_backoff_per_attempt() {
+        let config = WebhookConfig { retry_backoff: 500, ..WebhookConfig::default() };
+        let schedule: Vec<u64> = (0..4).map(|attempt| backoff(&config, attempt).as_millis() as u64).collect();
+        assert_eq!(schedule, [500, 1000, 2000, 4000]);
+    }
+
+    #[test]
+    fn drops_only_for_full_queue() {
+        let (slow, mut slow_receiver) = mpsc::channel(1);
+        let (fast, mut fast_receiver) = mpsc::channel(1);
+        let webhooks = WebhookSender { senders: vec![("slow".to_string(), slow), ("fast".to_string(), fast)] };
+
+        webhooks.enqueue(Bytes::from_static(b"first"));
+        assert_eq!(fast_receiver.try_recv().unwrap(), "first");
+        webhooks.enqueue(Bytes::from_static(b"second"));
+        assert_eq!(fast_receiver.try_recv().unwrap(), "second");
+
+        // 느린 수신 서버의 큐에는 첫 이벤트만 남는다.
+        assert_eq!(slow_receiver.try_recv().unwrap(), "first");
+        assert!(slow_receiver.try_recv().is_err());
+    }
+}